@@ -1,59 +1,78 @@
 pub(crate) mod buffer;
 pub(crate) mod cleanup;
+pub(crate) mod crash;
 pub(crate) mod editor;
 pub(crate) mod keymap;
+pub(crate) mod settings;
+pub(crate) mod swap;
 pub(crate) mod theme;
 pub(crate) mod ui;
 
 use crate::config::Config;
 use buffer::Buffer;
-use cleanup::{graceful_exit, CleanUnwrap};
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use editor::{actions::EditorAction, Editor};
 use ratatui::DefaultTerminal;
+use settings::Settings;
 use std::{
     io::{self, stdout},
     path::Path,
 };
-use theme::Theme;
+use swap::SwapFiles;
 
-pub fn initialize_buffers(config: &Config) -> Result<Vec<Buffer>, io::Error> {
-    if config.file_names.is_empty() {
-        return Ok(vec![Buffer::untitled()]);
-    }
+/// Opens the files given on the command line, returning the errors of those
+/// that could not be opened along with the buffers of the others.
+pub fn initialize_buffers(config: &Config) -> (Vec<Buffer>, Vec<String>) {
     let mut buffers: Vec<Buffer> = vec![];
+    let mut errors = vec![];
     for name in &config.file_names {
         let path = Path::new(name);
         if path.is_dir() {
-            graceful_exit(Some("Opening directories is not supported"))
+            errors.push(format!(
+                "Opening directories is not supported: {}",
+                path.display()
+            ));
+            continue;
         }
-        let name = path
-            .file_name()
-            .clean_expect("File name is not valid")
-            .to_owned();
-        if path.try_exists()? {
-            buffers.push(Buffer::load(name, path.into())?);
-        } else {
-            buffers.push(Buffer::empty(name, path.into()));
+        match Buffer::open(path) {
+            Ok(buffer) => buffers.push(buffer),
+            Err(err) => errors.push(format!("Could not open {}: {err}", path.display())),
         }
     }
-    Ok(buffers)
+    if buffers.is_empty() {
+        buffers.push(Buffer::untitled());
+    }
+    (buffers, errors)
 }
 
 pub fn run(terminal: &mut DefaultTerminal, config: Config) -> io::Result<()> {
-    let buffers = initialize_buffers(&config)?;
-    let mut editor = Editor::new(buffers, Theme::default());
+    let (buffers, mut errors) = initialize_buffers(&config);
+    let (settings, settings_errors) = Settings::load(&config);
+    errors.extend(
+        settings_errors
+            .into_iter()
+            .map(|err| format!("Error in configuration, ignored: {err}")),
+    );
+    let mut editor = Editor::new(buffers, settings);
+    for error in errors {
+        editor.show_error(error);
+    }
+    editor.set_swap_files(SwapFiles::in_state_dir());
+    editor.check_swap_files();
 
+    crash::install_panic_hook(crash::recovery_dir());
     enable_raw_mode()?;
     execute!(stdout(), EnterAlternateScreen)?;
     while editor.is_active() {
+        crash::track_buffers(editor.buffer_handles());
         terminal.draw(|frame| editor.draw(frame))?;
         editor.draw_cursor(terminal)?;
         editor.handle_input()?;
     }
+    editor.remove_swap_files();
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
@@ -1,20 +1,35 @@
 use std::{
+    cell::RefCell,
+    cmp::min,
     env,
     ffi::{OsStr, OsString},
     fs::{self, File},
-    io::{self, Write},
+    io::{self, ErrorKind},
     path::Path,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-use super::cleanup::CleanUnwrap;
+use ropey::{Rope, RopeSlice};
 
-#[derive(Debug, Clone)]
+use format::normalize_line_breaks;
+pub use format::{FileFormat, LineEnding};
+use history::{Change, History};
+use signs::Signs;
+
+mod file;
+mod format;
+pub mod graphemes;
+mod history;
+pub mod signs;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum HorizontalDirection {
     Forward,
     Backward,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerticalDirection {
     Up,
     Down,
@@ -28,20 +43,76 @@ pub enum RectilinearDirection {
     Left,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BufferPosition {
     pub line: usize,
     pub col: usize,
 }
 
+/// Lines inserted or removed by an edit: the `removed` lines following
+/// line `line` were replaced by `added` new ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineShift {
+    pub line: usize,
+    pub removed: usize,
+    pub added: usize,
+}
+
+/// The text of a buffer. Every line, including the last one, is stored with
+/// a terminating `\n`, so an empty rope is a buffer without any lines and
+/// `"\n"` is a buffer with a single empty line.
 #[derive(Debug, PartialEq)]
 pub struct Buffer {
+    /// Tells buffers apart for as long as the editor runs, even those
+    /// without a file.
+    id: usize,
     name: Option<OsString>,
     path: Option<OsString>,
-    pub lines: Vec<String>,
+    text: Rope,
+    history: History,
+    /// The line shifts since they were last taken, so that every window on
+    /// the buffer can keep its cursor on the same text.
+    line_shifts: Vec<LineShift>,
+    /// The state of the history when the text was last loaded or saved, or
+    /// `None` if the file no longer matches any state, such as after a
+    /// change of format. The buffer is modified unless the history is back
+    /// at that state.
+    saved_state: Option<usize>,
+    /// Counts the changes to the text, to tell whether it changed since some
+    /// earlier point.
+    version: usize,
+    format: FileFormat,
+    signs: Signs,
+    columns: RefCell<ColumnCache>,
+}
+
+/// Where the columns of the line last measured start, so that moving about
+/// a long line does not segment it again and again.
+#[derive(Debug, Default)]
+struct ColumnCache {
+    /// The version of the text and the line the columns are those of.
+    key: Option<(usize, usize)>,
+    starts: Rc<[usize]>,
+}
+
+impl PartialEq for ColumnCache {
+    /// Caches have no bearing on what a buffer holds.
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+/// A buffer id not given to any other buffer yet.
+fn next_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 impl Buffer {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     pub fn read_name(&self) -> Option<&OsStr> {
         match &self.name {
             Some(name) => Some(name),
@@ -49,6 +120,13 @@ impl Buffer {
         }
     }
 
+    /// The name shown for the buffer in tabs and lists.
+    pub fn display_name(&self) -> String {
+        self.name.as_ref().map_or("Untitled".to_owned(), |name| {
+            name.to_string_lossy().into_owned()
+        })
+    }
+
     pub fn set_name(&mut self, new_name: OsString) {
         self.name = Some(new_name);
     }
@@ -60,125 +138,468 @@ impl Buffer {
         }
     }
 
+    /// Whether the buffer's file is the one at `path`, even if one of them
+    /// is spelled differently.
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.path
+            .as_ref()
+            .is_some_and(|own| file::identity(Path::new(own)) == file::identity(path))
+    }
+
     pub fn set_path(&mut self, new_path: OsString) {
         let path = Path::new(&new_path);
-        let abs_path = if path.is_relative() {
-            let cwd = env::current_dir().clean_unwrap();
-            &cwd.join(path)
-        } else {
-            path
+        // Keep a relative path if the working directory is gone
+        let abs_path = match env::current_dir() {
+            Ok(cwd) if path.is_relative() => cwd.join(path),
+            _ => path.to_owned(),
         };
-        self.path = Some(abs_path.as_os_str().to_owned());
+        self.path = Some(abs_path.into_os_string());
     }
 
     pub fn untitled() -> Self {
         Buffer {
+            id: next_id(),
             name: None,
             path: None,
-            lines: vec![],
+            text: Rope::new(),
+            history: History::default(),
+            line_shifts: vec![],
+            saved_state: Some(0),
+            version: 0,
+            format: FileFormat::default(),
+            signs: Signs::default(),
+            columns: RefCell::default(),
         }
     }
 
-    pub fn save(&self) -> io::Result<()> {
-        let linebreak = "\n";
-        let path = self.path.as_ref().clean_unwrap();
-        let mut file = io::LineWriter::new(File::create(path)?);
-        for line in &self.lines {
-            file.write_all(line.as_bytes())?;
-            file.write_all(linebreak.as_bytes())?;
-        }
+    /// Writes the buffer to its file, see `write_atomically`.
+    pub fn save(&mut self, backup: bool) -> io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "No file name"));
+        };
+        let contents = self.format.encode(&self.text.to_string());
+        file::write_atomically(Path::new(path), &contents, backup)?;
+        self.saved_state = Some(self.history.state());
         Ok(())
     }
 
+    /// Writes the text to `path`, leaving the buffer's own file alone.
+    pub fn write_copy(&self, path: &Path) -> io::Result<()> {
+        let file = io::BufWriter::new(File::create(path)?);
+        self.text.write_to(file)
+    }
+
+    /// The text of the buffer, with every line ending in `\n`.
+    pub fn text(&self) -> &Rope {
+        &self.text
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.saved_state != Some(self.history.state())
+    }
+
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    pub fn format(&self) -> FileFormat {
+        self.format
+    }
+
+    /// The signs shown next to the lines of the buffer.
+    pub fn signs(&self) -> &Signs {
+        &self.signs
+    }
+
+    pub fn signs_mut(&mut self) -> &mut Signs {
+        &mut self.signs
+    }
+
+    /// Changes the line breaks the file is saved with.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.format.line_ending != line_ending {
+            self.format.line_ending = line_ending;
+            self.saved_state = None;
+            self.version += 1;
+        }
+    }
+
     pub fn load(name: OsString, path: OsString) -> io::Result<Self> {
-        let contents = fs::read_to_string(&path)?;
+        let (format, contents) = FileFormat::detect(&fs::read_to_string(&path)?);
         Ok(Buffer {
+            id: next_id(),
             name: Some(name),
             path: Some(path),
-            lines: contents.lines().map(|line| line.to_owned()).collect(),
+            text: Rope::from_str(&contents),
+            history: History::default(),
+            line_shifts: vec![],
+            saved_state: Some(0),
+            version: 0,
+            format,
+            signs: Signs::default(),
+            columns: RefCell::default(),
         })
     }
 
+    /// Loads the file at `path`, or creates an empty buffer for it if the file
+    /// does not exist yet.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or(io::Error::new(
+                ErrorKind::InvalidInput,
+                "File name is not valid",
+            ))?
+            .to_owned();
+        if path.try_exists()? {
+            Buffer::load(name, path.into())
+        } else {
+            Ok(Buffer::empty(name, path.into()))
+        }
+    }
+
     pub fn empty(name: OsString, path: OsString) -> Self {
         Buffer {
+            id: next_id(),
             name: Some(name),
             path: Some(path),
-            lines: vec![],
+            text: Rope::new(),
+            history: History::default(),
+            line_shifts: vec![],
+            saved_state: Some(0),
+            version: 0,
+            format: FileFormat::default(),
+            signs: Signs::default(),
+            columns: RefCell::default(),
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.text.len_chars() == 0
+    }
+
     pub fn lines_count(&self) -> usize {
-        self.lines.len()
+        self.text.len_lines() - 1
     }
 
     pub fn line_length(&self, index: usize) -> Option<usize> {
-        let count = self.lines_count();
-        if count == 0 || index >= count {
+        if index >= self.lines_count() {
             return None;
         }
-        Some(self.lines[index].chars().count())
+        Some(self.col_starts(index).len() - 1)
     }
 
-    pub fn insert_char(&mut self, c: char, pos: &BufferPosition) {
-        if self.lines.is_empty() {
-            self.lines.push(String::from(c));
-            return;
+    /// The char offsets within line `index` at which its columns start,
+    /// followed by its length in chars.
+    fn col_starts(&self, index: usize) -> Rc<[usize]> {
+        let key = Some((self.version, index));
+        let mut columns = self.columns.borrow_mut();
+        if columns.key != key {
+            let starts = match index < self.lines_count() {
+                true => {
+                    let line = self.text.line(index);
+                    graphemes::col_starts(line.slice(..line.len_chars() - 1))
+                }
+                false => vec![0],
+            };
+            *columns = ColumnCache {
+                key,
+                starts: starts.into(),
+            };
         }
-        let BufferPosition { line, col } = *pos;
-        let mut new_line: Vec<_> = self.lines[line].chars().collect();
-        new_line.insert(col, c);
-        self.lines[line] = String::from_iter(new_line);
+        Rc::clone(&columns.starts)
     }
 
-    pub fn remove_char(&mut self, pos: &BufferPosition) {
-        if self.lines.is_empty() {
+    /// The text of column `col` of line `index`, which must exist.
+    fn column(&self, index: usize, col: usize) -> RopeSlice<'_> {
+        let starts = self.col_starts(index);
+        let line_start = self.text.line_to_char(index);
+        self.text
+            .slice(line_start + starts[col]..line_start + starts[col + 1])
+    }
+
+    /// Returns the contents of a line without its line break.
+    pub fn line(&self, index: usize) -> String {
+        if index >= self.lines_count() {
+            return String::new();
+        }
+        let line = self.text.line(index);
+        line.slice(..line.len_chars() - 1).to_string()
+    }
+
+    /// Returns at most `len` columns of a line, starting from column `start`.
+    pub fn line_slice(&self, index: usize, start: usize, len: usize) -> String {
+        let starts = self.col_starts(index);
+        let cols = starts.len() - 1;
+        let line_start = self.text.line_to_char(index);
+        let range = starts[min(start, cols)]..starts[min(start.saturating_add(len), cols)];
+        self.text
+            .slice(line_start + range.start..line_start + range.end)
+            .to_string()
+    }
+
+    /// Returns every line of the buffer. Meant for tests and small buffers.
+    pub fn lines(&self) -> Vec<String> {
+        (0..self.lines_count()).map(|i| self.line(i)).collect()
+    }
+
+    /// Returns the column of the first character in the line that is not
+    /// whitespace, or the line length if there is none.
+    pub fn first_non_blank(&self, line: usize) -> usize {
+        let cols = self.line_length(line).unwrap_or(0);
+        (0..cols)
+            .take_while(|&col| self.column(line, col).chars().all(char::is_whitespace))
+            .count()
+    }
+
+    fn char_index(&self, pos: &BufferPosition) -> usize {
+        let starts = self.col_starts(pos.line);
+        let cols = starts.len() - 1;
+        let past_end = pos.col.saturating_sub(cols);
+        self.text.line_to_char(pos.line) + starts[min(pos.col, cols)] + past_end
+    }
+
+    /// Returns the first character of the column at `pos`, which is the
+    /// whole of it unless marks are combined with it.
+    pub fn char_at(&self, pos: &BufferPosition) -> Option<char> {
+        let cols = self.line_length(pos.line)?;
+        (pos.col < cols).then(|| self.column(pos.line, pos.col).char(0))
+    }
+
+    /// Returns the screen column, counted from the start of the line, at
+    /// which `pos` is drawn with tab stops every `tabstop` cells.
+    pub fn display_col(&self, pos: &BufferPosition, tabstop: usize) -> usize {
+        let cols = self.line_length(pos.line).unwrap_or(0);
+        let mut x = 0;
+        for col in 0..min(pos.col, cols) {
+            let column = self.column(pos.line, col);
+            x += match column.as_str() {
+                Some(grapheme) => graphemes::width(grapheme, x, tabstop),
+                None => graphemes::width(&column.to_string(), x, tabstop),
+            };
+        }
+        x + pos.col.saturating_sub(cols)
+    }
+
+    /// Returns the position right after `pos`, moving past the line break
+    /// if `pos` is at the end of a line.
+    pub fn position_after(&self, pos: &BufferPosition) -> BufferPosition {
+        let len = self.line_length(pos.line).unwrap_or(0);
+        if pos.col < len || pos.line + 1 >= self.lines_count() {
+            BufferPosition {
+                line: pos.line,
+                col: min(pos.col + 1, len),
+            }
+        } else {
+            BufferPosition {
+                line: pos.line + 1,
+                col: 0,
+            }
+        }
+    }
+
+    /// Returns the text between `start` (inclusive) and `end` (exclusive).
+    pub fn text_range(&self, start: &BufferPosition, end: &BufferPosition) -> String {
+        let range = self.char_index(start)..self.char_index(end);
+        self.text.slice(range).to_string()
+    }
+
+    /// Returns the lines from `first` to `last` (inclusive), each followed by
+    /// a line break.
+    pub fn lines_text(&self, first: usize, last: usize) -> String {
+        let range = self.text.line_to_char(first)..self.text.line_to_char(last + 1);
+        self.text.slice(range).to_string()
+    }
+
+    pub fn insert_text(&mut self, pos: &BufferPosition, text: &str) {
+        if text.is_empty() {
             return;
         }
-        let BufferPosition { line, col } = *pos;
-        if line < self.lines.len() {
-            let len = self.lines[line].chars().count();
-            if col >= len {
-                return;
+        let change = if self.is_empty() {
+            let mut text = text.to_owned();
+            if !text.ends_with('\n') {
+                text.push('\n');
             }
-            let new_line = self.lines[line]
-                .chars()
-                .enumerate()
-                .flat_map(|(i, c)| if i != col { Some(c) } else { None })
-                .collect();
-            self.lines[line] = new_line;
+            Change::insertion(0, text)
+        } else {
+            Change::insertion(self.char_index(pos), text.to_owned())
+        };
+        self.edit(change, pos);
+    }
+
+    /// Inserts whole lines, each terminated by a line break, before line
+    /// `index`.
+    pub fn insert_lines(&mut self, index: usize, text: &str) {
+        let at = self.text.line_to_char(index);
+        let pos = BufferPosition {
+            line: index,
+            col: 0,
+        };
+        self.edit(Change::insertion(at, text.to_owned()), &pos);
+    }
+
+    /// Removes the text between `start` (inclusive) and `end` (exclusive).
+    pub fn delete_range(&mut self, start: &BufferPosition, end: &BufferPosition) {
+        let removed = self.text_range(start, end);
+        if !removed.is_empty() {
+            self.edit(Change::removal(self.char_index(start), removed), start);
+        }
+    }
+
+    /// Replaces the text between `start` (inclusive) and `end` (exclusive)
+    /// with `text`.
+    pub fn replace_range(&mut self, start: &BufferPosition, end: &BufferPosition, text: &str) {
+        self.delete_range(start, end);
+        self.insert_text(start, text);
+    }
+
+    /// Replaces the whole text with `text`, as a single undo step.
+    pub fn replace_text(&mut self, text: String) {
+        let change = Change {
+            at: 0,
+            removed: self.text.to_string(),
+            inserted: normalize_line_breaks(&text),
+        };
+        self.edit(change, &BufferPosition::default());
+    }
+
+    /// Removes the lines from `first` to `last` (inclusive).
+    pub fn delete_lines(&mut self, first: usize, last: usize) {
+        let removed = self.lines_text(first, last);
+        let at = self.text.line_to_char(first);
+        let pos = BufferPosition {
+            line: first,
+            col: 0,
+        };
+        self.edit(Change::removal(at, removed), &pos);
+    }
+
+    fn edit(&mut self, change: Change, cursor: &BufferPosition) {
+        self.record_shift(change.at, &change.removed, &change.inserted);
+        change.apply(&mut self.text);
+        self.history.record(change, cursor);
+        self.version += 1;
+    }
+
+    fn record_shift(&mut self, at: usize, removed: &str, inserted: &str) {
+        let removed = removed.matches('\n').count();
+        let added = inserted.matches('\n').count();
+        if removed != added {
+            let line = self.text.char_to_line(at);
+            let shift = LineShift {
+                line,
+                removed,
+                added,
+            };
+            self.signs.shift(&shift, self.text.line_to_char(line) == at);
+            self.line_shifts.push(shift);
+        }
+    }
+
+    /// Returns the line shifts made by the edits since the last call.
+    pub fn take_line_shifts(&mut self) -> Vec<LineShift> {
+        std::mem::take(&mut self.line_shifts)
+    }
+
+    pub fn insert_char(&mut self, c: char, pos: &BufferPosition) {
+        let change = if self.is_empty() {
+            Change::insertion(0, format!("{c}\n"))
+        } else {
+            Change::insertion(self.char_index(pos), String::from(c))
+        };
+        self.edit(change, pos);
+    }
+
+    /// Removes the column at `pos`, with all the characters it is made of.
+    pub fn remove_char(&mut self, pos: &BufferPosition) {
+        if pos.col < self.line_length(pos.line).unwrap_or(0) {
+            let removed = self.column(pos.line, pos.col).to_string();
+            let change = Change::removal(self.char_index(pos), removed);
+            self.edit(change, pos);
         }
     }
 
     pub fn clear_line(&mut self, pos: &BufferPosition) {
-        if self.lines.is_empty() {
-            self.lines.push(String::new());
+        if self.is_empty() {
+            self.edit(Change::insertion(0, String::from("\n")), pos);
             return;
         }
-        let line = pos.line;
-        self.lines[line] = String::new();
+        let start = self.text.line_to_char(pos.line);
+        let removed = self.line(pos.line);
+        self.edit(Change::removal(start, removed), pos);
     }
 
     pub fn add_line(&mut self, index: usize, content: String) {
-        self.lines.insert(index, content);
+        let at = self.text.line_to_char(index);
+        let pos = BufferPosition {
+            line: index,
+            col: 0,
+        };
+        self.edit(Change::insertion(at, content + "\n"), &pos);
     }
 
     pub fn split_line(&mut self, pos: &BufferPosition) {
-        if self.lines.is_empty() {
-            self.lines.push(String::new());
-        }
-        let BufferPosition { line, col } = *pos;
-        let new_line: String = self.lines[line].chars().skip(col).collect();
-        self.add_line(line + 1, new_line);
-        let old_line: String = self.lines[line].chars().take(col).collect();
-        self.lines[line] = old_line;
+        let change = if self.is_empty() {
+            Change::insertion(0, String::from("\n\n"))
+        } else {
+            Change::insertion(self.char_index(pos), String::from("\n"))
+        };
+        self.edit(change, pos);
     }
 
     pub fn join_with_next_line(&mut self, line: usize) {
-        if line + 1 >= self.lines.len() {
+        if line + 1 >= self.lines_count() {
             return;
         }
-        let second_line = self.lines[line + 1].to_owned();
-        self.lines[line].push_str(&second_line);
-        self.lines.remove(line + 1);
+        let at = self.text.line_to_char(line + 1) - 1;
+        let col = self.line_length(line).unwrap_or(0);
+        self.edit(
+            Change::removal(at, String::from("\n")),
+            &BufferPosition { line, col },
+        );
+    }
+
+    /// Makes the following edits part of a single undo step, until
+    /// `end_undo_group` is called.
+    pub fn begin_undo_group(&mut self, cursor: &BufferPosition) {
+        self.history.begin_group(cursor);
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+    }
+
+    /// Marks the current end of the undo history, see `squash_undo_steps`.
+    pub fn undo_checkpoint(&self) -> usize {
+        self.history.checkpoint()
+    }
+
+    /// Merges every undo step recorded since `checkpoint` into one.
+    pub fn squash_undo_steps(&mut self, checkpoint: usize) {
+        self.history.squash_since(checkpoint);
+    }
+
+    /// Reverts the last undo step and returns the cursor position it was
+    /// recorded with.
+    pub fn undo(&mut self) -> Option<BufferPosition> {
+        let step = self.history.pop_undo()?;
+        for change in step.changes.iter().rev() {
+            self.record_shift(change.at, &change.inserted, &change.removed);
+            change.revert(&mut self.text);
+        }
+        self.version += 1;
+        Some(step.cursor)
+    }
+
+    /// Reapplies the last undone step and returns the cursor position it was
+    /// recorded with.
+    pub fn redo(&mut self) -> Option<BufferPosition> {
+        let step = self.history.pop_redo()?;
+        for change in step.changes.iter() {
+            self.record_shift(change.at, &change.removed, &change.inserted);
+            change.apply(&mut self.text);
+        }
+        self.version += 1;
+        Some(step.cursor)
     }
 }
@@ -0,0 +1,145 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, Metadata},
+    io::{self, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Gives up on resolving symbolic links that point at each other.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// The path that names the same file as `path` however it was spelled: with
+/// symbolic links resolved for an existing file, and made absolute for one
+/// that doesn't exist yet.
+pub fn identity(path: &Path) -> PathBuf {
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_owned())
+}
+
+/// Writes `contents` to `path` without ever leaving a partly written file: the
+/// text goes to a temporary file next to the target, which then replaces
+/// it. Symbolic links are followed so that their target is the one written,
+/// and the target keeps its permissions and, where allowed, its owner. With
+/// `backup`, the previous contents are first copied to a file named after
+/// the target followed by `~`.
+///
+/// Replacing the target would split it from its other hard links, and needs
+/// a directory that new files can be made in, so failing either the file is
+/// overwritten in place instead.
+pub fn write_atomically(path: &Path, contents: &str, backup: bool) -> io::Result<()> {
+    let target = resolve_symlinks(path)?;
+    let metadata = match fs::metadata(&target) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    if backup && metadata.is_some() {
+        fs::copy(&target, with_suffix(&target, "~"))?;
+    }
+    if metadata.as_ref().is_some_and(has_other_links) {
+        return write_in_place(&target, contents);
+    }
+    let temp = with_suffix(&target, &format!(".{}.tmp", process::id()));
+    let written =
+        write_temp(&temp, contents, metadata.as_ref()).and_then(|()| fs::rename(&temp, &target));
+    match written {
+        Ok(()) => sync_dir(&target),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            return match err.kind() {
+                ErrorKind::PermissionDenied if metadata.is_some() => {
+                    write_in_place(&target, contents)
+                }
+                _ => Err(err),
+            };
+        }
+    }
+    Ok(())
+}
+
+fn write_temp(temp: &Path, contents: &str, metadata: Option<&Metadata>) -> io::Result<()> {
+    let file = File::options().write(true).create_new(true).open(temp)?;
+    if let Some(metadata) = metadata {
+        file.set_permissions(metadata.permissions())?;
+        copy_owner(&file, metadata);
+    }
+    write_to(file, contents)
+}
+
+fn write_in_place(target: &Path, contents: &str) -> io::Result<()> {
+    let file = File::options().write(true).truncate(true).open(target)?;
+    write_to(file, contents)
+}
+
+fn write_to(file: File, contents: &str) -> io::Result<()> {
+    let mut writer = BufWriter::new(file);
+    writer.write_all(contents.as_bytes())?;
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()
+}
+
+#[cfg(unix)]
+fn has_other_links(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn has_other_links(_metadata: &Metadata) -> bool {
+    false
+}
+
+/// Gives the new file the owner of the one it replaces. Only the
+/// superuser may give files away, so failing is expected and ignored.
+#[cfg(unix)]
+fn copy_owner(file: &File, metadata: &Metadata) {
+    use std::os::unix::fs::{fchown, MetadataExt};
+    let _ = fchown(file, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn copy_owner(_file: &File, _metadata: &Metadata) {}
+
+/// Makes the rename itself durable.
+#[cfg(unix)]
+fn sync_dir(target: &Path) {
+    if let Some(dir) = target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        let _ = File::open(dir).and_then(|dir| dir.sync_all());
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_target: &Path) {}
+
+/// Follows `path` through symbolic links to the file they point at, which
+/// may not exist yet.
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_owned();
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                let link = fs::read_link(&path)?;
+                // Relative links are relative to the directory of the link
+                path = match path.parent() {
+                    Some(dir) => dir.join(link),
+                    None => link,
+                };
+            }
+            _ => return Ok(path),
+        }
+    }
+    Err(io::Error::new(
+        ErrorKind::InvalidInput,
+        "Too many levels of symbolic links",
+    ))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
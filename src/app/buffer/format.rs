@@ -0,0 +1,115 @@
+use std::fmt::Display;
+
+const BOM: char = '\u{feff}';
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// How the text of a buffer is laid out in its file, kept from loading to
+/// saving so that files are written back the way they were found. The
+/// buffer itself always ends every line with `\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    pub line_ending: LineEnding,
+    /// Whether the last line ends with a line break.
+    pub final_newline: bool,
+    /// Whether the file starts with a UTF-8 byte order mark.
+    pub bom: bool,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat {
+            line_ending: LineEnding::Lf,
+            final_newline: true,
+            bom: false,
+        }
+    }
+}
+
+impl FileFormat {
+    /// Finds out the format of the contents of a file, and returns it with
+    /// the text as the buffer keeps it. The line ending is CRLF only if every
+    /// line break is one: a file that mixes them keeps its carriage returns
+    /// as text, so that saving it writes them back as they were.
+    pub fn detect(contents: &str) -> (FileFormat, String) {
+        let (bom, contents) = match contents.strip_prefix(BOM) {
+            Some(rest) => (true, rest),
+            None => (false, contents),
+        };
+        let line_breaks = contents.matches('\n').count();
+        let line_ending = match contents.matches("\r\n").count() {
+            crlf if crlf > 0 && crlf == line_breaks => LineEnding::Crlf,
+            _ => LineEnding::Lf,
+        };
+        let format = FileFormat {
+            line_ending,
+            final_newline: contents.is_empty() || contents.ends_with('\n'),
+            bom,
+        };
+        let text = match line_ending {
+            LineEnding::Crlf => normalize_line_breaks(contents),
+            LineEnding::Lf => with_final_newline(contents.to_owned()),
+        };
+        (format, text)
+    }
+
+    /// Turns text as the buffer keeps it into the contents of a file in
+    /// this format.
+    pub fn encode(&self, text: &str) -> String {
+        let text = match self.final_newline {
+            true => text,
+            false => text.strip_suffix('\n').unwrap_or(text),
+        };
+        let mut contents = String::with_capacity(text.len() + text.len() / 32 + 3);
+        if self.bom {
+            contents.push(BOM);
+        }
+        match self.line_ending {
+            LineEnding::Lf => contents.push_str(text),
+            LineEnding::Crlf => contents.push_str(&text.replace('\n', "\r\n")),
+        }
+        contents
+    }
+}
+
+impl Display for FileFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line_ending {
+            LineEnding::Lf => write!(f, "LF")?,
+            LineEnding::Crlf => write!(f, "CRLF")?,
+        }
+        if self.bom {
+            write!(f, " BOM")?;
+        }
+        if !self.final_newline {
+            write!(f, " noeol")?;
+        }
+        Ok(())
+    }
+}
+
+/// Turns CRLF line breaks into LF and ends the last line with one.
+pub fn normalize_line_breaks(text: &str) -> String {
+    with_final_newline(text.replace(LineEnding::Crlf.as_str(), LineEnding::Lf.as_str()))
+}
+
+fn with_final_newline(mut text: String) -> String {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
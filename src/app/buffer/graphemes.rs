@@ -0,0 +1,140 @@
+//! Columns in a line count grapheme clusters, what a reader takes for a
+//! single character however many code points make it up. On screen, each
+//! one takes as many cells as it is wide, and tabs reach the next tab stop.
+
+use std::borrow::Cow;
+
+use ropey::RopeSlice;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+
+/// Drawn under marks that have nothing to combine with, so they are visible.
+const DOTTED_CIRCLE: char = '\u{25cc}';
+
+/// The number of columns in `line`.
+pub fn count(line: &str) -> usize {
+    line.graphemes(true).count()
+}
+
+/// The byte offset at which column `col` of `line` starts, or the length of
+/// the line if it is past the end.
+pub fn byte_offset(line: &str, col: usize) -> usize {
+    line.grapheme_indices(true)
+        .nth(col)
+        .map_or(line.len(), |(offset, _)| offset)
+}
+
+/// The column that follows the text of `line` up to byte `byte`.
+pub fn col_after_byte(line: &str, byte: usize) -> usize {
+    count(&line[..byte])
+}
+
+/// The char offsets at which the columns of `line` start, followed by the
+/// length of the line in chars. Unlike the functions taking a `&str`, this
+/// goes through the rope a chunk at a time instead of copying the line.
+pub fn col_starts(line: RopeSlice) -> Vec<usize> {
+    let len = line.len_bytes();
+    let mut starts = vec![];
+    let mut cursor = GraphemeCursor::new(0, len, true);
+    let mut chunks = line.chunks();
+    let mut chunk = chunks.next().unwrap_or("");
+    let mut chunk_start = 0;
+    let mut byte = 0;
+    while byte < len {
+        starts.push(line.byte_to_char(byte));
+        byte = loop {
+            match cursor.next_boundary(chunk, chunk_start) {
+                Ok(next) => break next.unwrap_or(len),
+                Err(GraphemeIncomplete::NextChunk) => {
+                    chunk_start += chunk.len();
+                    chunk = chunks.next().unwrap_or("");
+                }
+                Err(GraphemeIncomplete::PreContext(end)) => {
+                    let (context, context_start, _, _) = line.chunk_at_byte(end - 1);
+                    cursor.provide_context(context, context_start);
+                }
+                Err(_) => break len,
+            }
+        };
+    }
+    starts.push(line.len_chars());
+    starts
+}
+
+/// The first character of every column of `line`, the one motions look at.
+pub fn base_chars(line: &str) -> Vec<char> {
+    line.graphemes(true)
+        .filter_map(|grapheme| grapheme.chars().next())
+        .collect()
+}
+
+/// What is drawn for `grapheme`: control characters are shown in caret
+/// notation, as in `^M`, and lone marks over a dotted circle.
+pub fn displayed(grapheme: &str) -> Cow<'_, str> {
+    let mut chars = grapheme.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ '\0'..='\x1f'), None) => Cow::Owned(format!("^{}", (c as u8 + b'@') as char)),
+        (Some('\x7f'), None) => Cow::Borrowed("^?"),
+        (Some(c), None) if c.is_control() => Cow::Owned(format!("<{:02x}>", c as u32)),
+        _ if grapheme.width() == 0 => Cow::Owned(format!("{DOTTED_CIRCLE}{grapheme}")),
+        _ => Cow::Borrowed(grapheme),
+    }
+}
+
+/// The number of screen cells `grapheme` takes when drawn from screen
+/// column `x`.
+pub fn width(grapheme: &str, x: usize, tabstop: usize) -> usize {
+    match grapheme {
+        "\t" => tabstop - x % tabstop,
+        _ => displayed(grapheme).width(),
+    }
+}
+
+/// The screen column, counted from the start of `line`, at which column `col`
+/// is drawn. Columns past the end of the line take a cell each.
+pub fn display_col(line: &str, col: usize, tabstop: usize) -> usize {
+    let mut cols = 0;
+    let mut x = 0;
+    for grapheme in line.graphemes(true).take(col) {
+        cols += 1;
+        x += width(grapheme, x, tabstop);
+    }
+    x + col - cols
+}
+
+/// The column of `line` drawn over screen column `x`, or the one after the
+/// end of the line if `x` is past it.
+pub fn col_at_display(line: &str, x: usize, tabstop: usize) -> usize {
+    let mut end = 0;
+    for (col, grapheme) in line.graphemes(true).enumerate() {
+        end += width(grapheme, end, tabstop);
+        if x < end {
+            return col;
+        }
+    }
+    count(line)
+}
+
+/// The part of `line` drawn from screen column `left` on, `cells` wide and
+/// padded with spaces. Tabs are drawn as spaces, and wide characters cut by
+/// either edge are left out.
+pub fn visible_text(line: &str, left: usize, cells: usize, tabstop: usize) -> String {
+    let right = left + cells;
+    let mut text = String::with_capacity(cells);
+    let mut x = 0;
+    for grapheme in line.graphemes(true) {
+        let end = x + width(grapheme, x, tabstop);
+        if end > right {
+            break;
+        }
+        if x >= left && grapheme != "\t" {
+            text.push_str(&displayed(grapheme));
+        } else if end > left {
+            text.extend(std::iter::repeat_n(' ', end - x.max(left)));
+        }
+        x = end;
+    }
+    let drawn = x.saturating_sub(left);
+    text.extend(std::iter::repeat_n(' ', cells - drawn));
+    text
+}
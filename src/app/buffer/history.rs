@@ -0,0 +1,146 @@
+use ropey::Rope;
+
+use super::BufferPosition;
+
+/// A single reversible edit: the text removed at char index `at`, and the
+/// text inserted in its place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub at: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl Change {
+    pub fn insertion(at: usize, inserted: String) -> Self {
+        Change {
+            at,
+            removed: String::new(),
+            inserted,
+        }
+    }
+
+    pub fn removal(at: usize, removed: String) -> Self {
+        Change {
+            at,
+            removed,
+            inserted: String::new(),
+        }
+    }
+
+    pub fn apply(&self, text: &mut Rope) {
+        let end = self.at + self.removed.chars().count();
+        text.remove(self.at..end);
+        text.insert(self.at, &self.inserted);
+    }
+
+    pub fn revert(&self, text: &mut Rope) {
+        let end = self.at + self.inserted.chars().count();
+        text.remove(self.at..end);
+        text.insert(self.at, &self.removed);
+    }
+}
+
+/// A group of changes that is undone and redone as a whole, along with the
+/// cursor position to restore afterwards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoStep {
+    pub changes: Vec<Change>,
+    pub cursor: BufferPosition,
+    /// Tells the step apart from every other step of the history.
+    id: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct History {
+    undo_stack: Vec<UndoStep>,
+    redo_stack: Vec<UndoStep>,
+    open_group: Option<UndoStep>,
+    /// The id of the last step recorded, which the next one follows.
+    last_id: usize,
+}
+
+impl History {
+    /// Starts collecting changes into a single undo step. Does nothing if a
+    /// group is already open.
+    pub fn begin_group(&mut self, cursor: &BufferPosition) {
+        if self.open_group.is_none() {
+            self.open_group = Some(UndoStep {
+                changes: vec![],
+                cursor: cursor.clone(),
+                id: self.next_id(),
+            });
+        }
+    }
+
+    pub fn end_group(&mut self) {
+        if let Some(step) = self.open_group.take() {
+            if !step.changes.is_empty() {
+                self.undo_stack.push(step);
+            }
+        }
+    }
+
+    pub fn record(&mut self, change: Change, cursor: &BufferPosition) {
+        self.redo_stack.clear();
+        match self.open_group {
+            Some(ref mut step) => step.changes.push(change),
+            None => {
+                let id = self.next_id();
+                self.undo_stack.push(UndoStep {
+                    changes: vec![change],
+                    cursor: cursor.clone(),
+                    id,
+                })
+            }
+        }
+    }
+
+    fn next_id(&mut self) -> usize {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Identifies the text the history has led to: the id of the last step
+    /// applied, or 0 before any. Undoing back to a step gives its state again.
+    pub fn state(&self) -> usize {
+        self.open_group
+            .as_ref()
+            .filter(|step| !step.changes.is_empty())
+            .or(self.undo_stack.last())
+            .map_or(0, |step| step.id)
+    }
+
+    pub fn checkpoint(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    pub fn squash_since(&mut self, checkpoint: usize) {
+        if self.undo_stack.len() <= checkpoint + 1 {
+            return;
+        }
+        let mut steps = self.undo_stack.split_off(checkpoint).into_iter();
+        let Some(mut squashed) = steps.next() else {
+            return;
+        };
+        for step in steps {
+            squashed.changes.extend(step.changes);
+            squashed.id = step.id;
+        }
+        self.undo_stack.push(squashed);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<UndoStep> {
+        self.end_group();
+        let step = self.undo_stack.pop()?;
+        self.redo_stack.push(step.clone());
+        Some(step)
+    }
+
+    pub fn pop_redo(&mut self) -> Option<UndoStep> {
+        self.end_group();
+        let step = self.redo_stack.pop()?;
+        self.undo_stack.push(step.clone());
+        Some(step)
+    }
+}
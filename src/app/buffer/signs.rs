@@ -0,0 +1,115 @@
+//! Signs are marks drawn in the sign column next to the lines of a buffer,
+//! such as bookmarks. Each is placed by a group, the part of the editor it
+//! belongs to, which can move or clear its own signs without touching those
+//! of other groups. Of the signs on a line, the one with the highest
+//! priority is drawn.
+
+use std::collections::BTreeMap;
+
+use ratatui::style::Style;
+
+use super::LineShift;
+
+/// The group bookmarks are placed in.
+pub const BOOKMARKS: &str = "bookmarks";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sign {
+    /// What is drawn, at most two cells wide.
+    pub text: String,
+    pub style: Style,
+    pub priority: u8,
+}
+
+impl Sign {
+    pub fn new(text: &str, style: Style, priority: u8) -> Self {
+        Sign {
+            text: text.to_owned(),
+            style,
+            priority,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Signs {
+    /// The signs on each line, along with the groups that placed them, in
+    /// the order they were placed.
+    lines: BTreeMap<usize, Vec<(&'static str, Sign)>>,
+}
+
+impl Signs {
+    /// Places `sign` on `line`, replacing the one `group` had there.
+    pub fn place(&mut self, group: &'static str, line: usize, sign: Sign) {
+        self.remove(group, line);
+        self.lines.entry(line).or_default().push((group, sign));
+    }
+
+    /// Removes the sign `group` had on `line`, returning whether there was one.
+    pub fn remove(&mut self, group: &'static str, line: usize) -> bool {
+        let Some(signs) = self.lines.get_mut(&line) else {
+            return false;
+        };
+        let count = signs.len();
+        signs.retain(|&(placed_by, _)| placed_by != group);
+        let removed = signs.len() < count;
+        if signs.is_empty() {
+            self.lines.remove(&line);
+        }
+        removed
+    }
+
+    /// Removes every sign placed by `group`.
+    pub fn clear(&mut self, group: &'static str) {
+        self.lines.retain(|_, signs| {
+            signs.retain(|&(placed_by, _)| placed_by != group);
+            !signs.is_empty()
+        });
+    }
+
+    /// The sign drawn for `line`: the one with the highest priority, or the
+    /// latest placed of those sharing it.
+    pub fn top(&self, line: usize) -> Option<&Sign> {
+        self.lines
+            .get(&line)?
+            .iter()
+            .map(|(_, sign)| sign)
+            .rev()
+            .max_by_key(|sign| sign.priority)
+    }
+
+    /// The lines `group` placed signs on, in order.
+    pub fn lines(&self, group: &'static str) -> impl Iterator<Item = usize> + '_ {
+        self.lines
+            .iter()
+            .filter(move |(_, signs)| signs.iter().any(|&(placed_by, _)| placed_by == group))
+            .map(|(&line, _)| line)
+    }
+
+    /// Keeps signs on the same text after lines were inserted or removed.
+    /// `at_line_start` tells whether the edit started at the start of its
+    /// line, which then moves along with the lines after it. Signs on lines
+    /// removed as a whole go with them, and of the signs of lines joined
+    /// together, the first one of each group is kept.
+    pub fn shift(&mut self, shift: &LineShift, at_line_start: bool) {
+        let shifted = |line: usize| match line {
+            line if line < shift.line => Some(line),
+            line if line == shift.line && !at_line_start => Some(line),
+            line if line < shift.line + shift.removed => None,
+            line => Some(line - shift.removed + shift.added),
+        };
+        let mut lines: BTreeMap<usize, Vec<(&'static str, Sign)>> = BTreeMap::new();
+        for (line, signs) in std::mem::take(&mut self.lines) {
+            let Some(line) = shifted(line) else {
+                continue;
+            };
+            let placed = lines.entry(line).or_default();
+            for (group, sign) in signs {
+                if placed.iter().all(|&(placed_by, _)| placed_by != group) {
+                    placed.push((group, sign));
+                }
+            }
+        }
+        self.lines = lines;
+    }
+}
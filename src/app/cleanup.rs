@@ -1,37 +1,33 @@
-use std::{fmt::Debug, io::stdout, process::exit};
+use std::{
+    fmt::Debug,
+    io::{self, stdout},
+};
 
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
 
-const GRACEFUL_EXIT_CODE: i32 = 1;
-
+/// Gives up on a state the editor cannot recover from; anything else should
+/// be reported to the user instead. This panics rather than exiting, so that
+/// the panic hook restores the terminal and saves the unsaved buffers.
 pub fn graceful_exit(msg: Option<&str>) -> ! {
-    execute!(stdout(), LeaveAlternateScreen).expect("cleanup failed during graceful exit");
-    disable_raw_mode().expect("cleanup failed during graceful exit");
+    panic!("{}", msg.unwrap_or("the editor cannot go on"));
+}
+
+/// Leaves the alternate screen and raw mode.
+pub fn restore_terminal() -> io::Result<()> {
+    execute!(stdout(), LeaveAlternateScreen)?;
+    disable_raw_mode()?;
     ratatui::restore();
-    if let Some(msg) = msg {
-        eprintln!("{msg}");
-    }
-    exit(GRACEFUL_EXIT_CODE);
+    Ok(())
 }
 
 pub trait CleanUnwrap<T> {
-    fn clean_unwrap(self) -> T;
     fn clean_expect(self, msg: &str) -> T;
 }
 
 impl<T> CleanUnwrap<T> for Option<T> {
-    fn clean_unwrap(self) -> T {
-        match self {
-            Some(x) => x,
-            None => {
-                graceful_exit(None);
-            }
-        }
-    }
-
     fn clean_expect(self, msg: &str) -> T {
         match self {
             Some(x) => x,
@@ -46,13 +42,6 @@ impl<T, E> CleanUnwrap<T> for Result<T, E>
 where
     E: Debug,
 {
-    fn clean_unwrap(self) -> T {
-        match self {
-            Ok(x) => x,
-            Err(e) => graceful_exit(Some(&format!("{e:?}"))),
-        }
-    }
-
     fn clean_expect(self, msg: &str) -> T {
         match self {
             Ok(x) => x,
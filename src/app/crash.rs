@@ -0,0 +1,140 @@
+use std::{
+    cell::RefCell,
+    fs, io, panic,
+    path::{Path, PathBuf},
+    rc::Weak,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ropey::Rope;
+
+use crate::config::state_dir;
+
+use super::{buffer::Buffer, cleanup::restore_terminal};
+
+/// Where modified buffers are written when the editor crashes, inside the
+/// state directory.
+const RECOVERY_DIR_NAME: &str = "recovery";
+
+thread_local! {
+    /// The buffers of the running editor, for the panic hook to save.
+    static TRACKED_BUFFERS: RefCell<Vec<TrackedBuffer>> = const { RefCell::new(vec![]) };
+}
+
+/// A buffer for the panic hook to save, along with a copy of it from before
+/// the key being handled: a panic in the middle of an edit leaves the buffer
+/// itself borrowed, and it is likely the one with unsaved work.
+#[derive(Clone)]
+pub struct TrackedBuffer {
+    buffer: Weak<RefCell<Buffer>>,
+    snapshot: Option<Snapshot>,
+}
+
+#[derive(Clone)]
+struct Snapshot {
+    name: String,
+    text: Rope,
+    modified: bool,
+}
+
+impl TrackedBuffer {
+    /// Tracks `buffer`, copying it as it is now. Copying the text of a rope
+    /// is cheap, as it shares its contents until they change.
+    pub fn new(buffer: Weak<RefCell<Buffer>>) -> Self {
+        let snapshot = buffer.upgrade().and_then(|buffer| {
+            let buffer = buffer.try_borrow().ok()?;
+            Some(Snapshot {
+                name: buffer.display_name(),
+                text: buffer.text().clone(),
+                modified: buffer.is_modified(),
+            })
+        });
+        TrackedBuffer { buffer, snapshot }
+    }
+}
+
+/// Makes a panic restore the terminal and write the modified buffers to
+/// `dir` before the panic message is printed.
+pub fn install_panic_hook(dir: PathBuf) {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        let buffers = TRACKED_BUFFERS
+            .try_with(|tracked| tracked.try_borrow().map(|b| b.clone()).unwrap_or_default())
+            .unwrap_or_default();
+        let dumped = dump_buffers(&buffers, &dir);
+        default_hook(info);
+        report_dump(&dumped);
+    }));
+}
+
+/// Remembers the buffers to save if the editor panics, as they are before
+/// the next key is handled.
+pub fn track_buffers(buffers: Vec<Weak<RefCell<Buffer>>>) {
+    TRACKED_BUFFERS.with(|tracked| {
+        if let Ok(mut tracked) = tracked.try_borrow_mut() {
+            *tracked = buffers.into_iter().map(TrackedBuffer::new).collect();
+        }
+    });
+}
+
+pub fn recovery_dir() -> PathBuf {
+    state_dir()
+        .unwrap_or_else(|| std::env::temp_dir().join("minimodal"))
+        .join(RECOVERY_DIR_NAME)
+}
+
+/// Writes every modified buffer to a new file in `dir`, returning the name
+/// of each buffer with where it went, or why it could not be written. A
+/// buffer left in the middle of an edit is written as it was before it.
+pub fn dump_buffers(buffers: &[TrackedBuffer], dir: &Path) -> Vec<(String, io::Result<PathBuf>)> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let mut dumped = vec![];
+    for (i, tracked) in buffers.iter().enumerate() {
+        let Some(buffer) = tracked.buffer.upgrade() else {
+            continue;
+        };
+        let path = |name: &str| dir.join(format!("{timestamp}-{}-{name}", i + 1));
+        let write = |path: PathBuf, text: &Rope| {
+            fs::create_dir_all(dir)
+                .and_then(|()| text.write_to(io::BufWriter::new(fs::File::create(&path)?)))
+                .map(|()| path)
+        };
+        let (name, written) = match (buffer.try_borrow(), &tracked.snapshot) {
+            (Ok(buffer), _) if !buffer.is_modified() => continue,
+            (Ok(buffer), _) => {
+                let name = buffer.display_name();
+                let written = write(path(&name), buffer.text());
+                (name, written)
+            }
+            (Err(_), Some(snapshot)) if !snapshot.modified => continue,
+            (Err(_), Some(snapshot)) => (
+                format!("{} (before the last key)", snapshot.name),
+                write(path(&snapshot.name), &snapshot.text),
+            ),
+            (Err(_), None) => (
+                format!("buffer {}", i + 1),
+                Err(io::Error::other(
+                    "it was being edited when the editor crashed",
+                )),
+            ),
+        };
+        dumped.push((name, written));
+    }
+    dumped
+}
+
+fn report_dump(dumped: &[(String, io::Result<PathBuf>)]) {
+    if dumped.is_empty() {
+        return;
+    }
+    eprintln!("minimodal crashed, the unsaved buffers were written to:");
+    for (name, written) in dumped {
+        match written {
+            Ok(path) => eprintln!("  {name}: {}", path.display()),
+            Err(err) => eprintln!("  {name}: could not be written: {err}"),
+        }
+    }
+}
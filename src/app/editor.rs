@@ -1,12 +1,14 @@
 use std::{
-    cell::{Ref, RefMut},
+    cell::{Ref, RefCell, RefMut},
+    cmp::min,
     io::{self, stdout},
-    rc::Rc,
+    path::PathBuf,
+    rc::{Rc, Weak},
 };
 
 use crossterm::{
     cursor::SetCursorStyle,
-    event::{self, Event, KeyEvent, KeyEventKind},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
 };
 use ratatui::{
@@ -16,14 +18,24 @@ use ratatui::{
     DefaultTerminal, Frame,
 };
 
+use actions::EditorAction;
+use motions::{CharSearch, FindKind, Motion};
+use operators::{Operator, Register};
+use search::{MatchCache, PendingSearch, Search};
+use substitute::PendingSubstitution;
+
 use crate::app::{
-    buffer::{Buffer, BufferPosition},
-    cleanup::{graceful_exit, CleanUnwrap},
+    buffer::{Buffer, BufferPosition, HorizontalDirection},
+    cleanup::CleanUnwrap,
     keymap::KeyMap,
+    settings::{Settings, WindowSettings},
+    swap::SwapFiles,
     theme::Theme,
     ui::{
+        command_line::CommandLine,
         floating_window::FloatingContent,
-        leader_menu::{LeaderMenu, SubMenu},
+        leader_menu::{KeyHint, LeaderMenu, SubMenu},
+        notification::{Notification, Severity},
         status_bar::StatusBar,
         text_window::{selection::Selection, TextWindowState},
         Tab, TabState,
@@ -32,12 +44,21 @@ use crate::app::{
 
 mod action_handlers;
 pub mod actions;
+mod bookmarks;
+mod buffers;
+pub mod commands;
+pub mod motions;
+pub mod operators;
+mod recovery;
+pub mod search;
+pub mod substitute;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
     Normal,
     Command,
     Insert,
+    OperatorPending(Operator),
     Visual(Selection),
 }
 
@@ -56,37 +77,104 @@ pub struct Editor {
     mode: Mode,
     tabs: Vec<Tab>,
     tab_states: Vec<TabState>,
+    /// Every open buffer, whether a window shows it or not.
+    buffers: Vec<Rc<RefCell<Buffer>>>,
     theme: Rc<Theme>,
     lower_menu: Option<SubMenu>,
+    /// What the theme menu lists, gathered when it is opened.
+    theme_choices: Vec<String>,
     floating_window: Option<Box<dyn FloatingContent>>,
+    command_line: CommandLine,
+    /// The notifications shown so far, oldest first.
+    messages: Vec<Notification>,
+    register: Option<Register>,
+    pending_char_search: Option<(FindKind, HorizontalDirection, Option<usize>)>,
+    last_char_search: Option<CharSearch>,
+    count: Option<usize>,
+    operator_count: Option<usize>,
+    search: Option<Search>,
+    pending_search: Option<PendingSearch>,
+    /// Whether the matches of the last search are highlighted.
+    highlight_search: bool,
+    match_cache: RefCell<MatchCache>,
+    /// A `:s///c` waiting for each match to be confirmed.
+    pending_substitution: Option<PendingSubstitution>,
+    swap_files: SwapFiles,
+    /// Swap files found for open buffers, waiting to be offered for
+    /// recovery, with the index of their buffer.
+    pending_recoveries: Vec<(usize, PathBuf)>,
+    shiftwidth: usize,
+    expandtab: bool,
+    /// Whether saving keeps a backup of the previous contents.
+    backup: bool,
+    window_settings: WindowSettings,
 }
 
 const TABLINE_HEIGHT: u16 = 1;
 const STATUS_LINE_HEIGHT: u16 = 1;
 const FLOATING_WINDOW_SPACE_FRACTION: f64 = 0.8;
+/// The keys picking each theme in the theme menu, in order.
+const THEME_MENU_KEYS: &str = "123456789abcdefghijklmnopqrstuvwxyz";
+/// Keeps a mistyped count from stalling the editor on repeated actions.
+const MAX_COUNT: usize = 99_999;
+/// How many notifications the message history keeps.
+const MAX_MESSAGES: usize = 200;
 
 impl Editor {
-    pub fn new(buffers: Vec<Buffer>, theme_struct: Theme) -> Self {
-        let theme_rc = Rc::new(theme_struct);
+    pub fn new(buffers: Vec<Buffer>, settings: Settings) -> Self {
+        let theme_rc = Rc::new(settings.theme);
+        let window_settings = settings.window;
+        let buffers: Vec<_> = buffers
+            .into_iter()
+            .map(|buffer| Rc::new(RefCell::new(buffer)))
+            .collect();
         Editor {
             active: true,
-            keymap: KeyMap::default(),
+            keymap: settings.keymap,
             current_tab: 0,
             mode: Mode::Normal,
             theme: Rc::clone(&theme_rc),
             tabs: buffers.iter().map(|_| Tab::new()).collect(),
             tab_states: buffers
-                .into_iter()
-                .map(|buffer| TabState::new(buffer, Rc::downgrade(&theme_rc), Mode::Normal))
+                .iter()
+                .map(|buffer| {
+                    TabState::new(
+                        Rc::downgrade(buffer),
+                        Rc::downgrade(&theme_rc),
+                        Mode::Normal,
+                        window_settings.clone(),
+                    )
+                })
                 .collect(),
+            buffers,
+            shiftwidth: settings.shiftwidth,
+            expandtab: settings.expandtab,
+            backup: settings.backup,
+            window_settings,
             lower_menu: None,
+            theme_choices: vec![],
             floating_window: None,
+            command_line: CommandLine::default(),
+            messages: vec![],
+            register: None,
+            pending_char_search: None,
+            last_char_search: None,
+            count: None,
+            operator_count: None,
+            search: None,
+            pending_search: None,
+            highlight_search: false,
+            match_cache: RefCell::default(),
+            pending_substitution: None,
+            swap_files: SwapFiles::default(),
+            pending_recoveries: vec![],
         }
     }
 
     pub fn draw(&mut self, frame: &mut Frame) {
         // Must be the first step to ensure that other widgets are in the right
         // mode
+        self.sync_windows();
         self.propagate_mode();
 
         let (layout, indices) = match self.lower_menu {
@@ -111,7 +199,8 @@ impl Editor {
             );
 
             frame.render_widget(Clear, menu_area);
-            frame.render_widget(LeaderMenu::new(sub_menu, &self.theme), menu_area);
+            let items = self.menu_hints(sub_menu);
+            frame.render_widget(LeaderMenu::new(&items, &self.theme), menu_area);
         } else {
             frame.render_stateful_widget(
                 self.tabs[self.current_tab].clone(),
@@ -120,13 +209,20 @@ impl Editor {
             );
         }
 
-        let tab = &self.tab_states[self.current_tab];
-        let status_bar = StatusBar::new(
-            &tab.window_states,
-            self.get_mode().clone(),
-            Rc::downgrade(&self.theme),
-        );
-        frame.render_widget(&status_bar, layout[indices.status_bar]);
+        if self.shows_command_line() {
+            self.command_line
+                .render(layout[indices.status_bar], frame, &self.theme);
+        } else {
+            let tab = &self.tab_states[self.current_tab];
+            let status_bar = StatusBar::new(
+                tab.current_winstate(),
+                self.get_mode().clone(),
+                self.pending_keys(),
+                self.search_count(),
+                Rc::downgrade(&self.theme),
+            );
+            frame.render_widget(&status_bar, layout[indices.status_bar]);
+        }
 
         if let Some(ref floating) = self.floating_window {
             let area = Self::floating_window_area(frame);
@@ -134,35 +230,64 @@ impl Editor {
         }
     }
 
+    fn shows_command_line(&self) -> bool {
+        matches!(self.get_mode(), Mode::Command)
+            || self.command_line.notification().is_some()
+            || self.command_line.message().is_some()
+    }
+
     pub fn propagate_mode(&mut self) {
         let mode = self.get_mode().to_owned();
+        let pattern = self.highlighted_pattern().cloned();
         for tabstate in &mut self.tab_states {
             tabstate.propagate_mode(mode.clone());
+            for window_state in &mut tabstate.window_states {
+                window_state.search_highlight = pattern.clone();
+                window_state.current_match = None;
+            }
+        }
+        let current_match = self.substitution_match();
+        self.current_winstate_mut().current_match = current_match;
+    }
+
+    /// Keeps every window on the text it showed after edits made from the
+    /// current window.
+    fn sync_windows(&mut self) {
+        let current_tab = self.current_tab;
+        for buffer in &self.buffers {
+            let shifts = buffer.borrow_mut().take_line_shifts();
+            let buffer = Rc::downgrade(buffer);
+            for shift in &shifts {
+                for (i, tabstate) in self.tab_states.iter_mut().enumerate() {
+                    let editing = (i == current_tab).then(|| tabstate.current_window());
+                    tabstate.shift_lines(&buffer, shift, editing);
+                }
+            }
         }
     }
 
+    /// Every open buffer, for saving them when something goes wrong.
+    pub fn buffer_handles(&self) -> Vec<Weak<RefCell<Buffer>>> {
+        self.buffers.iter().map(Rc::downgrade).collect()
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
 
     fn generate_tabline(&self) -> Tabs<'_> {
-        let buffer_titles = self
-            .tab_states
-            .iter()
-            .map(|tab| -> String {
-                tab.buffer
-                    .borrow()
-                    .read_name()
-                    .map_or("Untitled", |x| {
-                        let tab_str = x.try_into();
-                        if let Err(err) = tab_str {
-                            graceful_exit(Some(&format!("file name is not valid Unicode! {err}")));
-                        }
-                        tab_str.unwrap()
-                    })
-                    .to_owned()
-            })
-            .map(|name| format!(" {name} "));
+        let buffer_titles = self.tab_states.iter().map(|tab| -> String {
+            let buffer = tab
+                .current_winstate()
+                .buffer()
+                .upgrade()
+                .clean_expect("showing a dead buffer in the tabline!");
+            let buffer = buffer.borrow();
+            match buffer.is_modified() {
+                true => format!(" {} [+] ", buffer.display_name()),
+                false => format!(" {} ", buffer.display_name()),
+            }
+        });
         let tabs_style = Style::default()
             .fg(self.theme.tabline_foreground)
             .bg(self.theme.tabline_background)
@@ -197,7 +322,8 @@ impl Editor {
         sub_menu: &SubMenu,
         frame: &mut Frame,
     ) -> (Rc<[Rect]>, EditorLayoutIndices) {
-        let needed_height = LeaderMenu::required_height(sub_menu, frame.area().width);
+        let items = self.menu_hints(sub_menu);
+        let needed_height = LeaderMenu::required_height(&items, frame.area().width);
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
@@ -233,6 +359,46 @@ impl Editor {
         &self.mode
     }
 
+    #[cfg(test)]
+    pub(crate) fn command_line(&self) -> &CommandLine {
+        &self.command_line
+    }
+
+    #[cfg(test)]
+    pub(crate) fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn current_tab_index(&self) -> usize {
+        self.current_tab
+    }
+
+    /// Where to keep swap files, which are not written until this is set.
+    pub fn set_swap_files(&mut self, swap_files: SwapFiles) {
+        self.swap_files = swap_files;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn has_floating_window(&self) -> bool {
+        self.floating_window.is_some()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn window_count(&self) -> usize {
+        self.current_tabstate().windows.len()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn current_window_index(&self) -> usize {
+        self.current_tabstate().current_window()
+    }
+
     pub(crate) fn current_tabstate_mut(&mut self) -> &mut TabState {
         &mut self.tab_states[self.current_tab]
     }
@@ -241,38 +407,46 @@ impl Editor {
         &self.tab_states[self.current_tab]
     }
 
-    fn current_winstate_mut(&mut self) -> &mut TextWindowState {
-        &mut self.current_tabstate_mut().window_states
+    pub(crate) fn current_winstate_mut(&mut self) -> &mut TextWindowState {
+        self.current_tabstate_mut().current_winstate_mut()
     }
 
     fn current_winstate(&self) -> &TextWindowState {
-        &self.current_tabstate().window_states
+        self.current_tabstate().current_winstate()
+    }
+
+    /// The position of the current window's buffer in the buffer list.
+    pub(crate) fn current_buffer_index(&self) -> usize {
+        let buffer = self.current_winstate().buffer();
+        self.buffers
+            .iter()
+            .position(|listed| Rc::downgrade(listed).ptr_eq(buffer))
+            .clean_expect("showing a buffer missing from the buffer list!")
     }
 
     pub(crate) fn current_buffer(&self) -> Ref<'_, Buffer> {
-        self.current_tabstate().buffer.borrow()
+        self.buffers[self.current_buffer_index()].borrow()
     }
 
     pub(crate) fn current_buffer_mut(&self) -> RefMut<'_, Buffer> {
-        self.current_tabstate().buffer.borrow_mut()
+        self.buffers[self.current_buffer_index()].borrow_mut()
     }
 
     pub(crate) fn current_bufpos(&self) -> BufferPosition {
         self.current_winstate().cursor.clone()
     }
 
-    fn get_cursor_pos(&self) -> Position {
-        let Position { x, y } = self.current_tabstate().get_cursor_pos();
-        Position {
-            y: y + TABLINE_HEIGHT,
-            x,
+    pub(crate) fn get_cursor_pos(&self) -> Position {
+        if let Mode::Command = self.get_mode() {
+            return self.command_line.cursor_pos();
         }
+        self.current_tabstate().get_cursor_pos()
     }
 
     pub fn draw_cursor(&mut self, term: &mut DefaultTerminal) -> io::Result<()> {
         let pos = self.get_cursor_pos();
         term.set_cursor_position(pos)?;
-        if let Mode::Insert = self.get_mode() {
+        if let Mode::Insert | Mode::Command = self.get_mode() {
             execute!(stdout(), SetCursorStyle::SteadyBar)?;
         } else {
             execute!(stdout(), SetCursorStyle::SteadyBlock)?;
@@ -282,6 +456,16 @@ impl Editor {
     }
 
     pub(crate) fn handle_input(&mut self) -> io::Result<()> {
+        let timeouts = [self.keymap.time_left(), self.swap_files.time_left()];
+        if let Some(time_left) = timeouts.into_iter().flatten().min() {
+            if !event::poll(time_left)? {
+                if self.keymap.time_left().is_some_and(|left| left.is_zero()) {
+                    self.handle_key_timeout();
+                }
+                self.update_swap_files();
+                return Ok(());
+            }
+        }
         match event::read()? {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_press(key_event)
@@ -292,22 +476,189 @@ impl Editor {
     }
 
     pub(crate) fn handle_key_press(&mut self, key: KeyEvent) {
+        self.dispatch_key_press(key);
+        self.sync_windows();
+        self.swap_files.note_changes(&self.buffers);
+    }
+
+    fn dispatch_key_press(&mut self, key: KeyEvent) {
+        self.command_line.clear_notification();
         if let Some(window) = &mut self.floating_window {
             if let Some(callback) = window.handle_input(&key) {
                 callback(self);
             }
             return;
         }
-        let bound_action = if let Some(ref menu) = self.lower_menu {
-            self.keymap.handle_menu_input(&key, menu)
-        } else {
-            self.keymap.handle_key(&key, self.get_mode())
+        if self.pending_substitution.is_some() {
+            self.confirm_substitution(&key);
+            return;
+        }
+        if let Some((kind, dir, count)) = self.pending_char_search.take() {
+            self.finish_char_search(kind, dir, count, &key);
+            return;
+        }
+        if self.accumulate_count(&key) {
+            return;
+        }
+        let mode = self.get_mode().to_owned();
+        let bound_actions = match self.lower_menu {
+            Some(SubMenu::Themes) => self.theme_menu_input(&key).into_iter().collect(),
+            Some(ref menu) => self
+                .keymap
+                .handle_menu_input(&key, menu)
+                .into_iter()
+                .collect(),
+            None => self.keymap.handle_key(&key, &mode),
+        };
+        self.execute_bound_actions(bound_actions);
+    }
+
+    pub(crate) fn menu_hints(&self, sub_menu: &SubMenu) -> Vec<KeyHint> {
+        match sub_menu {
+            SubMenu::Themes => self
+                .theme_choices
+                .iter()
+                .zip(THEME_MENU_KEYS.chars())
+                .map(|(name, key)| KeyHint::new(&key.to_string(), name))
+                .collect(),
+            _ => self.keymap.menu_hints(sub_menu).to_vec(),
+        }
+    }
+
+    fn theme_menu_input(&self, key: &KeyEvent) -> Option<EditorAction> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char(' ') => Some(EditorAction::ExitMenu),
+            KeyCode::Char(c) => {
+                let index = THEME_MENU_KEYS.find(c)?;
+                let name = self.theme_choices.get(index)?;
+                Some(EditorAction::SetTheme(name.to_owned()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolves a key sequence that was left incomplete for too long.
+    pub(crate) fn handle_key_timeout(&mut self) {
+        let mode = self.get_mode().to_owned();
+        let bound_actions = self.keymap.flush_pending(&mode);
+        self.execute_bound_actions(bound_actions);
+    }
+
+    fn execute_bound_actions(&mut self, actions: Vec<EditorAction>) {
+        if self.keymap.has_pending_keys() {
+            // The count applies to the whole key sequence
+            return;
+        }
+        let mut count = self.count.take();
+        for action in actions {
+            self.execute_counted_action(action, count.take());
+        }
+        if !matches!(self.get_mode(), Mode::OperatorPending(_)) {
+            self.operator_count = None;
+        }
+    }
+
+    /// Adds a typed digit to the pending count. A leading `0` is not part of
+    /// a count, so that it can still jump to the start of the line.
+    fn accumulate_count(&mut self, key: &KeyEvent) -> bool {
+        let counting_mode = matches!(
+            self.get_mode(),
+            Mode::Normal | Mode::Visual(_) | Mode::OperatorPending(_)
+        );
+        if !counting_mode
+            || self.lower_menu.is_some()
+            || self.keymap.has_pending_keys()
+            || !key.modifiers.is_empty()
+        {
+            return false;
+        }
+        let digit = match key.code {
+            KeyCode::Char(c) => c.to_digit(10),
+            _ => None,
         };
-        if let Some(action) = bound_action {
-            self.execute_editor_action(action);
+        match (digit, self.count) {
+            (Some(0), None) | (None, _) => false,
+            (Some(digit), count) => {
+                let count = count.unwrap_or(0) * 10 + digit as usize;
+                self.count = Some(min(count, MAX_COUNT));
+                true
+            }
         }
     }
 
+    /// The count typed so far, combined with the one typed before a pending
+    /// operator, so that `2d3w` deletes six words.
+    fn take_operator_count(&mut self, count: Option<usize>) -> Option<usize> {
+        match (self.operator_count.take(), count) {
+            (None, None) => None,
+            (first, second) => {
+                let count = first.unwrap_or(1) * second.unwrap_or(1);
+                Some(min(count, MAX_COUNT))
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pending_count(&self) -> Option<usize> {
+        self.count
+    }
+
+    #[cfg(test)]
+    pub(crate) fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// The count and keys typed so far towards the next command.
+    pub(crate) fn pending_keys(&self) -> String {
+        let count = self.count.map(|count| count.to_string());
+        count.unwrap_or_default() + &self.keymap.pending_keys()
+    }
+
+    /// Completes `f`, `t`, `F` or `T` with the character that was typed.
+    /// Any other key cancels the search.
+    fn finish_char_search(
+        &mut self,
+        kind: FindKind,
+        dir: HorizontalDirection,
+        count: Option<usize>,
+        key: &KeyEvent,
+    ) {
+        match key.code {
+            KeyCode::Char(target) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let search = CharSearch::new(kind, dir, target);
+                self.execute_motion(Motion::FindChar(search), count);
+            }
+            _ => {
+                if let Mode::OperatorPending(_) = self.get_mode() {
+                    self.mode = Mode::Normal;
+                }
+            }
+        }
+    }
+
+    /// Shows a notification on the command line and keeps it in the
+    /// message history.
+    pub(crate) fn notify(&mut self, severity: Severity, msg: String) {
+        let notification = Notification::new(severity, msg);
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages.push(notification.clone());
+        self.command_line.notify(notification);
+    }
+
+    pub(crate) fn show_error(&mut self, msg: String) {
+        self.notify(Severity::Error, msg);
+    }
+
+    pub(crate) fn show_warning(&mut self, msg: String) {
+        self.notify(Severity::Warning, msg);
+    }
+
+    pub(crate) fn show_info(&mut self, msg: String) {
+        self.notify(Severity::Info, msg);
+    }
+
     pub(crate) fn clear_floating_window(&mut self) {
         self.floating_window = None;
     }
@@ -1,17 +1,28 @@
+use std::{cmp::min, ffi::OsString, path::Path, rc::Rc};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::layout::Direction;
+
 use crate::app::{
     buffer::{
         BufferPosition, HorizontalDirection as Horizontal, RectilinearDirection as Rectilinear,
         VerticalDirection,
     },
-    cleanup::CleanUnwrap,
+    theme::{available_themes, Theme},
     ui::{
-        floating_window::{saving_unnamed::SavingUnnamed, FloatingContent},
+        command_line::Prompt,
+        floating_window::{
+            messages::Messages,
+            saving_unnamed::SavingUnnamed,
+            unsaved_changes::{Closing, UnsavedChanges},
+            FloatingContent,
+        },
         leader_menu::SubMenu,
         text_window::selection::Selection,
     },
 };
 
-use super::{actions::EditorAction, Editor, Mode};
+use super::{actions::EditorAction, commands::parse_command, motions::Motion, Editor, Mode};
 
 impl Editor {
     pub fn execute_editor_action(&mut self, action: EditorAction) {
@@ -21,14 +32,40 @@ impl Editor {
                 self.sticky_jump_to_EOL();
                 self.append();
             }
+            EditorAction::ApplyOperator(op, motion) => self.apply_operator(op, motion, None),
+            EditorAction::ApplyOperatorToSelection(op) => self.apply_operator_to_selection(op),
             EditorAction::Back => self.back(),
+            EditorAction::BrowseCommandHistory(dir) => {
+                self.command_line.browse_history(dir);
+                self.update_incremental_search();
+            }
+            EditorAction::ClearBookmarks => self.clear_bookmarks(),
+            EditorAction::ClearSearchHighlight => self.clear_search_highlight(),
+            EditorAction::CloseTab => self.close_tab(),
+            EditorAction::CloseWindow => self.close_window(),
+            EditorAction::CycleBuffer(dir) => self.cycle_buffer(dir),
+            EditorAction::CycleLineNumbers => {
+                let mode = self.current_winstate_mut().cycle_line_numbers();
+                self.show_info(format!("Line numbers: {mode}"));
+            }
             EditorAction::CycleTab(dir) => self.cycle_tab(dir),
+            EditorAction::CycleWindow(dir) => self.cycle_window(dir),
+            EditorAction::DeleteBuffer => self.delete_buffer(),
+            EditorAction::EditCommandLine(key) => self.edit_command_line(key),
             EditorAction::EndOfBuffer => self.jump_to_last_line(),
+            EditorAction::EnterBufferPicker => self.enter_buffer_picker(),
+            EditorAction::EnterCommand => self.enter_command(),
             EditorAction::EnterInsert => self.enter_insert(),
             EditorAction::EnterFloatingMenu(menu) => self.enter_floating_menu(menu),
             EditorAction::EnterMenu => self.enter_menu(),
+            EditorAction::EnterSearch(dir) => self.enter_search(dir),
+            EditorAction::EnterThemeMenu => self.enter_theme_menu(),
             EditorAction::EnterVisual => self.enter_visual(),
             EditorAction::EOL => self.sticky_jump_to_EOL(),
+            EditorAction::FocusWindow(dir) => self.focus_window(dir),
+            EditorAction::GotoBookmark(dir) => self.goto_bookmark(dir),
+            EditorAction::ExecuteCommand => self.execute_command(),
+            EditorAction::ExitCommand => self.exit_command(),
             EditorAction::ExitInsert => self.exit_insert(),
             EditorAction::ExitEditor => self.exit(),
             EditorAction::ExitMenu => self.exit_menu(),
@@ -36,17 +73,94 @@ impl Editor {
             EditorAction::Home => self.jump_to_home(),
             EditorAction::InsertChar(c) => self.insert_char(c),
             EditorAction::InsertLineBreak => self.insert_line_break(),
+            EditorAction::InsertTab => self.insert_tab(),
             EditorAction::InsertNewLine(dir) => self.insert_new_line(dir),
+            EditorAction::Motion(motion) => self.execute_motion(motion, None),
             EditorAction::MoveCursor(mode, dir) => self.move_cursor(&mode, dir),
+            EditorAction::MoveDisplayLine(dir) => self.move_display_line(dir),
             EditorAction::MoveToHomeAndEnterInsert => {
                 self.jump_to_home();
                 self.enter_insert();
             }
+            EditorAction::NewTab => self.open_tab(None),
             EditorAction::NextLine => self.jump_to_next_line(),
+            EditorAction::OnlyWindow => self.only_window(),
+            EditorAction::OpenFile(path) => self.open_file(Path::new(&path)),
+            EditorAction::OpenFileInTab(path) => self.open_tab(Some(Path::new(&path))),
+            EditorAction::Paste(dir) => self.paste(dir),
             EditorAction::RemoveChar(dir) => self.remove_char(dir),
+            EditorAction::RepeatSearch(dir) => self.repeat_search(dir),
+            EditorAction::Redo => self.redo(),
             EditorAction::ReplaceLine => self.replace_line(),
-            EditorAction::SaveBuffer => self.save_current_buffer(),
+            EditorAction::ResizeWindow(direction, delta) => self.resize_window(direction, delta),
+            EditorAction::SaveAndExit => self.save_and_exit(),
+            EditorAction::SaveBuffer => {
+                self.save_current_buffer();
+            }
+            EditorAction::SaveBufferAs(path) => self.save_current_buffer_as(path),
+            EditorAction::SetLineEnding(line_ending) => {
+                self.current_buffer_mut().set_line_ending(line_ending)
+            }
+            EditorAction::SetTheme(name) => self.set_theme(&name),
+            EditorAction::ShowMessages => {
+                let messages = Messages::new(self.messages.clone());
+                self.enter_floating_menu(Box::new(messages));
+            }
+            EditorAction::SplitWindow(direction) => self.split_window(direction),
+            EditorAction::SwitchBuffer(number) => match number.checked_sub(1) {
+                Some(index) => self.switch_buffer(index),
+                None => self.show_error("Buffer 0 does not exist".to_owned()),
+            },
+            EditorAction::StartCharSearch(kind, dir) => {
+                self.pending_char_search = Some((kind, dir, None))
+            }
+            EditorAction::StartOperator(op) => self.start_operator(op, None),
+            EditorAction::Substitute(substitution) => self.substitute(substitution),
             EditorAction::SwitchToMode(mode) => self.switch_mode(mode),
+            EditorAction::ToggleBookmark => self.toggle_bookmark(),
+            EditorAction::ToggleWrap => self.current_winstate_mut().toggle_wrap(),
+            EditorAction::Undo => self.undo(),
+        }
+    }
+
+    /// Executes an action with the count typed before it, which either
+    /// parameterizes the action or repeats it. Actions that take no count
+    /// ignore it.
+    pub fn execute_counted_action(&mut self, action: EditorAction, count: Option<usize>) {
+        let Some(count) = count else {
+            self.execute_editor_action(action);
+            return;
+        };
+        match action {
+            EditorAction::ApplyOperator(op, motion) => self.apply_operator(op, motion, Some(count)),
+            EditorAction::EndOfBuffer => self.execute_motion(Motion::LastLine, Some(count)),
+            EditorAction::Motion(motion) => self.execute_motion(motion, Some(count)),
+            EditorAction::StartCharSearch(kind, dir) => {
+                self.pending_char_search = Some((kind, dir, Some(count)))
+            }
+            EditorAction::StartOperator(op) => self.start_operator(op, Some(count)),
+            EditorAction::GotoBookmark(_)
+            | EditorAction::RepeatSearch(_)
+            | EditorAction::Undo
+            | EditorAction::Redo => {
+                for _ in 0..count {
+                    self.execute_editor_action(action.clone());
+                }
+            }
+            EditorAction::Back
+            | EditorAction::MoveCursor(_, _)
+            | EditorAction::MoveDisplayLine(_)
+            | EditorAction::NextLine
+            | EditorAction::Paste(_)
+            | EditorAction::RemoveChar(_) => {
+                // Repeated edits are undone as a whole
+                let checkpoint = self.current_buffer().undo_checkpoint();
+                for _ in 0..count {
+                    self.execute_editor_action(action.clone());
+                }
+                self.current_buffer_mut().squash_undo_steps(checkpoint);
+            }
+            _ => self.execute_editor_action(action),
         }
     }
 
@@ -78,11 +192,56 @@ impl Editor {
         }
     }
 
-    fn enter_floating_menu(&mut self, menu: Box<dyn FloatingContent>) {
+    fn cycle_window(&mut self, dir: Horizontal) {
+        let forward = dir == Horizontal::Forward;
+        self.current_tabstate_mut().cycle_window(forward);
+    }
+
+    fn close_window(&mut self) {
+        if !self.current_tabstate_mut().close_window() {
+            self.show_error("Cannot close last window".to_owned());
+        }
+    }
+
+    fn only_window(&mut self) {
+        self.current_tabstate_mut().only_window();
+    }
+
+    fn focus_window(&mut self, dir: Rectilinear) {
+        self.current_tabstate_mut().focus_window(dir);
+    }
+
+    fn resize_window(&mut self, direction: Direction, delta: isize) {
+        self.current_tabstate_mut().resize_window(direction, delta);
+    }
+
+    fn split_window(&mut self, direction: Direction) {
+        if !self.current_tabstate_mut().split_window(direction) {
+            self.show_error("Not enough room".to_owned());
+        }
+    }
+
+    fn edit_command_line(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Backspace && self.command_line.is_empty() {
+            self.exit_command();
+            return;
+        }
+        self.command_line.handle_input(&key);
+        self.update_incremental_search();
+    }
+
+    fn enter_command(&mut self) {
+        self.command_line.start(Prompt::Command);
+        self.mode = Mode::Command;
+    }
+
+    pub(super) fn enter_floating_menu(&mut self, menu: Box<dyn FloatingContent>) {
         self.floating_window = Some(menu);
     }
 
-    fn enter_insert(&mut self) {
+    pub(super) fn enter_insert(&mut self) {
+        let cursor = self.current_bufpos();
+        self.current_buffer_mut().begin_undo_group(&cursor);
         self.current_winstate_mut().stick_to_EOL = false;
         self.mode = Mode::Insert;
     }
@@ -91,17 +250,74 @@ impl Editor {
         self.lower_menu = Some(SubMenu::Root);
     }
 
+    fn enter_theme_menu(&mut self) {
+        self.theme_choices = available_themes();
+        self.lower_menu = Some(SubMenu::Themes);
+    }
+
     fn enter_visual(&mut self) {
         if self.current_buffer().lines_count() != 0 {
             self.mode = Mode::Visual(Selection::from_single(&self.current_bufpos()));
         }
     }
 
+    fn execute_command(&mut self) {
+        let prompt = self.command_line.prompt().to_owned();
+        let input = self.command_line.take_input();
+        self.mode = Mode::Normal;
+        if let Prompt::Search(dir) = prompt {
+            self.finish_search(&input, dir);
+            return;
+        }
+        if input.trim().is_empty() {
+            return;
+        }
+        match parse_command(&input) {
+            Ok(action) => self.execute_editor_action(action),
+            Err(err) => self.show_error(err.to_string()),
+        }
+    }
+
+    /// Switches every window to another theme, keeping the current one if
+    /// it cannot be loaded.
+    fn set_theme(&mut self, name: &str) {
+        match Theme::named(name) {
+            Ok(theme) => {
+                self.theme = Rc::new(theme);
+                for tab in &mut self.tab_states {
+                    tab.set_theme(Rc::downgrade(&self.theme));
+                }
+                self.restyle_bookmarks();
+            }
+            Err(err) => self.show_error(err.to_string()),
+        }
+    }
+
     fn exit(&mut self) {
-        self.active = false;
+        let modified: Vec<_> = self
+            .buffers
+            .iter()
+            .filter(|buffer| buffer.borrow().is_modified())
+            .collect();
+        let message = match modified.as_slice() {
+            [] => {
+                self.active = false;
+                return;
+            }
+            [buffer] => format!("Save changes to {}?", buffer.borrow().display_name()),
+            buffers => format!("Save changes to {} buffers?", buffers.len()),
+        };
+        self.enter_floating_menu(Box::new(UnsavedChanges::new(Closing::Editor, message)));
+    }
+
+    fn exit_command(&mut self) {
+        self.cancel_search();
+        self.command_line.clear();
+        self.mode = Mode::Normal;
     }
 
     fn exit_insert(&mut self) {
+        self.current_buffer_mut().end_undo_group();
         self.mode = Mode::Normal;
         self.current_winstate_mut().snap_to_EOL();
         self.current_winstate_mut().last_manual_col = self.current_bufpos().col;
@@ -117,8 +333,31 @@ impl Editor {
 
     fn insert_char(&mut self, c: char) {
         let cursor = self.current_bufpos();
+        let line_length = |ed: &Self| ed.current_buffer().line_length(cursor.line);
+        let before = line_length(self);
         self.current_buffer_mut().insert_char(c, &cursor);
-        self.current_winstate_mut().advance_insertion_cursor();
+        // Combining marks join the character before the cursor
+        if line_length(self) != before {
+            self.current_winstate_mut().advance_insertion_cursor();
+        }
+    }
+
+    /// Inserts spaces up to the next multiple of `shiftwidth` cells, or a
+    /// tab if `expandtab` is off.
+    fn insert_tab(&mut self) {
+        if !self.expandtab {
+            self.insert_char('\t');
+            return;
+        }
+        let cursor = self.current_bufpos();
+        let tabstop = self.current_winstate().settings.tabstop;
+        let x = self.current_buffer().display_col(&cursor, tabstop);
+        let spaces = self.shiftwidth - x % self.shiftwidth;
+        self.current_buffer_mut()
+            .insert_text(&cursor, &" ".repeat(spaces));
+        for _ in 0..spaces {
+            self.current_winstate_mut().advance_insertion_cursor();
+        }
     }
 
     fn insert_line_break(&mut self) {
@@ -133,7 +372,9 @@ impl Editor {
 
     fn insert_new_line(&mut self, dir: VerticalDirection) {
         let line_count = self.current_buffer().lines_count();
-        let mut line = self.current_bufpos().line;
+        let cursor = self.current_bufpos();
+        let mut line = cursor.line;
+        self.current_buffer_mut().begin_undo_group(&cursor);
         if let VerticalDirection::Down = dir {
             line += 1;
         }
@@ -147,6 +388,7 @@ impl Editor {
                 let second_line = BufferPosition { line, col: 0 };
                 self.current_winstate_mut().jump(&second_line);
             }
+            self.enter_insert();
             return;
         }
 
@@ -190,6 +432,14 @@ impl Editor {
         }
     }
 
+    fn move_display_line(&mut self, dir: VerticalDirection) {
+        let mode = self.get_mode().to_owned();
+        self.current_winstate_mut().move_display_line(&mode, dir);
+        if matches!(self.get_mode(), Mode::Visual(_)) {
+            self.update_selection();
+        }
+    }
+
     fn remove_char(&mut self, dir: Horizontal) {
         let pos = self.current_bufpos();
         let mode = self.get_mode().to_owned();
@@ -235,6 +485,13 @@ impl Editor {
         self.current_winstate_mut().last_manual_col = self.current_winstate().cursor.col;
     }
 
+    fn redo(&mut self) {
+        let cursor = self.current_buffer_mut().redo();
+        if let Some(cursor) = cursor {
+            self.restore_cursor(&cursor);
+        }
+    }
+
     fn replace_line(&mut self) {
         self.enter_insert();
         let current_pos = self.current_bufpos();
@@ -242,15 +499,42 @@ impl Editor {
         self.current_winstate_mut().snap_to_EOL();
     }
 
-    fn save_current_buffer(&mut self) {
+    pub(super) fn restore_cursor(&mut self, cursor: &BufferPosition) {
+        let lines_count = self.current_buffer().lines_count();
+        let line = min(cursor.line, lines_count.saturating_sub(1));
+        self.current_winstate_mut()
+            .jump(&BufferPosition { line, ..*cursor });
+        self.current_winstate_mut().snap_to_EOL();
+        self.current_winstate_mut().last_manual_col = self.current_bufpos().col;
+    }
+
+    fn save_and_exit(&mut self) {
+        if self.save_current_buffer() {
+            self.exit();
+        }
+    }
+
+    /// Saves the current buffer, asking for a file name if it has none.
+    /// Returns whether it was saved.
+    pub(super) fn save_current_buffer(&mut self) -> bool {
+        self.exit_menu();
         if self.current_buffer().read_name().is_some() {
-            self.current_buffer()
-                .save()
-                .clean_expect("Saving the buffer failed");
+            self.save_buffer(self.current_buffer_index())
         } else {
             self.enter_floating_menu(Box::new(SavingUnnamed::default()));
+            false
         }
-        self.exit_menu();
+    }
+
+    fn save_current_buffer_as(&mut self, path: OsString) {
+        let Some(name) = Path::new(&path).file_name() else {
+            self.show_error(format!("Invalid file name: {}", path.to_string_lossy()));
+            return;
+        };
+        let name = name.to_owned();
+        self.current_buffer_mut().set_name(name);
+        self.current_buffer_mut().set_path(path);
+        self.save_current_buffer();
     }
 
     fn sticky_jump_to_EOL(&mut self) {
@@ -263,4 +547,11 @@ impl Editor {
     fn switch_mode(&mut self, mode: Mode) {
         self.mode = mode;
     }
+
+    fn undo(&mut self) {
+        let cursor = self.current_buffer_mut().undo();
+        if let Some(cursor) = cursor {
+            self.restore_cursor(&cursor);
+        }
+    }
 }
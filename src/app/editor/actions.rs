@@ -1,5 +1,17 @@
-use crate::app::buffer::{HorizontalDirection, RectilinearDirection, VerticalDirection};
-use crate::app::editor::Mode;
+use std::ffi::OsString;
+
+use crossterm::event::KeyEvent;
+use ratatui::layout::Direction;
+
+use crate::app::buffer::{
+    HorizontalDirection, LineEnding, RectilinearDirection, VerticalDirection,
+};
+use crate::app::editor::{
+    motions::{FindKind, Motion},
+    operators::Operator,
+    substitute::Substitution,
+    Mode,
+};
 use crate::app::ui::floating_window::FloatingContent;
 
 #[allow(clippy::upper_case_acronyms)]
@@ -7,14 +19,37 @@ use crate::app::ui::floating_window::FloatingContent;
 pub enum EditorAction {
     Append,
     AppendAtEOL,
+    ApplyOperator(Operator, Motion),
+    ApplyOperatorToSelection(Operator),
     Back,
+    BrowseCommandHistory(VerticalDirection),
+    /// Removes every bookmark of the current buffer.
+    ClearBookmarks,
+    ClearSearchHighlight,
+    CloseTab,
+    CloseWindow,
+    CycleBuffer(HorizontalDirection),
+    /// Switches the line numbers of the current window to the next mode.
+    CycleLineNumbers,
     CycleTab(HorizontalDirection),
+    CycleWindow(HorizontalDirection),
+    DeleteBuffer,
+    EditCommandLine(KeyEvent),
     EndOfBuffer,
+    EnterBufferPicker,
+    EnterCommand,
     EnterInsert,
     EnterFloatingMenu(Box<dyn FloatingContent>),
     EnterMenu,
+    EnterSearch(HorizontalDirection),
+    EnterThemeMenu,
     EnterVisual,
     EOL,
+    FocusWindow(RectilinearDirection),
+    /// Moves to the next bookmarked line above or below the cursor.
+    GotoBookmark(VerticalDirection),
+    ExecuteCommand,
+    ExitCommand,
     ExitEditor,
     ExitInsert,
     ExitMenu,
@@ -22,12 +57,44 @@ pub enum EditorAction {
     Home,
     InsertChar(char),
     InsertLineBreak,
+    /// Indents at the cursor in insert mode, as the Tab key does.
+    InsertTab,
     InsertNewLine(VerticalDirection),
+    Motion(Motion),
+    /// Moves to the row above or below on the screen, within a wrapped line
+    /// if the cursor is on one.
+    MoveDisplayLine(VerticalDirection),
     MoveCursor(Mode, RectilinearDirection),
     MoveToHomeAndEnterInsert,
+    NewTab,
     NextLine,
+    OnlyWindow,
+    OpenFile(OsString),
+    OpenFileInTab(OsString),
+    Paste(HorizontalDirection),
     RemoveChar(HorizontalDirection),
+    RepeatSearch(HorizontalDirection),
+    Redo,
     ReplaceLine,
+    /// Grows the current window by a number of cells along a direction.
+    ResizeWindow(Direction, isize),
+    SaveAndExit,
     SaveBuffer,
+    SaveBufferAs(OsString),
+    SetLineEnding(LineEnding),
+    SetTheme(String),
+    ShowMessages,
+    /// Splits the current window, laying the two halves out along a
+    /// direction: `Vertical` stacks them like `:split` does.
+    SplitWindow(Direction),
+    StartCharSearch(FindKind, HorizontalDirection),
+    StartOperator(Operator),
+    /// Shows a buffer, by its number in the buffer list, in the current
+    /// window.
+    SwitchBuffer(usize),
+    Substitute(Substitution),
     SwitchToMode(Mode),
+    ToggleBookmark,
+    ToggleWrap,
+    Undo,
 }
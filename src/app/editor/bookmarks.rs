@@ -0,0 +1,71 @@
+use crate::app::buffer::{
+    signs::{Sign, BOOKMARKS},
+    BufferPosition, VerticalDirection,
+};
+
+use super::Editor;
+
+/// What bookmarked lines show in the sign column.
+const BOOKMARK_TEXT: &str = "▶";
+/// Low, so that signs such as errors can hide bookmarks.
+const BOOKMARK_PRIORITY: u8 = 10;
+
+impl Editor {
+    /// Bookmarks the cursor line, or removes its bookmark.
+    pub(super) fn toggle_bookmark(&mut self) {
+        let line = self.current_bufpos().line;
+        let sign = self.bookmark_sign();
+        let mut buffer = self.current_buffer_mut();
+        if !buffer.signs_mut().remove(BOOKMARKS, line) {
+            buffer.signs_mut().place(BOOKMARKS, line, sign);
+        }
+    }
+
+    /// Moves to the next bookmarked line in `dir`, going around the end of
+    /// the buffer.
+    pub(super) fn goto_bookmark(&mut self, dir: VerticalDirection) {
+        let cursor = self.current_bufpos();
+        let lines: Vec<usize> = self.current_buffer().signs().lines(BOOKMARKS).collect();
+        let target = match dir {
+            VerticalDirection::Down => lines
+                .iter()
+                .find(|&&line| line > cursor.line)
+                .or(lines.first()),
+            VerticalDirection::Up => lines
+                .iter()
+                .rfind(|&&line| line < cursor.line)
+                .or(lines.last()),
+        };
+        let Some(&line) = target else {
+            self.show_error("No bookmarks".to_owned());
+            return;
+        };
+        let col = self.current_buffer().first_non_blank(line);
+        self.restore_cursor(&BufferPosition { line, col });
+    }
+
+    pub(super) fn clear_bookmarks(&mut self) {
+        self.current_buffer_mut().signs_mut().clear(BOOKMARKS);
+    }
+
+    /// Redraws the bookmarks of every buffer in the style of the current
+    /// theme.
+    pub(super) fn restyle_bookmarks(&mut self) {
+        let sign = self.bookmark_sign();
+        for buffer in &self.buffers {
+            let mut buffer = buffer.borrow_mut();
+            let lines: Vec<usize> = buffer.signs().lines(BOOKMARKS).collect();
+            for line in lines {
+                buffer.signs_mut().place(BOOKMARKS, line, sign.clone());
+            }
+        }
+    }
+
+    fn bookmark_sign(&self) -> Sign {
+        Sign::new(
+            BOOKMARK_TEXT,
+            self.theme.styles.bookmark_sign,
+            BOOKMARK_PRIORITY,
+        )
+    }
+}
@@ -0,0 +1,206 @@
+use std::{cell::RefCell, cmp::min, fs, path::Path, rc::Rc};
+
+use crate::app::{
+    buffer::{Buffer, HorizontalDirection},
+    ui::{
+        floating_window::{
+            buffer_picker::{BufferEntry, BufferPicker},
+            saving_unnamed::SavingUnnamed,
+            unsaved_changes::{Closing, UnsavedChanges},
+        },
+        Tab, TabState,
+    },
+};
+
+use super::{Editor, Mode};
+
+impl Editor {
+    /// Finds the buffer of a file in the buffer list, loading the file into
+    /// a new one if it is not open yet.
+    fn buffer_for_file(&mut self, path: &Path) -> Option<usize> {
+        if path.is_dir() {
+            self.show_error("Opening directories is not supported".to_owned());
+            return None;
+        }
+        let open = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.borrow().is_file(path));
+        if open.is_some() {
+            return open;
+        }
+        match Buffer::open(path) {
+            Ok(buffer) => {
+                let read_only =
+                    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly());
+                if read_only {
+                    self.show_warning(format!("{} is read-only", path.display()));
+                }
+                self.buffers.push(Rc::new(RefCell::new(buffer)));
+                self.check_swap_file(self.buffers.len() - 1);
+                Some(self.buffers.len() - 1)
+            }
+            Err(err) => {
+                self.show_error(format!("Could not open {}: {err}", path.display()));
+                None
+            }
+        }
+    }
+
+    /// Opens a file in the current window.
+    pub(super) fn open_file(&mut self, path: &Path) {
+        if let Some(index) = self.buffer_for_file(path) {
+            self.switch_buffer(index);
+        }
+    }
+
+    /// Opens a file, or a new untitled buffer, in a new tab.
+    pub(super) fn open_tab(&mut self, path: Option<&Path>) {
+        let index = match path {
+            Some(path) => match self.buffer_for_file(path) {
+                Some(index) => index,
+                None => return,
+            },
+            None => {
+                self.buffers.push(Rc::new(RefCell::new(Buffer::untitled())));
+                self.buffers.len() - 1
+            }
+        };
+        self.tabs.push(Tab::new());
+        self.tab_states.push(TabState::new(
+            Rc::downgrade(&self.buffers[index]),
+            Rc::downgrade(&self.theme),
+            Mode::Normal,
+            self.window_settings.clone(),
+        ));
+        self.current_tab = self.tabs.len() - 1;
+    }
+
+    pub(super) fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            self.show_error("Cannot close last tab".to_owned());
+            return;
+        }
+        self.tabs.remove(self.current_tab);
+        self.tab_states.remove(self.current_tab);
+        self.current_tab = min(self.current_tab, self.tabs.len() - 1);
+    }
+
+    /// Shows the buffer at `index` in the buffer list in the current window.
+    pub(crate) fn switch_buffer(&mut self, index: usize) {
+        let Some(buffer) = self.buffers.get(index).map(Rc::downgrade) else {
+            self.show_error(format!("Buffer {} does not exist", index + 1));
+            return;
+        };
+        if index != self.current_buffer_index() {
+            self.current_tabstate_mut().show_buffer(buffer);
+        }
+    }
+
+    /// Shows the next or previous buffer of the list in the current window.
+    pub(super) fn cycle_buffer(&mut self, dir: HorizontalDirection) {
+        let count = self.buffers.len();
+        let index = match dir {
+            HorizontalDirection::Forward => (self.current_buffer_index() + 1) % count,
+            HorizontalDirection::Backward => (self.current_buffer_index() + count - 1) % count,
+        };
+        self.switch_buffer(index);
+    }
+
+    /// Removes the current buffer from the list, asking first what to do
+    /// with its unsaved changes.
+    pub(super) fn delete_buffer(&mut self) {
+        if self.current_buffer().is_modified() {
+            let message = format!("Save changes to {}?", self.current_buffer().display_name());
+            self.enter_floating_menu(Box::new(UnsavedChanges::new(Closing::Buffer, message)));
+        } else {
+            self.remove_current_buffer();
+        }
+    }
+
+    /// Saves or discards the unsaved changes in the way of `closing`, then
+    /// closes it. Saving stops at the first buffer that cannot be saved.
+    pub(crate) fn close_unsaved(&mut self, closing: Closing, save: bool) {
+        if save {
+            let closed = match closing {
+                Closing::Editor => (0..self.buffers.len()).collect(),
+                Closing::Buffer => vec![self.current_buffer_index()],
+            };
+            for index in closed {
+                if !self.buffers[index].borrow().is_modified() {
+                    continue;
+                }
+                if self.buffers[index].borrow().path().is_none() {
+                    // Ask for a name, closing goes on once it is saved
+                    self.switch_buffer(index);
+                    self.enter_floating_menu(Box::new(SavingUnnamed::then_close(closing)));
+                    return;
+                }
+                if !self.save_buffer(index) {
+                    return;
+                }
+            }
+        }
+        match closing {
+            Closing::Editor => self.active = false,
+            Closing::Buffer => self.remove_current_buffer(),
+        }
+    }
+
+    /// Writes the buffer at `index` in the buffer list to its file, telling
+    /// how it went. Returns whether it was saved.
+    pub(super) fn save_buffer(&mut self, index: usize) -> bool {
+        let saved = self.buffers[index].borrow_mut().save(self.backup);
+        let name = self.buffers[index].borrow().display_name();
+        match saved {
+            Ok(()) => {
+                let lines = self.buffers[index].borrow().lines_count();
+                self.show_info(format!("\"{name}\" {lines}L written"));
+                true
+            }
+            Err(err) => {
+                self.show_error(format!("Could not save {name}: {err}"));
+                false
+            }
+        }
+    }
+
+    /// Removes the current buffer from the list. The windows showing it
+    /// switch to a neighbouring buffer, or to a new one if it was the last.
+    fn remove_current_buffer(&mut self) {
+        let index = self.current_buffer_index();
+        if self.buffers.len() == 1 {
+            self.buffers.push(Rc::new(RefCell::new(Buffer::untitled())));
+        }
+        let replacement = match index + 1 < self.buffers.len() {
+            true => index + 1,
+            false => index - 1,
+        };
+        let deleted = Rc::downgrade(&self.buffers[index]);
+        let replacement = Rc::downgrade(&self.buffers[replacement]);
+        for tab in &mut self.tab_states {
+            tab.replace_buffer(&deleted, &replacement);
+        }
+        let removed = self.buffers.remove(index);
+        let swap_removed = self.swap_files.remove(&removed.borrow());
+        if let Err(err) = swap_removed {
+            self.show_warning(format!("Could not delete swap file: {err}"));
+        }
+    }
+
+    pub(super) fn enter_buffer_picker(&mut self) {
+        let entries = self
+            .buffers
+            .iter()
+            .map(|buffer| {
+                let weak = Rc::downgrade(buffer);
+                BufferEntry {
+                    name: buffer.borrow().display_name(),
+                    shown: self.tab_states.iter().any(|tab| tab.shows_buffer(&weak)),
+                }
+            })
+            .collect();
+        let picker = BufferPicker::new(entries, self.current_buffer_index());
+        self.floating_window = Some(Box::new(picker));
+    }
+}
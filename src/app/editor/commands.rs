@@ -0,0 +1,241 @@
+use std::{ffi::OsString, fmt::Display};
+
+use ratatui::layout::Direction;
+
+use crate::app::buffer::{HorizontalDirection, LineEnding};
+
+use super::{actions::EditorAction, substitute::parse_substitute};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    UnknownCommand(String),
+    MissingArgument(String),
+    TrailingCharacters(String),
+    InvalidArgument(String),
+    InvalidRange,
+    RangeNotAllowed,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::UnknownCommand(cmd) => write!(f, "Not an editor command: {cmd}"),
+            CommandError::MissingArgument(cmd) => write!(f, "Argument required: {cmd}"),
+            CommandError::TrailingCharacters(arg) => write!(f, "Trailing characters: {arg}"),
+            CommandError::InvalidArgument(arg) => write!(f, "Invalid argument: {arg}"),
+            CommandError::InvalidRange => write!(f, "Invalid range"),
+            CommandError::RangeNotAllowed => write!(f, "No range allowed"),
+        }
+    }
+}
+
+/// A line a command applies to, counted from zero once resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineAddress {
+    Current,
+    Last,
+    /// A line number as typed, counted from one.
+    Number(usize),
+}
+
+/// A line address followed by the sum of its `+N` and `-N` offsets.
+pub type Address = (LineAddress, isize);
+
+/// The lines a command applies to, such as `%`, `.,$` or `3,7+1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineRange {
+    pub start: Address,
+    pub end: Address,
+}
+
+impl LineRange {
+    /// The range covering only the cursor line.
+    pub fn current_line() -> Self {
+        LineRange {
+            start: (LineAddress::Current, 0),
+            end: (LineAddress::Current, 0),
+        }
+    }
+
+    /// The first and last line of the range in a buffer with `lines_count`
+    /// lines, in order.
+    pub fn resolve(
+        &self,
+        current: usize,
+        lines_count: usize,
+    ) -> Result<(usize, usize), CommandError> {
+        let resolve = |(address, offset): &Address| {
+            let line = match address {
+                LineAddress::Current => current,
+                LineAddress::Last => lines_count.saturating_sub(1),
+                LineAddress::Number(number) => number.saturating_sub(1),
+            };
+            line.checked_add_signed(*offset)
+                .filter(|&line| line < lines_count)
+                .ok_or(CommandError::InvalidRange)
+        };
+        let (start, end) = (resolve(&self.start)?, resolve(&self.end)?);
+        Ok((start.min(end), start.max(end)))
+    }
+}
+
+/// Splits a leading range off a command.
+fn parse_range(input: &str) -> Result<(Option<LineRange>, &str), CommandError> {
+    if let Some(rest) = input.strip_prefix('%') {
+        let range = LineRange {
+            start: (LineAddress::Number(1), 0),
+            end: (LineAddress::Last, 0),
+        };
+        return Ok((Some(range), rest));
+    }
+    let (start, rest) = parse_address(input)?;
+    let Some(start) = start else {
+        return Ok((None, input));
+    };
+    let Some(rest) = rest.strip_prefix(',') else {
+        let range = LineRange {
+            end: start.to_owned(),
+            start,
+        };
+        return Ok((Some(range), rest));
+    };
+    match parse_address(rest)? {
+        (Some(end), rest) => Ok((Some(LineRange { start, end }), rest)),
+        (None, _) => Err(CommandError::InvalidRange),
+    }
+}
+
+/// Reads an address such as `.`, `$`, `12` or `.+3`, if there is one.
+fn parse_address(input: &str) -> Result<(Option<Address>, &str), CommandError> {
+    let digits = |text: &str| text.chars().take_while(char::is_ascii_digit).count();
+    let (address, rest) = match input.chars().next() {
+        Some('.') => (Some(LineAddress::Current), &input[1..]),
+        Some('$') => (Some(LineAddress::Last), &input[1..]),
+        Some('0'..='9') => {
+            let len = digits(input);
+            let number = input[..len]
+                .parse()
+                .map_err(|_| CommandError::InvalidRange)?;
+            (Some(LineAddress::Number(number)), &input[len..])
+        }
+        Some('+' | '-') => (Some(LineAddress::Current), input),
+        _ => (None, input),
+    };
+    let Some(address) = address else {
+        return Ok((None, input));
+    };
+    let mut offset: isize = 0;
+    let mut rest = rest;
+    while let Some(sign @ ('+' | '-')) = rest.chars().next() {
+        let len = digits(&rest[1..]);
+        let amount: isize = match len {
+            0 => 1,
+            _ => rest[1..=len]
+                .parse()
+                .map_err(|_| CommandError::InvalidRange)?,
+        };
+        offset += if sign == '+' { amount } else { -amount };
+        rest = &rest[1 + len..];
+    }
+    Ok((Some((address, offset)), rest))
+}
+
+/// Turns the text typed on the command line (without the leading `:`) into
+/// the action it stands for.
+pub fn parse_command(input: &str) -> Result<EditorAction, CommandError> {
+    let (range, input) = parse_range(input.trim())?;
+    let input = input.trim();
+    if let Some(substitution) = parse_substitute(input, range.to_owned())? {
+        return Ok(EditorAction::Substitute(substitution));
+    }
+    if range.is_some() {
+        return Err(CommandError::RangeNotAllowed);
+    }
+    let (name, arg) = match input.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, Some(arg.trim())),
+        None => (input, None),
+    };
+
+    match name {
+        "w" | "write" => Ok(match arg {
+            Some(path) => EditorAction::SaveBufferAs(OsString::from(path)),
+            None => EditorAction::SaveBuffer,
+        }),
+        "q" | "quit" => no_argument(EditorAction::ExitEditor, arg),
+        "wq" | "x" | "xit" => no_argument(EditorAction::SaveAndExit, arg),
+        "e" | "edit" => match arg {
+            Some(path) => Ok(EditorAction::OpenFile(OsString::from(path))),
+            None => Err(CommandError::MissingArgument(name.to_owned())),
+        },
+        "tabe" | "tabedit" | "tabnew" => Ok(match arg {
+            Some(path) => EditorAction::OpenFileInTab(OsString::from(path)),
+            None => EditorAction::NewTab,
+        }),
+        "tabc" | "tabclose" => no_argument(EditorAction::CloseTab, arg),
+        "b" | "buffer" => match arg.map(str::parse) {
+            Some(Ok(number)) => Ok(EditorAction::SwitchBuffer(number)),
+            Some(Err(_)) => Err(CommandError::TrailingCharacters(
+                arg.unwrap_or_default().to_owned(),
+            )),
+            None => Err(CommandError::MissingArgument(name.to_owned())),
+        },
+        "bn" | "bnext" => no_argument(EditorAction::CycleBuffer(HorizontalDirection::Forward), arg),
+        "bp" | "bprevious" | "bN" | "bNext" => no_argument(
+            EditorAction::CycleBuffer(HorizontalDirection::Backward),
+            arg,
+        ),
+        "bd" | "bdelete" => no_argument(EditorAction::DeleteBuffer, arg),
+        "ls" | "buffers" => no_argument(EditorAction::EnterBufferPicker, arg),
+        "ff" | "fileformat" => match arg {
+            Some("unix") => Ok(EditorAction::SetLineEnding(LineEnding::Lf)),
+            Some("dos") => Ok(EditorAction::SetLineEnding(LineEnding::Crlf)),
+            Some(other) => Err(CommandError::InvalidArgument(other.to_owned())),
+            None => Err(CommandError::MissingArgument(name.to_owned())),
+        },
+        "delb" | "delbookmarks" => no_argument(EditorAction::ClearBookmarks, arg),
+        "mes" | "messages" => no_argument(EditorAction::ShowMessages, arg),
+        "noh" | "nohlsearch" => no_argument(EditorAction::ClearSearchHighlight, arg),
+        "sp" | "split" => no_argument(EditorAction::SplitWindow(Direction::Vertical), arg),
+        "vs" | "vsplit" => no_argument(EditorAction::SplitWindow(Direction::Horizontal), arg),
+        "clo" | "close" => no_argument(EditorAction::CloseWindow, arg),
+        "on" | "only" => no_argument(EditorAction::OnlyWindow, arg),
+        "res" | "resize" => resize(Direction::Vertical, name, arg),
+        "vert" | "vertical" => match arg.and_then(|arg| arg.split_once(char::is_whitespace)) {
+            Some(("res" | "resize", size)) => resize(Direction::Horizontal, name, Some(size)),
+            _ => Err(CommandError::MissingArgument(format!("{name} resize"))),
+        },
+        "tabn" | "tabnext" => {
+            no_argument(EditorAction::CycleTab(HorizontalDirection::Forward), arg)
+        }
+        "tabp" | "tabprevious" | "tabN" | "tabNext" => {
+            no_argument(EditorAction::CycleTab(HorizontalDirection::Backward), arg)
+        }
+        _ => Err(CommandError::UnknownCommand(name.to_owned())),
+    }
+}
+
+/// Reads the `+N` or `-N` of `:resize`.
+fn resize(
+    direction: Direction,
+    name: &str,
+    arg: Option<&str>,
+) -> Result<EditorAction, CommandError> {
+    let Some(arg) = arg else {
+        return Err(CommandError::MissingArgument(name.to_owned()));
+    };
+    let delta = match arg.trim().chars().next() {
+        Some('+' | '-') => arg.trim().parse().ok(),
+        _ => None,
+    };
+    match delta {
+        Some(delta) => Ok(EditorAction::ResizeWindow(direction, delta)),
+        None => Err(CommandError::TrailingCharacters(arg.to_owned())),
+    }
+}
+
+fn no_argument(action: EditorAction, arg: Option<&str>) -> Result<EditorAction, CommandError> {
+    match arg {
+        Some(arg) => Err(CommandError::TrailingCharacters(arg.to_owned())),
+        None => Ok(action),
+    }
+}
@@ -0,0 +1,462 @@
+use std::cmp::min;
+
+use crate::app::buffer::{
+    graphemes, Buffer, BufferPosition, HorizontalDirection, VerticalDirection,
+};
+
+/// A cursor movement that can also be used to select the text an operator
+/// acts on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    CurrentLine,
+    WordStart(WordType),
+    WordBackward(WordType),
+    WordEnd(WordType),
+    Paragraph(VerticalDirection),
+    FindChar(CharSearch),
+    /// Repeats the last character search; the editor resolves this into a
+    /// `FindChar` before the motion is performed.
+    RepeatFind,
+    RepeatFindReversed,
+    MatchingBracket,
+}
+
+/// How the text between the cursor and the target of a motion is selected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MotionKind {
+    /// The character under the target is not included.
+    Exclusive,
+    /// The character under the target is included.
+    Inclusive,
+    /// Whole lines from the cursor to the target are included.
+    Linewise,
+}
+
+/// A `word` is a run of letters, digits and underscores or a run of other
+/// non-blank characters, while a `WORD` is any run of non-blank characters.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordType {
+    Word,
+    BigWord,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FindKind {
+    /// Lands on the character (`f` and `F`).
+    Find,
+    /// Lands next to the character (`t` and `T`).
+    Till,
+}
+
+/// A search for a character within the current line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharSearch {
+    pub kind: FindKind,
+    pub dir: HorizontalDirection,
+    pub target: char,
+    /// Set when the search is repeated with `;` or `,`, so that `t` and `T`
+    /// skip over a match right next to the cursor instead of getting stuck.
+    pub repeated: bool,
+}
+
+impl CharSearch {
+    pub fn new(kind: FindKind, dir: HorizontalDirection, target: char) -> Self {
+        CharSearch {
+            kind,
+            dir,
+            target,
+            repeated: false,
+        }
+    }
+
+    /// The search performed by `;`, or by `,` when `reverse` is set.
+    pub fn repetition(&self, reverse: bool) -> Self {
+        let dir = match (&self.dir, reverse) {
+            (dir, false) => dir.clone(),
+            (HorizontalDirection::Forward, true) => HorizontalDirection::Backward,
+            (HorizontalDirection::Backward, true) => HorizontalDirection::Forward,
+        };
+        CharSearch {
+            dir,
+            repeated: true,
+            ..self.clone()
+        }
+    }
+}
+
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Blank,
+    Punctuation,
+    WordChar,
+}
+
+impl Motion {
+    pub fn kind(&self) -> MotionKind {
+        match self {
+            Motion::Left
+            | Motion::Right
+            | Motion::LineStart
+            | Motion::WordStart(_)
+            | Motion::WordBackward(_)
+            | Motion::Paragraph(_)
+            | Motion::RepeatFind
+            | Motion::RepeatFindReversed => MotionKind::Exclusive,
+            Motion::FindChar(search) => match search.dir {
+                HorizontalDirection::Forward => MotionKind::Inclusive,
+                HorizontalDirection::Backward => MotionKind::Exclusive,
+            },
+            Motion::LineEnd | Motion::WordEnd(_) | Motion::MatchingBracket => MotionKind::Inclusive,
+            Motion::Up
+            | Motion::Down
+            | Motion::FirstLine
+            | Motion::LastLine
+            | Motion::CurrentLine => MotionKind::Linewise,
+        }
+    }
+
+    /// Computes where the motion leads from `from`, or `None` if it cannot be
+    /// performed.
+    pub fn target(&self, buffer: &Buffer, from: &BufferPosition) -> Option<BufferPosition> {
+        let lines_count = buffer.lines_count();
+        if lines_count == 0 {
+            return None;
+        }
+        let BufferPosition { line, col } = *from;
+        let line_length = buffer.line_length(line).unwrap_or(0);
+        let target = match self {
+            Motion::Left if col > 0 => BufferPosition { line, col: col - 1 },
+            Motion::Right if col < line_length => BufferPosition { line, col: col + 1 },
+            Motion::Up if line > 0 => BufferPosition {
+                line: line - 1,
+                col,
+            },
+            Motion::Down if line + 1 < lines_count => BufferPosition {
+                line: line + 1,
+                col,
+            },
+            Motion::LineStart => BufferPosition { line, col: 0 },
+            Motion::LineEnd => BufferPosition {
+                line,
+                col: line_length.saturating_sub(1),
+            },
+            Motion::FirstLine => BufferPosition {
+                line: 0,
+                col: buffer.first_non_blank(0),
+            },
+            Motion::LastLine => BufferPosition {
+                line: lines_count - 1,
+                col: 0,
+            },
+            Motion::CurrentLine => from.clone(),
+            Motion::WordStart(word_type) => word_start(buffer, from, word_type),
+            Motion::WordBackward(word_type) => word_backward(buffer, from, word_type),
+            Motion::WordEnd(word_type) => word_end(buffer, from, word_type),
+            Motion::Paragraph(dir) => paragraph(buffer, line, dir),
+            Motion::FindChar(search) => find_char(buffer, from, search)?,
+            Motion::MatchingBracket => matching_bracket(buffer, from)?,
+            _ => return None,
+        };
+        Some(target)
+    }
+
+    /// Like `target`, but repeats the motion `count` times. Motions that
+    /// cannot be repeated as far as asked stop at the last position reached.
+    /// `G` and `gg` jump to line `count` instead.
+    pub fn counted_target(
+        &self,
+        buffer: &Buffer,
+        from: &BufferPosition,
+        count: Option<usize>,
+    ) -> Option<BufferPosition> {
+        let Some(count) = count else {
+            return self.target(buffer, from);
+        };
+        let last_line = buffer.lines_count().checked_sub(1)?;
+        match self {
+            Motion::FirstLine | Motion::LastLine => {
+                let line = min(count.saturating_sub(1), last_line);
+                Some(BufferPosition {
+                    line,
+                    col: buffer.first_non_blank(line),
+                })
+            }
+            Motion::CurrentLine | Motion::LineEnd => {
+                let line = min(from.line + count - 1, last_line);
+                let pos = BufferPosition { line, ..*from };
+                self.target(buffer, &pos)
+            }
+            _ => {
+                let mut pos = self.target(buffer, from)?;
+                let repeated = match self {
+                    Motion::FindChar(search) => Motion::FindChar(search.repetition(false)),
+                    _ => self.clone(),
+                };
+                for _ in 1..count {
+                    match repeated.target(buffer, &pos) {
+                        Some(next) => pos = next,
+                        None => break,
+                    }
+                }
+                Some(pos)
+            }
+        }
+    }
+}
+
+fn char_class(c: Option<char>, word_type: &WordType) -> CharClass {
+    match c {
+        None => CharClass::Blank,
+        Some(c) if c.is_whitespace() => CharClass::Blank,
+        Some(_) if *word_type == WordType::BigWord => CharClass::WordChar,
+        Some(c) if c.is_alphanumeric() || c == '_' => CharClass::WordChar,
+        Some(_) => CharClass::Punctuation,
+    }
+}
+
+/// Steps over every character of the buffer, treating the end of each line
+/// as a blank.
+fn next_position(buffer: &Buffer, pos: &BufferPosition) -> Option<BufferPosition> {
+    let len = buffer.line_length(pos.line)?;
+    if pos.col < len {
+        Some(BufferPosition {
+            line: pos.line,
+            col: pos.col + 1,
+        })
+    } else if pos.line + 1 < buffer.lines_count() {
+        Some(BufferPosition {
+            line: pos.line + 1,
+            col: 0,
+        })
+    } else {
+        None
+    }
+}
+
+fn previous_position(buffer: &Buffer, pos: &BufferPosition) -> Option<BufferPosition> {
+    if pos.col > 0 {
+        Some(BufferPosition {
+            line: pos.line,
+            col: pos.col - 1,
+        })
+    } else if pos.line > 0 {
+        Some(BufferPosition {
+            line: pos.line - 1,
+            col: buffer.line_length(pos.line - 1)?,
+        })
+    } else {
+        None
+    }
+}
+
+fn is_empty_line(buffer: &Buffer, line: usize) -> bool {
+    buffer.line_length(line) == Some(0)
+}
+
+fn last_position(buffer: &Buffer) -> BufferPosition {
+    let line = buffer.lines_count() - 1;
+    BufferPosition {
+        line,
+        col: buffer.line_length(line).unwrap_or(0),
+    }
+}
+
+fn word_start(buffer: &Buffer, from: &BufferPosition, word_type: &WordType) -> BufferPosition {
+    let class_at = |pos: &BufferPosition| char_class(buffer.char_at(pos), word_type);
+    let start_class = class_at(from);
+    let mut pos = from.clone();
+    if start_class != CharClass::Blank {
+        while class_at(&pos) == start_class {
+            match next_position(buffer, &pos) {
+                Some(next) => pos = next,
+                None => return last_position(buffer),
+            }
+        }
+    }
+    while class_at(&pos) == CharClass::Blank {
+        // An empty line counts as a word of its own
+        if pos.line != from.line && is_empty_line(buffer, pos.line) {
+            break;
+        }
+        match next_position(buffer, &pos) {
+            Some(next) => pos = next,
+            None => return last_position(buffer),
+        }
+    }
+    pos
+}
+
+/// The last character of the word under `pos`.
+pub fn current_word_end(
+    buffer: &Buffer,
+    pos: &BufferPosition,
+    word_type: &WordType,
+) -> BufferPosition {
+    let class_at = |pos: &BufferPosition| char_class(buffer.char_at(pos), word_type);
+    let class = class_at(pos);
+    let mut end = pos.clone();
+    while let Some(next) = next_position(buffer, &end) {
+        if class_at(&next) != class || next.line != pos.line {
+            break;
+        }
+        end = next;
+    }
+    end
+}
+
+fn word_end(buffer: &Buffer, from: &BufferPosition, word_type: &WordType) -> BufferPosition {
+    let class_at = |pos: &BufferPosition| char_class(buffer.char_at(pos), word_type);
+    let Some(mut pos) = next_position(buffer, from) else {
+        return from.clone();
+    };
+    while class_at(&pos) == CharClass::Blank {
+        match next_position(buffer, &pos) {
+            Some(next) => pos = next,
+            None => return from.clone(),
+        }
+    }
+    let class = class_at(&pos);
+    while let Some(next) = next_position(buffer, &pos) {
+        if class_at(&next) != class {
+            break;
+        }
+        pos = next;
+    }
+    pos
+}
+
+fn word_backward(buffer: &Buffer, from: &BufferPosition, word_type: &WordType) -> BufferPosition {
+    let class_at = |pos: &BufferPosition| char_class(buffer.char_at(pos), word_type);
+    let Some(mut pos) = previous_position(buffer, from) else {
+        return from.clone();
+    };
+    while class_at(&pos) == CharClass::Blank {
+        if pos.col == 0 && is_empty_line(buffer, pos.line) {
+            return pos;
+        }
+        match previous_position(buffer, &pos) {
+            Some(previous) => pos = previous,
+            None => return pos,
+        }
+    }
+    let class = class_at(&pos);
+    while let Some(previous) = previous_position(buffer, &pos) {
+        if class_at(&previous) != class {
+            break;
+        }
+        pos = previous;
+    }
+    pos
+}
+
+fn paragraph(buffer: &Buffer, line: usize, dir: &VerticalDirection) -> BufferPosition {
+    let lines_count = buffer.lines_count();
+    let mut line = line;
+    match dir {
+        VerticalDirection::Down => {
+            while line < lines_count && is_empty_line(buffer, line) {
+                line += 1;
+            }
+            while line < lines_count && !is_empty_line(buffer, line) {
+                line += 1;
+            }
+            if line >= lines_count {
+                return last_position(buffer);
+            }
+        }
+        VerticalDirection::Up => {
+            while line > 0 && is_empty_line(buffer, line) {
+                line -= 1;
+            }
+            while line > 0 && !is_empty_line(buffer, line) {
+                line -= 1;
+            }
+        }
+    }
+    BufferPosition { line, col: 0 }
+}
+
+fn find_char(
+    buffer: &Buffer,
+    from: &BufferPosition,
+    search: &CharSearch,
+) -> Option<BufferPosition> {
+    let chars = graphemes::base_chars(&buffer.line(from.line));
+    let skip = match search.kind {
+        FindKind::Till if search.repeated => 1,
+        _ => 0,
+    };
+    let col = match search.dir {
+        HorizontalDirection::Forward => {
+            let found = (from.col + 1 + skip..chars.len()).find(|&i| chars[i] == search.target)?;
+            match search.kind {
+                FindKind::Find => found,
+                FindKind::Till => found - 1,
+            }
+        }
+        HorizontalDirection::Backward => {
+            let found = (0..from.col.saturating_sub(skip))
+                .rev()
+                .find(|&i| chars[i] == search.target)?;
+            match search.kind {
+                FindKind::Find => found,
+                FindKind::Till => found + 1,
+            }
+        }
+    };
+    Some(BufferPosition {
+        line: from.line,
+        col,
+    })
+}
+
+/// Finds the bracket matching the first bracket at or after the cursor on the
+/// current line.
+fn matching_bracket(buffer: &Buffer, from: &BufferPosition) -> Option<BufferPosition> {
+    let line = graphemes::base_chars(&buffer.line(from.line));
+    let (col, bracket) = line
+        .iter()
+        .enumerate()
+        .skip(from.col)
+        .find(|(_, c)| BRACKET_PAIRS.iter().any(|&(o, cl)| **c == o || **c == cl))?;
+    let mut pos = BufferPosition {
+        line: from.line,
+        col,
+    };
+    let (open, close, forward) = BRACKET_PAIRS.iter().find_map(|&(open, close)| {
+        if *bracket == open {
+            Some((open, close, true))
+        } else if *bracket == close {
+            Some((open, close, false))
+        } else {
+            None
+        }
+    })?;
+
+    let mut depth = 0usize;
+    loop {
+        match buffer.char_at(&pos) {
+            Some(c) if c == open => depth = if forward { depth + 1 } else { depth - 1 },
+            Some(c) if c == close => depth = if forward { depth - 1 } else { depth + 1 },
+            _ => (),
+        }
+        if depth == 0 {
+            return Some(pos);
+        }
+        pos = if forward {
+            next_position(buffer, &pos)?
+        } else {
+            previous_position(buffer, &pos)?
+        };
+    }
+}
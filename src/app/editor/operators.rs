@@ -0,0 +1,321 @@
+use std::cmp::{max, min};
+
+use crate::app::buffer::{graphemes, BufferPosition, HorizontalDirection};
+
+use super::{
+    motions::{self, CharSearch, Motion, MotionKind},
+    Editor, Mode,
+};
+
+/// An action that is applied to the text covered by a motion or a selection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operator {
+    Change,
+    Delete,
+    Indent,
+    Outdent,
+    Yank,
+}
+
+/// Text that was yanked or deleted, ready to be pasted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+/// The text an operator acts on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextRange {
+    /// Characters from `start` up to, but not including, `end`.
+    Chars {
+        start: BufferPosition,
+        end: BufferPosition,
+    },
+    /// Whole lines from `first` to `last`, inclusive.
+    Lines { first: usize, last: usize },
+}
+
+impl Editor {
+    /// Waits for a motion to apply the operator to. Typing the same operator
+    /// twice applies it to the current line.
+    pub(super) fn start_operator(&mut self, op: Operator, count: Option<usize>) {
+        match self.get_mode() {
+            Mode::OperatorPending(pending) if *pending == op => {
+                let count = self.take_operator_count(count);
+                self.apply_operator(op, Motion::CurrentLine, count)
+            }
+            Mode::OperatorPending(_) => self.mode = Mode::Normal,
+            _ if self.current_buffer().is_empty() => (),
+            _ => {
+                self.operator_count = count;
+                self.mode = Mode::OperatorPending(op);
+            }
+        }
+    }
+
+    pub(super) fn execute_motion(&mut self, motion: Motion, count: Option<usize>) {
+        let Some(motion) = self.resolve_motion(motion) else {
+            if let Mode::OperatorPending(_) = self.get_mode() {
+                self.mode = Mode::Normal;
+            }
+            return;
+        };
+        match self.get_mode().to_owned() {
+            Mode::OperatorPending(op) => {
+                let count = self.take_operator_count(count);
+                self.apply_operator(op, motion, count)
+            }
+            _ => self.move_by(motion, count),
+        }
+    }
+
+    /// Remembers character searches and turns `;` and `,` into the search
+    /// they repeat. Returns `None` if there is nothing to repeat.
+    fn resolve_motion(&mut self, motion: Motion) -> Option<Motion> {
+        match motion {
+            Motion::FindChar(ref search) => {
+                self.last_char_search = Some(search.to_owned());
+                Some(motion)
+            }
+            Motion::RepeatFind => self.repeated_char_search(false),
+            Motion::RepeatFindReversed => self.repeated_char_search(true),
+            _ => Some(motion),
+        }
+    }
+
+    fn repeated_char_search(&self, reverse: bool) -> Option<Motion> {
+        let search: &CharSearch = self.last_char_search.as_ref()?;
+        Some(Motion::FindChar(search.repetition(reverse)))
+    }
+
+    fn move_by(&mut self, motion: Motion, count: Option<usize>) {
+        let cursor = self.current_bufpos();
+        let target = motion.counted_target(&self.current_buffer(), &cursor, count);
+        if let Some(target) = target {
+            self.restore_cursor(&target);
+            self.update_selection();
+        }
+    }
+
+    pub(super) fn apply_operator(&mut self, op: Operator, motion: Motion, count: Option<usize>) {
+        self.mode = Mode::Normal;
+        let cursor = self.current_bufpos();
+        let buffer = self.current_buffer();
+        let Some(mut target) = motion.counted_target(&buffer, &cursor, count) else {
+            return;
+        };
+        let mut kind = motion.kind();
+        if let Motion::WordStart(ref word_type) = motion {
+            let on_blank = buffer.char_at(&cursor).is_none_or(char::is_whitespace);
+            if op == Operator::Change && !on_blank {
+                // Like vim, `cw` changes only up to the end of the word
+                let word_end = motions::current_word_end(&buffer, &cursor, word_type);
+                let more_words = count.map(|count| count - 1).filter(|&count| count > 0);
+                target = match more_words {
+                    Some(count) => Motion::WordEnd(word_type.to_owned())
+                        .counted_target(&buffer, &word_end, Some(count))
+                        .unwrap_or(word_end),
+                    None => word_end,
+                };
+                kind = MotionKind::Inclusive;
+            } else if target.line > cursor.line && !on_blank {
+                // The last word of a line does not take the line break along
+                let line = target.line - 1;
+                target = BufferPosition {
+                    line,
+                    col: buffer.line_length(line).unwrap_or(0),
+                };
+            }
+        }
+        let start = min(&cursor, &target).to_owned();
+        let mut end = max(&cursor, &target).to_owned();
+        if kind == MotionKind::Exclusive && end.col == 0 && end.line > start.line {
+            // An exclusive motion ending at the start of a line stops at the
+            // end of the previous one instead
+            end = BufferPosition {
+                line: end.line - 1,
+                col: buffer.line_length(end.line - 1).unwrap_or(0),
+            };
+        }
+        let range = match kind {
+            MotionKind::Exclusive => TextRange::Chars { start, end },
+            // The end is included, but never the line break after it, which
+            // an empty line has nothing but
+            MotionKind::Inclusive => TextRange::Chars {
+                end: BufferPosition {
+                    col: min(end.col + 1, buffer.line_length(end.line).unwrap_or(0)),
+                    ..end
+                },
+                start,
+            },
+            MotionKind::Linewise => TextRange::Lines {
+                first: start.line,
+                last: end.line,
+            },
+        };
+        drop(buffer);
+        self.apply_operator_to_range(op, range);
+    }
+
+    pub(super) fn apply_operator_to_selection(&mut self, op: Operator) {
+        let Mode::Visual(sel) = self.get_mode().to_owned() else {
+            return;
+        };
+        self.mode = Mode::Normal;
+        let start = min(&sel.fixed_point, &sel.moving_point).to_owned();
+        let end = max(&sel.fixed_point, &sel.moving_point);
+        let end = self.current_buffer().position_after(end);
+        self.apply_operator_to_range(op, TextRange::Chars { start, end });
+    }
+
+    pub(super) fn apply_operator_to_range(&mut self, op: Operator, range: TextRange) {
+        let cursor = self.current_bufpos();
+        self.current_buffer_mut().begin_undo_group(&cursor);
+        match op {
+            Operator::Yank => {
+                self.register = Some(self.read_range(&range));
+                self.restore_cursor(&self.range_start(&range));
+            }
+            Operator::Delete => {
+                self.register = Some(self.read_range(&range));
+                self.delete_range(&range);
+                self.restore_cursor(&self.range_start(&range));
+            }
+            Operator::Change => {
+                self.register = Some(self.read_range(&range));
+                self.delete_range(&range);
+                if let TextRange::Lines { first, .. } = range {
+                    self.current_buffer_mut().insert_lines(first, "\n");
+                }
+                let start = self.range_start(&range);
+                self.current_winstate_mut().jump(&start);
+                // The insert session joins the undo group opened above
+                self.enter_insert();
+                return;
+            }
+            Operator::Indent | Operator::Outdent => {
+                let (first, last) = match range {
+                    TextRange::Chars { ref start, ref end } => (start.line, end.line),
+                    TextRange::Lines { first, last } => (first, last),
+                };
+                for line in first..=last {
+                    self.shift_line(line, &op);
+                }
+                let first_non_blank = self.current_buffer().first_non_blank(first);
+                self.restore_cursor(&BufferPosition {
+                    line: first,
+                    col: first_non_blank,
+                });
+            }
+        }
+        self.current_buffer_mut().end_undo_group();
+    }
+
+    fn read_range(&self, range: &TextRange) -> Register {
+        let buffer = self.current_buffer();
+        match range {
+            TextRange::Chars { start, end } => Register {
+                text: buffer.text_range(start, end),
+                linewise: false,
+            },
+            TextRange::Lines { first, last } => Register {
+                text: buffer.lines_text(*first, *last),
+                linewise: true,
+            },
+        }
+    }
+
+    fn delete_range(&mut self, range: &TextRange) {
+        let mut buffer = self.current_buffer_mut();
+        match range {
+            TextRange::Chars { start, end } => buffer.delete_range(start, end),
+            TextRange::Lines { first, last } => buffer.delete_lines(*first, *last),
+        }
+    }
+
+    fn range_start(&self, range: &TextRange) -> BufferPosition {
+        match range {
+            TextRange::Chars { start, .. } => start.to_owned(),
+            TextRange::Lines { first, .. } => {
+                let line = min(
+                    *first,
+                    self.current_buffer().lines_count().saturating_sub(1),
+                );
+                BufferPosition {
+                    line,
+                    col: self.current_buffer().first_non_blank(line),
+                }
+            }
+        }
+    }
+
+    /// Makes the indentation of `line` `shiftwidth` cells wider or
+    /// narrower, rewriting it with spaces or tabs as `expandtab` says.
+    fn shift_line(&mut self, line: usize, op: &Operator) {
+        let tabstop = self.current_winstate().settings.tabstop;
+        let mut buffer = self.current_buffer_mut();
+        let indent_end = BufferPosition {
+            line,
+            col: buffer.first_non_blank(line),
+        };
+        let width = buffer.display_col(&indent_end, tabstop);
+        let new_width = match op {
+            Operator::Indent if buffer.line_length(line) != Some(0) => width + self.shiftwidth,
+            Operator::Outdent => width.saturating_sub(self.shiftwidth),
+            _ => return,
+        };
+        let indent = match self.expandtab {
+            true => " ".repeat(new_width),
+            false => "\t".repeat(new_width / tabstop) + &" ".repeat(new_width % tabstop),
+        };
+        let start = BufferPosition { line, col: 0 };
+        if buffer.text_range(&start, &indent_end) != indent {
+            buffer.replace_range(&start, &indent_end, &indent);
+        }
+    }
+
+    pub(super) fn paste(&mut self, dir: HorizontalDirection) {
+        let Some(register) = self.register.clone() else {
+            return;
+        };
+        let cursor = self.current_bufpos();
+        self.current_buffer_mut().begin_undo_group(&cursor);
+        if register.linewise {
+            let line = match dir {
+                HorizontalDirection::Forward if !self.current_buffer().is_empty() => {
+                    cursor.line + 1
+                }
+                _ => cursor.line,
+            };
+            self.current_buffer_mut().insert_lines(line, &register.text);
+            let col = self.current_buffer().first_non_blank(line);
+            self.restore_cursor(&BufferPosition { line, col });
+        } else {
+            let line_length = self.current_buffer().line_length(cursor.line).unwrap_or(0);
+            let pos = match dir {
+                HorizontalDirection::Forward if line_length > 0 => BufferPosition {
+                    col: cursor.col + 1,
+                    ..cursor
+                },
+                _ => cursor,
+            };
+            self.current_buffer_mut().insert_text(&pos, &register.text);
+            let last_line = register.text.split('\n').next_back().unwrap_or_default();
+            let line_breaks = register.text.matches('\n').count();
+            let end = if line_breaks == 0 {
+                BufferPosition {
+                    line: pos.line,
+                    col: (pos.col + graphemes::count(last_line)).saturating_sub(1),
+                }
+            } else {
+                BufferPosition {
+                    line: pos.line + line_breaks,
+                    col: graphemes::count(last_line).saturating_sub(1),
+                }
+            };
+            self.restore_cursor(&end);
+        }
+        self.current_buffer_mut().end_undo_group();
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fs, path::Path};
+
+use crate::app::{swap::recoverable_swap, ui::floating_window::swap_recovery::SwapRecovery};
+
+use super::Editor;
+
+/// The lines of context shown around the part of the swap file that differs
+/// from the file.
+const DIFF_CONTEXT: usize = 2;
+
+/// Compares two texts by trimming the lines they start and end with, and
+/// lists the rest as lines removed from `old` and added in `new`.
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == old.len() && prefix == new.len() {
+        return vec!["  (no differences)".to_owned()];
+    }
+    let context_start = prefix.saturating_sub(DIFF_CONTEXT);
+    let context_end = (old.len() - suffix + DIFF_CONTEXT).min(old.len());
+    let mut diff = vec![format!("@@ line {} @@", prefix + 1)];
+    diff.extend(
+        old[context_start..prefix]
+            .iter()
+            .map(|line| format!("  {line}")),
+    );
+    diff.extend(
+        old[prefix..old.len() - suffix]
+            .iter()
+            .map(|line| format!("- {line}")),
+    );
+    diff.extend(
+        new[prefix..new.len() - suffix]
+            .iter()
+            .map(|line| format!("+ {line}")),
+    );
+    diff.extend(
+        old[old.len() - suffix..context_end]
+            .iter()
+            .map(|line| format!("  {line}")),
+    );
+    diff
+}
+
+impl Editor {
+    /// Looks for swap files left behind for the open buffers and offers to
+    /// recover them one after the other.
+    pub fn check_swap_files(&mut self) {
+        for index in 0..self.buffers.len() {
+            self.check_swap_file(index);
+        }
+    }
+
+    /// Offers to recover the swap file of the buffer at `index`, if it has
+    /// one more recent than its file.
+    pub(super) fn check_swap_file(&mut self, index: usize) {
+        let path = self.buffers[index].borrow().path().map(ToOwned::to_owned);
+        let swap = path
+            .zip(self.swap_files.dir())
+            .and_then(|(path, dir)| recoverable_swap(dir, Path::new(&path)));
+        if let Some(swap) = swap {
+            self.pending_recoveries.push((index, swap));
+            if self.floating_window.is_none() {
+                self.offer_next_recovery();
+            }
+        }
+    }
+
+    /// Shows the recovery dialog for the next swap file found, if any.
+    pub(crate) fn offer_next_recovery(&mut self) {
+        self.clear_floating_window();
+        if self.pending_recoveries.is_empty() {
+            return;
+        }
+        let (index, swap) = self.pending_recoveries.remove(0);
+        let swapped = match fs::read_to_string(&swap) {
+            Ok(text) => text,
+            Err(err) => {
+                self.show_error(format!("Could not read {}: {err}", swap.display()));
+                self.offer_next_recovery();
+                return;
+            }
+        };
+        let buffer = self.buffers[index].borrow();
+        let diff = diff_lines(&buffer.text().to_string(), &swapped);
+        let dialog = SwapRecovery::new(index, buffer.display_name(), swap, diff);
+        drop(buffer);
+        self.floating_window = Some(Box::new(dialog));
+    }
+
+    /// Replaces the text of the buffer at `index` with that of its swap
+    /// file. The buffer is left modified, as the file is not saved.
+    pub(crate) fn recover_swap(&mut self, index: usize, swap: &Path) {
+        match fs::read_to_string(swap) {
+            Ok(text) => {
+                self.buffers[index].borrow_mut().replace_text(text);
+                let name = self.buffers[index].borrow().display_name();
+                self.show_info(format!("Recovered {name}, save it to keep the changes"));
+            }
+            Err(err) => self.show_error(format!("Could not read {}: {err}", swap.display())),
+        }
+        self.offer_next_recovery();
+    }
+
+    pub(crate) fn delete_swap(&mut self, swap: &Path) {
+        if let Err(err) = fs::remove_file(swap) {
+            self.show_error(format!("Could not delete {}: {err}", swap.display()));
+        }
+        self.offer_next_recovery();
+    }
+
+    /// Writes the swap files that are due, reporting when that fails.
+    pub fn update_swap_files(&mut self) {
+        if let Err(err) = self.swap_files.update(&self.buffers) {
+            self.show_warning(format!("Could not write swap file: {err}"));
+        }
+    }
+
+    /// Deletes the swap files when the editor exits normally.
+    pub fn remove_swap_files(&mut self) {
+        self.swap_files.remove_all();
+    }
+}
@@ -0,0 +1,242 @@
+use std::{
+    cell::RefCell,
+    rc::{Rc, Weak},
+};
+
+use regex::{Regex, RegexBuilder};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::app::{
+    buffer::{Buffer, BufferPosition, HorizontalDirection},
+    ui::command_line::Prompt,
+};
+
+use super::{Editor, Mode};
+
+/// A search for a pattern, as typed after `/` or `?`.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub pattern: String,
+    pub regex: Regex,
+    pub dir: HorizontalDirection,
+}
+
+/// A search being typed, which the cursor returns to the start of if it is
+/// cancelled.
+#[derive(Debug)]
+pub struct PendingSearch {
+    origin: BufferPosition,
+    preview: Option<Regex>,
+}
+
+/// Compiles a search pattern. Case is ignored unless the pattern contains an
+/// uppercase letter.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+}
+
+/// The matches of `regex` in a line, as ranges of columns.
+pub fn line_matches(line: &str, regex: &Regex) -> Vec<(usize, usize)> {
+    // Matching columns by grapheme starts, found once, rather than
+    // counting from the start of the line for each match.
+    let starts: Vec<usize> = line.grapheme_indices(true).map(|(byte, _)| byte).collect();
+    let col = |byte: usize| starts.partition_point(|&start| start < byte);
+    regex
+        .find_iter(line)
+        .map(|found| (col(found.start()), col(found.end())))
+        .collect()
+}
+
+/// Where every match of `regex` in the buffer starts. Matches never span
+/// several lines.
+pub fn match_starts(buffer: &Buffer, regex: &Regex) -> Vec<BufferPosition> {
+    (0..buffer.lines_count())
+        .flat_map(|line| {
+            line_matches(&buffer.line(line), regex)
+                .into_iter()
+                .map(move |(col, _)| BufferPosition { line, col })
+        })
+        .collect()
+}
+
+/// The match starts last looked up, kept until their buffer changes or
+/// another pattern is looked up, so that the match count drawn on every
+/// frame doesn't search the whole buffer each time.
+#[derive(Debug, Default)]
+pub struct MatchCache {
+    /// The buffer, its version and the pattern the matches are those of.
+    /// Holding the buffer keeps its address from being reused by another.
+    key: Option<(Weak<RefCell<Buffer>>, usize, String)>,
+    starts: Rc<[BufferPosition]>,
+}
+
+/// The closest of `starts` after `from` in the direction of the search,
+/// wrapping around the ends of the buffer.
+fn next_match(
+    starts: &[BufferPosition],
+    from: &BufferPosition,
+    dir: &HorizontalDirection,
+) -> Option<BufferPosition> {
+    match dir {
+        HorizontalDirection::Forward => {
+            starts.iter().find(|&start| start > from).or(starts.first())
+        }
+        HorizontalDirection::Backward => starts
+            .iter()
+            .rev()
+            .find(|&start| start < from)
+            .or(starts.last()),
+    }
+    .cloned()
+}
+
+impl Editor {
+    pub(super) fn enter_search(&mut self, dir: HorizontalDirection) {
+        self.pending_search = Some(PendingSearch {
+            origin: self.current_bufpos(),
+            preview: None,
+        });
+        self.command_line.start(Prompt::Search(dir));
+        self.mode = Mode::Command;
+    }
+
+    /// Moves to the first match of what has been typed so far.
+    pub(super) fn update_incremental_search(&mut self) {
+        let Prompt::Search(dir) = self.command_line.prompt().to_owned() else {
+            return;
+        };
+        let Some(origin) = self.pending_search.as_ref().map(|s| s.origin.to_owned()) else {
+            return;
+        };
+        let input = self.command_line.input();
+        let preview = match input {
+            "" => None,
+            pattern => compile_pattern(pattern).ok(),
+        };
+        let target = preview
+            .as_ref()
+            .and_then(|regex| next_match(&self.match_starts(regex), &origin, &dir));
+        self.restore_cursor(&target.unwrap_or(origin));
+        if let Some(ref mut pending) = self.pending_search {
+            pending.preview = preview;
+        }
+    }
+
+    pub(super) fn cancel_search(&mut self) {
+        if let Some(pending) = self.pending_search.take() {
+            self.restore_cursor(&pending.origin);
+        }
+    }
+
+    /// Searches for a typed pattern, or for the last one again if nothing
+    /// was typed.
+    pub(super) fn finish_search(&mut self, input: &str, dir: HorizontalDirection) {
+        let Some(pending) = self.pending_search.take() else {
+            return;
+        };
+        self.restore_cursor(&pending.origin);
+        let pattern = match (input, &self.search) {
+            ("", Some(search)) => search.pattern.to_owned(),
+            ("", None) => {
+                self.show_error("No previous search pattern".to_owned());
+                return;
+            }
+            (pattern, _) => pattern.to_owned(),
+        };
+        match compile_pattern(&pattern) {
+            Ok(regex) => {
+                self.search = Some(Search {
+                    pattern,
+                    regex,
+                    dir: dir.to_owned(),
+                });
+                self.jump_to_match(&dir);
+            }
+            Err(err) => self.show_error(format!("Invalid pattern: {err}")),
+        }
+    }
+
+    /// Repeats the last search, in the opposite direction if `dir` is
+    /// backward.
+    pub(super) fn repeat_search(&mut self, dir: HorizontalDirection) {
+        let Some(ref search) = self.search else {
+            self.show_error("No previous search pattern".to_owned());
+            return;
+        };
+        let dir = match (&search.dir, dir) {
+            (search_dir, HorizontalDirection::Forward) => search_dir.to_owned(),
+            (HorizontalDirection::Forward, HorizontalDirection::Backward) => {
+                HorizontalDirection::Backward
+            }
+            (HorizontalDirection::Backward, HorizontalDirection::Backward) => {
+                HorizontalDirection::Forward
+            }
+        };
+        self.jump_to_match(&dir);
+    }
+
+    fn jump_to_match(&mut self, dir: &HorizontalDirection) {
+        let Some(search) = self.search.clone() else {
+            return;
+        };
+        self.highlight_search = true;
+        let cursor = self.current_bufpos();
+        let starts = self.match_starts(&search.regex);
+        let target = next_match(&starts, &cursor, dir);
+        match target {
+            Some(target) => {
+                self.restore_cursor(&target);
+                self.update_selection();
+            }
+            None => self.show_error(format!("Pattern not found: {}", search.pattern)),
+        }
+    }
+
+    pub(super) fn clear_search_highlight(&mut self) {
+        self.highlight_search = false;
+    }
+
+    /// The pattern whose matches are highlighted: the one being typed, or
+    /// else the last one searched for.
+    pub(super) fn highlighted_pattern(&self) -> Option<&Regex> {
+        match self.pending_search {
+            Some(ref pending) => pending.preview.as_ref(),
+            None if self.highlight_search => self.search.as_ref().map(|search| &search.regex),
+            None => None,
+        }
+    }
+
+    /// Which of the highlighted matches the cursor is on or past, and how
+    /// many there are.
+    pub(crate) fn search_count(&self) -> Option<(usize, usize)> {
+        let regex = self.highlighted_pattern()?;
+        let starts = self.match_starts(regex);
+        let cursor = self.current_bufpos();
+        let current = starts.iter().take_while(|&start| start <= &cursor).count();
+        Some((current, starts.len()))
+    }
+
+    /// The starts of the matches of `regex` in the current buffer, searched
+    /// for again only if the buffer or the pattern changed since last time.
+    fn match_starts(&self, regex: &Regex) -> Rc<[BufferPosition]> {
+        let buffer = &self.buffers[self.current_buffer_index()];
+        let version = buffer.borrow().version();
+        let mut cache = self.match_cache.borrow_mut();
+        let cached = cache
+            .key
+            .as_ref()
+            .is_some_and(|(cached, cached_version, pattern)| {
+                cached.ptr_eq(&Rc::downgrade(buffer))
+                    && *cached_version == version
+                    && pattern == regex.as_str()
+            });
+        if !cached {
+            cache.starts = match_starts(&buffer.borrow(), regex).into();
+            cache.key = Some((Rc::downgrade(buffer), version, regex.as_str().to_owned()));
+        }
+        Rc::clone(&cache.starts)
+    }
+}
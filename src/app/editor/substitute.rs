@@ -0,0 +1,361 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use regex::{Regex, RegexBuilder};
+
+use crate::app::{
+    buffer::{graphemes, Buffer, BufferPosition, HorizontalDirection},
+    cleanup::CleanUnwrap,
+};
+
+use super::{
+    commands::{CommandError, LineRange},
+    search::{compile_pattern, Search},
+    Editor,
+};
+
+/// A `:substitute` command, with its replacement already translated to the
+/// syntax of `regex`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub range: LineRange,
+    /// Empty to reuse the last search pattern.
+    pub pattern: String,
+    pub replacement: String,
+    pub flags: SubstituteFlags,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SubstituteFlags {
+    /// Replace every match on a line instead of only the first one (`g`).
+    pub global: bool,
+    /// Ask before each replacement (`c`).
+    pub confirm: bool,
+    /// Whether case is ignored (`i`) or not (`I`), instead of deciding from
+    /// the pattern.
+    pub ignore_case: Option<bool>,
+}
+
+/// A substitution going through the matches of its range, either all at
+/// once or one confirmation at a time.
+#[derive(Debug)]
+pub struct PendingSubstitution {
+    regex: Regex,
+    replacement: String,
+    global: bool,
+    /// Where the next match is looked for.
+    next: BufferPosition,
+    last_line: usize,
+    /// The match waiting for confirmation, as its start and the column it
+    /// ends at.
+    current: Option<(BufferPosition, usize)>,
+    last_replaced: Option<BufferPosition>,
+}
+
+/// Reads `s/pattern/replacement/flags`, with any punctuation as the
+/// delimiter. Returns nothing if `input` is some other command.
+pub fn parse_substitute(
+    input: &str,
+    range: Option<LineRange>,
+) -> Result<Option<Substitution>, CommandError> {
+    let name_len = input
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(input.len());
+    let (name, rest) = input.split_at(name_len);
+    if !matches!(name, "s" | "substitute") {
+        return Ok(None);
+    }
+    let mut chars = rest.chars();
+    let delimiter = match chars.next() {
+        None => return Err(CommandError::MissingArgument(name.to_owned())),
+        Some(c) if c.is_alphanumeric() || c.is_whitespace() || c == '\\' => {
+            return Err(CommandError::TrailingCharacters(rest.to_owned()))
+        }
+        Some(c) => c,
+    };
+    let rest = chars.as_str();
+    let (pattern, rest) = split_at_delimiter(rest, delimiter);
+    let (replacement, flags) = split_at_delimiter(rest.unwrap_or_default(), delimiter);
+    let mut parsed = SubstituteFlags::default();
+    for flag in flags.unwrap_or_default().trim_end().chars() {
+        match flag {
+            'g' => parsed.global = true,
+            'c' => parsed.confirm = true,
+            'i' => parsed.ignore_case = Some(true),
+            'I' => parsed.ignore_case = Some(false),
+            _ => return Err(CommandError::TrailingCharacters(flag.to_string())),
+        }
+    }
+    Ok(Some(Substitution {
+        range: range.unwrap_or_else(LineRange::current_line),
+        pattern,
+        replacement: translate_replacement(&replacement),
+        flags: parsed,
+    }))
+}
+
+/// Splits `text` at the first unescaped `delimiter`, unescaping the
+/// delimiter in the part before it.
+fn split_at_delimiter(text: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) if escaped == delimiter => part.push(escaped),
+                Some((_, escaped)) => {
+                    part.push('\\');
+                    part.push(escaped);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => return (part, Some(&text[i + c.len_utf8()..])),
+            c => part.push(c),
+        }
+    }
+    (part, None)
+}
+
+/// Turns `\1`, `&`, `\r` and friends into what `Captures::expand` expects.
+fn translate_replacement(replacement: &str) -> String {
+    let mut translated = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => translated.push_str("${0}"),
+            '$' => translated.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => translated.push_str(&format!("${{{digit}}}")),
+                Some('n' | 'r') => translated.push('\n'),
+                Some('t') => translated.push('\t'),
+                Some('$') => translated.push_str("$$"),
+                Some(escaped) => translated.push(escaped),
+                None => translated.push('\\'),
+            },
+            c => translated.push(c),
+        }
+    }
+    translated
+}
+
+impl PendingSubstitution {
+    /// Finds the next match in the range and makes it the current one.
+    fn find_next(&mut self, buffer: &Buffer) -> bool {
+        self.current = None;
+        while self.next.line <= self.last_line {
+            let BufferPosition { line, col } = self.next;
+            let text = buffer.line(line);
+            if col <= graphemes::count(&text) {
+                let start = graphemes::byte_offset(&text, col);
+                if let Some(found) = self.regex.find_at(&text, start) {
+                    let col = |byte: usize| graphemes::col_after_byte(&text, byte);
+                    let start = BufferPosition {
+                        line,
+                        col: col(found.start()),
+                    };
+                    self.current = Some((start, col(found.end())));
+                    return true;
+                }
+            }
+            self.next = BufferPosition {
+                line: line + 1,
+                col: 0,
+            };
+        }
+        false
+    }
+
+    /// Moves past the current match without replacing it.
+    fn skip(&mut self) {
+        if let Some((start, end)) = self.current.take() {
+            self.advance(&start, end, start.col == end);
+        }
+    }
+
+    /// Replaces the current match.
+    fn replace(&mut self, buffer: &mut Buffer) {
+        let Some((start, end)) = self.current.take() else {
+            return;
+        };
+        let text = buffer.line(start.line);
+        let captures = self
+            .regex
+            .captures_at(&text, graphemes::byte_offset(&text, start.col))
+            .clean_expect("the current match disappeared!");
+        let mut replacement = String::new();
+        captures.expand(&self.replacement, &mut replacement);
+        let end_pos = BufferPosition {
+            line: start.line,
+            col: end,
+        };
+        buffer.replace_range(&start, &end_pos, &replacement);
+        self.last_replaced = Some(start.clone());
+
+        // Line breaks in the replacement push the rest of the range down
+        let breaks = replacement.matches('\n').count();
+        self.last_line += breaks;
+        let last_part = replacement.rsplit('\n').next().unwrap_or_default();
+        let col = match breaks {
+            0 => start.col + graphemes::count(last_part),
+            _ => graphemes::count(last_part),
+        };
+        let after = BufferPosition {
+            line: start.line + breaks,
+            col,
+        };
+        self.advance(&after, col, start.col == end);
+    }
+
+    /// Continues after a match ending at column `end` of `pos.line`. Empty
+    /// matches step over a character so they are not found again.
+    fn advance(&mut self, pos: &BufferPosition, end: usize, empty: bool) {
+        self.next = match self.global {
+            true => BufferPosition {
+                line: pos.line,
+                col: end + usize::from(empty),
+            },
+            false => BufferPosition {
+                line: pos.line + 1,
+                col: 0,
+            },
+        };
+    }
+}
+
+impl Editor {
+    pub(super) fn substitute(&mut self, substitution: Substitution) {
+        let current = self.current_bufpos().line;
+        let lines_count = self.current_buffer().lines_count();
+        let (first_line, last_line) = match substitution.range.resolve(current, lines_count) {
+            Ok(lines) => lines,
+            Err(err) => {
+                self.show_error(err.to_string());
+                return;
+            }
+        };
+        let pattern = match (substitution.pattern.as_str(), &self.search) {
+            ("", Some(search)) => search.pattern.to_owned(),
+            ("", None) => {
+                self.show_error("No previous search pattern".to_owned());
+                return;
+            }
+            (pattern, _) => pattern.to_owned(),
+        };
+        let compiled = match substitution.flags.ignore_case {
+            Some(ignore_case) => RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build(),
+            None => compile_pattern(&pattern),
+        };
+        let regex = match compiled {
+            Ok(regex) => regex,
+            Err(err) => {
+                self.show_error(format!("Invalid pattern: {err}"));
+                return;
+            }
+        };
+        let dir = self
+            .search
+            .as_ref()
+            .map_or(HorizontalDirection::Forward, |search| search.dir.to_owned());
+        self.search = Some(Search {
+            pattern: pattern.to_owned(),
+            regex: regex.clone(),
+            dir,
+        });
+
+        let mut pending = PendingSubstitution {
+            regex,
+            replacement: substitution.replacement,
+            global: substitution.flags.global,
+            next: BufferPosition {
+                line: first_line,
+                col: 0,
+            },
+            last_line,
+            current: None,
+            last_replaced: None,
+        };
+        if !pending.find_next(&self.current_buffer()) {
+            self.show_error(format!("Pattern not found: {pattern}"));
+            return;
+        }
+        let cursor = self.current_bufpos();
+        self.current_buffer_mut().begin_undo_group(&cursor);
+        if substitution.flags.confirm {
+            self.pending_substitution = Some(pending);
+            self.show_substitution_prompt();
+            return;
+        }
+        loop {
+            pending.replace(&mut self.current_buffer_mut());
+            if !pending.find_next(&self.current_buffer()) {
+                break;
+            }
+        }
+        self.finish_substitution(pending);
+    }
+
+    /// Answers the confirmation asked for the current match: `y` replaces
+    /// it, `n` skips it, `a` replaces it and all the following ones, and `q`
+    /// or Esc stop.
+    pub(super) fn confirm_substitution(&mut self, key: &KeyEvent) {
+        let Some(mut pending) = self.pending_substitution.take() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('y') => pending.replace(&mut self.current_buffer_mut()),
+            KeyCode::Char('n') => pending.skip(),
+            KeyCode::Char('a') => loop {
+                pending.replace(&mut self.current_buffer_mut());
+                if !pending.find_next(&self.current_buffer()) {
+                    break;
+                }
+            },
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.finish_substitution(pending);
+                return;
+            }
+            _ => {
+                self.pending_substitution = Some(pending);
+                self.show_substitution_prompt();
+                return;
+            }
+        }
+        if pending.find_next(&self.current_buffer()) {
+            self.pending_substitution = Some(pending);
+            self.show_substitution_prompt();
+        } else {
+            self.finish_substitution(pending);
+        }
+    }
+
+    /// Moves to the match waiting for confirmation and asks about it.
+    fn show_substitution_prompt(&mut self) {
+        let Some((start, replacement)) = self
+            .pending_substitution
+            .as_ref()
+            .and_then(|pending| Some((pending.current.clone()?.0, pending.replacement.clone())))
+        else {
+            return;
+        };
+        self.restore_cursor(&start);
+        let replacement = replacement.replace("$$", "$").replace('\n', "\\r");
+        self.command_line
+            .set_message(format!("replace with {replacement} (y/n/a/q)?"));
+    }
+
+    fn finish_substitution(&mut self, pending: PendingSubstitution) {
+        self.current_buffer_mut().end_undo_group();
+        self.command_line.clear_message();
+        if let Some(ref pos) = pending.last_replaced {
+            self.restore_cursor(pos);
+        }
+    }
+
+    /// The match waiting for confirmation, as its start and the column it
+    /// ends at.
+    pub(super) fn substitution_match(&self) -> Option<(BufferPosition, usize)> {
+        self.pending_substitution
+            .as_ref()
+            .and_then(|pending| pending.current.clone())
+    }
+}
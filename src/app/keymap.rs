@@ -2,56 +2,382 @@ use super::{
     buffer::{
         HorizontalDirection::*, RectilinearDirection as Rectilinear, VerticalDirection as Vertical,
     },
-    editor::{actions::EditorAction, Mode},
-    ui::leader_menu::SubMenu,
+    editor::{
+        actions::EditorAction,
+        motions::{FindKind, Motion, WordType},
+        operators::Operator,
+        Mode,
+    },
+    ui::leader_menu::{KeyHint, SubMenu},
 };
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::crossterm::event::KeyEvent;
-use std::collections::HashMap;
+use ratatui::layout::Direction;
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use trie::{KeyTrie, Lookup};
 
 use crate::app::EditorAction::*;
 
-pub struct KeyMap {
-    normal_mode: HashMap<KeyCode, EditorAction>,
-    insert_mode: HashMap<KeyCode, EditorAction>,
-    root_menu: HashMap<KeyCode, EditorAction>,
-    visual_mode: HashMap<KeyCode, EditorAction>,
+mod parse;
+mod trie;
+
+/// The value that removes a default binding in a keymap file.
+const UNBIND: &str = "Unbind";
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(PathBuf, io::Error),
+    Syntax(PathBuf, toml::de::Error),
+    UnknownSection(String),
+    NotATable(String),
+    MalformedKeys {
+        section: String,
+        keys: String,
+        reason: String,
+    },
+    InvalidAction {
+        section: String,
+        keys: String,
+        reason: String,
+    },
 }
 
-impl KeyMap {
-    pub fn handle_key(&self, key: &KeyEvent, mode: &Mode) -> Option<EditorAction> {
-        match mode {
-            Mode::Insert => self.handle_insert_mode(key),
+impl Display for KeymapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeymapError::Io(path, err) => write!(f, "could not read {}: {err}", path.display()),
+            KeymapError::Syntax(path, err) => write!(f, "{}: {err}", path.display()),
+            KeymapError::UnknownSection(section) => write!(
+                f,
+                "unknown section [{section}], expected one of: {}",
+                SECTIONS.join(", ")
+            ),
+            KeymapError::NotATable(section) => {
+                write!(f, "[{section}] must be a table of key bindings")
+            }
+            KeymapError::MalformedKeys {
+                section,
+                keys,
+                reason,
+            } => write!(f, "[{section}] malformed key \"{keys}\": {reason}"),
+            KeymapError::InvalidAction {
+                section,
+                keys,
+                reason,
+            } => write!(f, "[{section}] \"{keys}\": {reason}"),
+        }
+    }
+}
 
-            Mode::Normal => self.normal_mode.get(&key.code).cloned(),
+const SECTIONS: [&str; 6] = [
+    "normal",
+    "insert",
+    "visual",
+    "operator_pending",
+    "command",
+    "root_menu",
+];
 
-            Mode::Command => todo!("commands not implemented yet"),
+/// How long an incomplete key sequence is waited on by default.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
 
-            Mode::Visual(_) => self.visual_mode.get(&key.code).cloned(),
+pub struct KeyMap {
+    normal_mode: KeyTrie,
+    command_mode: KeyTrie,
+    operator_pending_mode: KeyTrie,
+    insert_mode: KeyTrie,
+    root_menu: KeyTrie,
+    root_menu_hints: Vec<KeyHint>,
+    visual_mode: KeyTrie,
+    pending: Vec<KeyEvent>,
+    pending_since: Option<Instant>,
+    timeout: Option<Duration>,
+}
+
+impl KeyMap {
+    /// Feeds a key into the pending sequence and returns the actions it
+    /// completes, if any. A sequence that turns out not to be bound falls
+    /// back to the action of its longest bound prefix, or else to what its
+    /// keys do on their own, and its last key is then looked up on its own.
+    pub fn handle_key(&mut self, key: &KeyEvent, mode: &Mode) -> Vec<EditorAction> {
+        self.pending.push(*key);
+        match self.trie(mode).lookup(&self.pending) {
+            Lookup::Action(action) => {
+                self.clear_pending();
+                vec![action]
+            }
+            Lookup::Prefix { .. } => {
+                self.pending_since = Some(Instant::now());
+                vec![]
+            }
+            Lookup::NoMatch if self.pending.len() > 1 => {
+                let prefix = self.pending[..self.pending.len() - 1].to_vec();
+                let fallback = match self.trie(mode).lookup(&prefix) {
+                    Lookup::Prefix { fallback } => fallback,
+                    _ => None,
+                };
+                self.clear_pending();
+                let mut actions = match (fallback, mode) {
+                    (Some(action), _) => vec![action],
+                    // Anything that is not a motion cancels the pending operator
+                    (None, Mode::OperatorPending(_)) => return vec![SwitchToMode(Mode::Normal)],
+                    // The keys typed so far do what they would on their own,
+                    // such as being inserted
+                    (None, _) => Self::unbound_keys(&prefix, mode),
+                };
+                actions.extend(self.handle_key(key, mode));
+                actions
+            }
+            Lookup::NoMatch => {
+                self.clear_pending();
+                Self::unbound_key(key, mode).into_iter().collect()
+            }
+        }
+    }
+
+    /// What a key that is not bound to anything does in each mode.
+    fn unbound_key(key: &KeyEvent, mode: &Mode) -> Option<EditorAction> {
+        let modified = key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match (mode, key.code) {
+            (Mode::Insert, KeyCode::Char(c)) if !modified => Some(InsertChar(c)),
+            (Mode::Command, _) => Some(EditCommandLine(*key)),
+            (Mode::OperatorPending(_), _) => Some(SwitchToMode(Mode::Normal)),
+            _ => None,
         }
     }
 
+    /// What keys that are not bound to anything do when typed one after
+    /// the other.
+    fn unbound_keys(keys: &[KeyEvent], mode: &Mode) -> Vec<EditorAction> {
+        keys.iter()
+            .filter_map(|key| Self::unbound_key(key, mode))
+            .collect()
+    }
+
     pub fn handle_menu_input(&self, key: &KeyEvent, menu: &SubMenu) -> Option<EditorAction> {
-        let menu: &HashMap<KeyCode, EditorAction> = match menu {
+        let menu = match menu {
             SubMenu::Root => &self.root_menu,
+            SubMenu::Themes => return None,
         };
-        menu.get(&key.code).cloned()
+        match menu.lookup(&[*key]) {
+            Lookup::Action(action) => Some(action),
+            _ => None,
+        }
     }
 
-    fn handle_insert_mode(&self, key: &KeyEvent) -> Option<EditorAction> {
-        match key.code {
-            KeyCode::Char(c) => Some(EditorAction::InsertChar(c)),
-            _ => self.insert_mode.get(&key.code).cloned(),
+    /// Reads a keymap file and merges its bindings over the defaults,
+    /// leaving out those that could not be read, with the reasons why.
+    pub fn load(path: &Path) -> (Self, Vec<KeymapError>) {
+        let mut keymap = KeyMap::default();
+        let table = fs::read_to_string(path)
+            .map_err(|err| KeymapError::Io(path.to_owned(), err))
+            .and_then(|contents| {
+                toml::from_str::<toml::Table>(&contents)
+                    .map_err(|err| KeymapError::Syntax(path.to_owned(), err))
+            });
+        let errors = match table {
+            Ok(table) => keymap.merge(&table),
+            Err(err) => vec![err],
+        };
+        (keymap, errors)
+    }
+
+    /// Merges bindings over the current ones. Each section of `table` maps
+    /// key sequences to action names, or to `Unbind` to remove a binding.
+    /// Returns why each binding or section left out could not be merged.
+    pub fn merge(&mut self, table: &toml::Table) -> Vec<KeymapError> {
+        let mut errors = vec![];
+        for (section, bindings) in table {
+            if !SECTIONS.contains(&section.as_str()) {
+                errors.push(KeymapError::UnknownSection(section.to_owned()));
+                continue;
+            }
+            let toml::Value::Table(bindings) = bindings else {
+                errors.push(KeymapError::NotATable(section.to_owned()));
+                continue;
+            };
+            for (keys, action) in bindings {
+                if let Err(err) = self.merge_binding(section, keys, action) {
+                    errors.push(err);
+                }
+            }
+        }
+        errors
+    }
+
+    fn merge_binding(
+        &mut self,
+        section: &str,
+        keys: &str,
+        action: &toml::Value,
+    ) -> Result<(), KeymapError> {
+        let malformed = |reason: String| KeymapError::MalformedKeys {
+            section: section.to_owned(),
+            keys: keys.to_owned(),
+            reason,
+        };
+        let invalid = |reason: String| KeymapError::InvalidAction {
+            section: section.to_owned(),
+            keys: keys.to_owned(),
+            reason,
+        };
+        let sequence = parse::parse_keys(keys).map_err(malformed)?;
+        let toml::Value::String(action) = action else {
+            return Err(invalid(format!(
+                "expected an action name, found {}",
+                action.type_str()
+            )));
+        };
+        let cursor_mode = match section {
+            "insert" => Mode::Insert,
+            _ => Mode::Normal,
+        };
+        let action = match action.as_str() {
+            UNBIND => None,
+            action => Some(parse::parse_action(action, &cursor_mode).map_err(invalid)?),
+        };
+        if section == "root_menu" {
+            let [key] = sequence.as_slice() else {
+                return Err(malformed("menu entries must be a single key".to_owned()));
+            };
+            self.bind_menu_entry(key, action, keys);
+            return Ok(());
+        }
+        let trie = match section {
+            "normal" => &mut self.normal_mode,
+            "insert" => &mut self.insert_mode,
+            "visual" => &mut self.visual_mode,
+            "operator_pending" => &mut self.operator_pending_mode,
+            "command" => &mut self.command_mode,
+            _ => return Err(KeymapError::UnknownSection(section.to_owned())),
+        };
+        match action {
+            Some(action) => trie.insert_sequence(&sequence, action),
+            None => trie.remove_sequence(&sequence),
+        }
+        Ok(())
+    }
+
+    /// Binds a key in the leader menu, labelling it with the action name as
+    /// it was written in the keymap file.
+    fn bind_menu_entry(&mut self, key: &KeyEvent, action: Option<EditorAction>, label: &str) {
+        let key_hint = key_name(&trie::normalize(key));
+        self.root_menu_hints.retain(|hint| hint.key() != key_hint);
+        match action {
+            Some(action) => {
+                self.root_menu.insert_sequence(&[*key], action);
+                self.root_menu_hints
+                    .push(KeyHint::new(&key_hint, &humanize(label)));
+            }
+            None => self.root_menu.remove_sequence(&[*key]),
+        }
+    }
+
+    pub fn menu_hints(&self, menu: &SubMenu) -> &[KeyHint] {
+        match menu {
+            SubMenu::Root => &self.root_menu_hints,
+            SubMenu::Themes => &[],
+        }
+    }
+
+    pub fn has_pending_keys(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// The keys typed so far of an incomplete sequence, for display.
+    pub fn pending_keys(&self) -> String {
+        self.pending.iter().map(key_name).collect()
+    }
+
+    /// How much longer the pending sequence is waited on, if there is one
+    /// and a timeout is set.
+    pub fn time_left(&self) -> Option<Duration> {
+        let since = self.pending_since?;
+        Some(self.timeout?.saturating_sub(since.elapsed()))
+    }
+
+    /// Gives up on the pending sequence once it has timed out, returning the
+    /// action of the sequence typed so far if it is bound on its own, or else
+    /// what its keys do on their own.
+    pub fn flush_pending(&mut self, mode: &Mode) -> Vec<EditorAction> {
+        let fallback = match self.trie(mode).lookup(&self.pending) {
+            Lookup::Prefix { fallback } => fallback,
+            _ => None,
+        };
+        let pending = std::mem::take(&mut self.pending);
+        self.clear_pending();
+        match (fallback, mode) {
+            (Some(action), _) => vec![action],
+            (None, Mode::OperatorPending(_)) => vec![SwitchToMode(Mode::Normal)],
+            (None, _) => Self::unbound_keys(&pending, mode),
+        }
+    }
+
+    fn clear_pending(&mut self) {
+        self.pending.clear();
+        self.pending_since = None;
+    }
+
+    fn trie(&self, mode: &Mode) -> &KeyTrie {
+        match mode {
+            Mode::Normal => &self.normal_mode,
+            Mode::Command => &self.command_mode,
+            Mode::Insert => &self.insert_mode,
+            Mode::OperatorPending(_) => &self.operator_pending_mode,
+            Mode::Visual(_) => &self.visual_mode,
         }
     }
 }
 
+/// Turns an action name like `SaveBuffer` into `Save Buffer`. Actions with
+/// arguments are left as they are.
+fn humanize(name: &str) -> String {
+    if name.contains('(') {
+        return name.to_owned();
+    }
+    let mut label = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(char::is_lowercase) {
+            label.push(' ');
+        }
+        label.push(c);
+        previous = Some(c);
+    }
+    label
+}
+
+fn key_name(key: &KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "<Space>".to_owned(),
+        KeyCode::Char(c) => c.to_string(),
+        code => format!("<{code}>"),
+    };
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        format!("^{name}")
+    } else {
+        name
+    }
+}
+
 impl Default for KeyMap {
     fn default() -> Self {
-        let mut normal_mode = HashMap::new();
-        let mut root_menu = HashMap::new();
-        let mut insert_mode = HashMap::new();
-        let mut visual_mode = HashMap::new();
+        let mut normal_mode = KeyTrie::default();
+        let mut command_mode = KeyTrie::default();
+        let mut operator_pending_mode = KeyTrie::default();
+        let mut root_menu = KeyTrie::default();
+        let mut insert_mode = KeyTrie::default();
+        let mut visual_mode = KeyTrie::default();
+        insert_motions(&mut normal_mode);
+        insert_motions(&mut operator_pending_mode);
+        insert_motions(&mut visual_mode);
         normal_mode.insert(KeyCode::Char(' '), EnterMenu);
         normal_mode.insert(KeyCode::Char('i'), EnterInsert);
         normal_mode.insert(KeyCode::Char('I'), MoveToHomeAndEnterInsert);
@@ -78,6 +404,10 @@ impl Default for KeyMap {
             MoveCursor(Mode::Normal, Rectilinear::Right),
         );
         normal_mode.insert(KeyCode::Right, MoveCursor(Mode::Normal, Rectilinear::Right));
+        normal_mode.insert_sequence(&[key('g'), key('j')], MoveDisplayLine(Vertical::Down));
+        normal_mode.insert_sequence(&[key('g'), key('k')], MoveDisplayLine(Vertical::Up));
+        normal_mode.insert_sequence(&[key(']'), key('b')], GotoBookmark(Vertical::Down));
+        normal_mode.insert_sequence(&[key('['), key('b')], GotoBookmark(Vertical::Up));
         normal_mode.insert(KeyCode::Char('$'), EOL);
         normal_mode.insert(KeyCode::Char('0'), Home);
         normal_mode.insert(KeyCode::Char('G'), EndOfBuffer);
@@ -88,10 +418,38 @@ impl Default for KeyMap {
         normal_mode.insert(KeyCode::Char('x'), RemoveChar(Forward));
         normal_mode.insert(KeyCode::Char('X'), RemoveChar(Backward));
         normal_mode.insert(KeyCode::Char('v'), EnterVisual);
+        normal_mode.insert(KeyCode::Char(':'), EnterCommand);
+        normal_mode.insert(KeyCode::Char('/'), EnterSearch(Forward));
+        normal_mode.insert(KeyCode::Char('?'), EnterSearch(Backward));
+        normal_mode.insert(KeyCode::Char('n'), RepeatSearch(Forward));
+        normal_mode.insert(KeyCode::Char('N'), RepeatSearch(Backward));
+        normal_mode.insert(KeyCode::Char('u'), Undo);
+        normal_mode.insert(KeyCode::Char('d'), StartOperator(Operator::Delete));
+        normal_mode.insert(KeyCode::Char('c'), StartOperator(Operator::Change));
+        normal_mode.insert(KeyCode::Char('y'), StartOperator(Operator::Yank));
+        normal_mode.insert(KeyCode::Char('>'), StartOperator(Operator::Indent));
+        normal_mode.insert(KeyCode::Char('<'), StartOperator(Operator::Outdent));
+        normal_mode.insert(
+            KeyCode::Char('D'),
+            ApplyOperator(Operator::Delete, Motion::LineEnd),
+        );
+        normal_mode.insert(
+            KeyCode::Char('C'),
+            ApplyOperator(Operator::Change, Motion::LineEnd),
+        );
+        normal_mode.insert(
+            KeyCode::Char('Y'),
+            ApplyOperator(Operator::Yank, Motion::CurrentLine),
+        );
+        normal_mode.insert(KeyCode::Char('p'), Paste(Forward));
+        normal_mode.insert(KeyCode::Char('P'), Paste(Backward));
+        normal_mode.insert_sequence(&[ctrl('r')], Redo);
         normal_mode.insert(KeyCode::Enter, NextLine);
+        insert_window_commands(&mut normal_mode);
         normal_mode.insert(KeyCode::Backspace, Back);
         insert_mode.insert(KeyCode::Esc, ExitInsert);
         insert_mode.insert(KeyCode::Enter, InsertLineBreak);
+        insert_mode.insert(KeyCode::Tab, InsertTab);
         insert_mode.insert(KeyCode::Delete, RemoveChar(Forward));
         insert_mode.insert(KeyCode::Backspace, RemoveChar(Backward));
         insert_mode.insert(KeyCode::Left, MoveCursor(Mode::Insert, Rectilinear::Left));
@@ -100,11 +458,38 @@ impl Default for KeyMap {
         insert_mode.insert(KeyCode::Down, MoveCursor(Mode::Insert, Rectilinear::Down));
         insert_mode.insert(KeyCode::Home, Home);
         insert_mode.insert(KeyCode::End, EOL);
+        command_mode.insert(KeyCode::Esc, ExitCommand);
+        command_mode.insert(KeyCode::Enter, ExecuteCommand);
+        command_mode.insert(KeyCode::Up, BrowseCommandHistory(Vertical::Up));
+        command_mode.insert(KeyCode::Down, BrowseCommandHistory(Vertical::Down));
+        operator_pending_mode.insert(KeyCode::Char('d'), StartOperator(Operator::Delete));
+        operator_pending_mode.insert(KeyCode::Char('c'), StartOperator(Operator::Change));
+        operator_pending_mode.insert(KeyCode::Char('y'), StartOperator(Operator::Yank));
+        operator_pending_mode.insert(KeyCode::Char('>'), StartOperator(Operator::Indent));
+        operator_pending_mode.insert(KeyCode::Char('<'), StartOperator(Operator::Outdent));
+        operator_pending_mode.insert(KeyCode::Char('h'), Motion(Motion::Left));
+        operator_pending_mode.insert(KeyCode::Left, Motion(Motion::Left));
+        operator_pending_mode.insert(KeyCode::Char('j'), Motion(Motion::Down));
+        operator_pending_mode.insert(KeyCode::Down, Motion(Motion::Down));
+        operator_pending_mode.insert(KeyCode::Char('k'), Motion(Motion::Up));
+        operator_pending_mode.insert(KeyCode::Up, Motion(Motion::Up));
+        operator_pending_mode.insert(KeyCode::Char('l'), Motion(Motion::Right));
+        operator_pending_mode.insert(KeyCode::Right, Motion(Motion::Right));
+        operator_pending_mode.insert(KeyCode::Char('0'), Motion(Motion::LineStart));
+        operator_pending_mode.insert(KeyCode::Char('$'), Motion(Motion::LineEnd));
+        operator_pending_mode.insert(KeyCode::Char('G'), Motion(Motion::LastLine));
         root_menu.insert(KeyCode::Esc, ExitMenu);
         root_menu.insert(KeyCode::Char(' '), ExitMenu);
+        root_menu.insert(KeyCode::Char('b'), EnterBufferPicker);
+        root_menu.insert(KeyCode::Char('m'), ToggleBookmark);
+        root_menu.insert(KeyCode::Char('n'), CycleLineNumbers);
         root_menu.insert(KeyCode::Char('q'), ExitEditor);
+        root_menu.insert(KeyCode::Char('t'), EnterThemeMenu);
         root_menu.insert(KeyCode::Char('w'), SaveBuffer);
+        root_menu.insert(KeyCode::Char('z'), ToggleWrap);
         visual_mode.insert(KeyCode::Char('I'), MoveToHomeAndEnterInsert);
+        visual_mode.insert(KeyCode::Char('n'), RepeatSearch(Forward));
+        visual_mode.insert(KeyCode::Char('N'), RepeatSearch(Backward));
         visual_mode.insert(KeyCode::Char('S'), ReplaceLine);
         visual_mode.insert(
             KeyCode::Char('h'),
@@ -126,6 +511,8 @@ impl Default for KeyMap {
             MoveCursor(Mode::Normal, Rectilinear::Right),
         );
         visual_mode.insert(KeyCode::Right, MoveCursor(Mode::Normal, Rectilinear::Right));
+        visual_mode.insert_sequence(&[key('g'), key('j')], MoveDisplayLine(Vertical::Down));
+        visual_mode.insert_sequence(&[key('g'), key('k')], MoveDisplayLine(Vertical::Up));
         visual_mode.insert(KeyCode::Char('$'), EOL);
         visual_mode.insert(KeyCode::Char('0'), Home);
         visual_mode.insert(KeyCode::Char('G'), EndOfBuffer);
@@ -133,12 +520,147 @@ impl Default for KeyMap {
         visual_mode.insert(KeyCode::Backspace, Back);
         visual_mode.insert(KeyCode::Esc, ExitVisual);
         visual_mode.insert(KeyCode::Char('v'), ExitVisual);
+        visual_mode.insert(
+            KeyCode::Char('d'),
+            ApplyOperatorToSelection(Operator::Delete),
+        );
+        visual_mode.insert(
+            KeyCode::Char('x'),
+            ApplyOperatorToSelection(Operator::Delete),
+        );
+        visual_mode.insert(
+            KeyCode::Char('c'),
+            ApplyOperatorToSelection(Operator::Change),
+        );
+        visual_mode.insert(
+            KeyCode::Char('s'),
+            ApplyOperatorToSelection(Operator::Change),
+        );
+        visual_mode.insert(KeyCode::Char('y'), ApplyOperatorToSelection(Operator::Yank));
+        visual_mode.insert(
+            KeyCode::Char('>'),
+            ApplyOperatorToSelection(Operator::Indent),
+        );
+        visual_mode.insert(
+            KeyCode::Char('<'),
+            ApplyOperatorToSelection(Operator::Outdent),
+        );
 
         KeyMap {
+            command_mode,
             insert_mode,
             normal_mode,
+            operator_pending_mode,
             root_menu,
+            root_menu_hints: vec![
+                KeyHint::new("b", "Buffers"),
+                KeyHint::new("m", "Bookmark"),
+                KeyHint::new("n", "Line Numbers"),
+                KeyHint::new("q", "Quit"),
+                KeyHint::new("t", "Switch Theme"),
+                KeyHint::new("w", "Save Buffer"),
+                KeyHint::new("z", "Toggle Wrap"),
+            ],
             visual_mode,
+            pending: vec![],
+            pending_since: None,
+            timeout: Some(DEFAULT_TIMEOUT),
         }
     }
 }
+
+/// Binds the motions that behave the same in normal, visual and
+/// operator-pending modes.
+fn insert_motions(map: &mut KeyTrie) {
+    map.insert(
+        KeyCode::Char('w'),
+        Motion(Motion::WordStart(WordType::Word)),
+    );
+    map.insert(
+        KeyCode::Char('W'),
+        Motion(Motion::WordStart(WordType::BigWord)),
+    );
+    map.insert(
+        KeyCode::Char('b'),
+        Motion(Motion::WordBackward(WordType::Word)),
+    );
+    map.insert(
+        KeyCode::Char('B'),
+        Motion(Motion::WordBackward(WordType::BigWord)),
+    );
+    map.insert(KeyCode::Char('e'), Motion(Motion::WordEnd(WordType::Word)));
+    map.insert(
+        KeyCode::Char('E'),
+        Motion(Motion::WordEnd(WordType::BigWord)),
+    );
+    map.insert(KeyCode::Char('{'), Motion(Motion::Paragraph(Vertical::Up)));
+    map.insert(
+        KeyCode::Char('}'),
+        Motion(Motion::Paragraph(Vertical::Down)),
+    );
+    map.insert(KeyCode::Char('f'), StartCharSearch(FindKind::Find, Forward));
+    map.insert(
+        KeyCode::Char('F'),
+        StartCharSearch(FindKind::Find, Backward),
+    );
+    map.insert(KeyCode::Char('t'), StartCharSearch(FindKind::Till, Forward));
+    map.insert(
+        KeyCode::Char('T'),
+        StartCharSearch(FindKind::Till, Backward),
+    );
+    map.insert(KeyCode::Char(';'), Motion(Motion::RepeatFind));
+    map.insert(KeyCode::Char(','), Motion(Motion::RepeatFindReversed));
+    map.insert(KeyCode::Char('%'), Motion(Motion::MatchingBracket));
+    map.insert_sequence(&[key('g'), key('g')], Motion(Motion::FirstLine));
+}
+
+fn key(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+/// Binds the window commands, which all start with `Ctrl-W` like in vim.
+fn insert_window_commands(trie: &mut KeyTrie) {
+    let key = |c: char| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+    let bindings = [
+        (key('s'), SplitWindow(Direction::Vertical)),
+        (key('S'), SplitWindow(Direction::Vertical)),
+        (ctrl('s'), SplitWindow(Direction::Vertical)),
+        (key('v'), SplitWindow(Direction::Horizontal)),
+        (ctrl('v'), SplitWindow(Direction::Horizontal)),
+        (key('c'), CloseWindow),
+        (key('q'), CloseWindow),
+        (key('o'), OnlyWindow),
+        (ctrl('o'), OnlyWindow),
+        (key('w'), CycleWindow(Forward)),
+        (ctrl('w'), CycleWindow(Forward)),
+        (key('W'), CycleWindow(Backward)),
+        (key('h'), FocusWindow(Rectilinear::Left)),
+        (ctrl('h'), FocusWindow(Rectilinear::Left)),
+        (key('j'), FocusWindow(Rectilinear::Down)),
+        (ctrl('j'), FocusWindow(Rectilinear::Down)),
+        (key('k'), FocusWindow(Rectilinear::Up)),
+        (ctrl('k'), FocusWindow(Rectilinear::Up)),
+        (key('l'), FocusWindow(Rectilinear::Right)),
+        (ctrl('l'), FocusWindow(Rectilinear::Right)),
+        (key('+'), ResizeWindow(Direction::Vertical, 1)),
+        (key('-'), ResizeWindow(Direction::Vertical, -1)),
+        (key('>'), ResizeWindow(Direction::Horizontal, 1)),
+        (key('<'), ResizeWindow(Direction::Horizontal, -1)),
+    ];
+    for (key, action) in bindings {
+        trie.insert_sequence(&[ctrl('w'), key], action);
+    }
+    for (code, dir) in [
+        (KeyCode::Left, Rectilinear::Left),
+        (KeyCode::Down, Rectilinear::Down),
+        (KeyCode::Up, Rectilinear::Up),
+        (KeyCode::Right, Rectilinear::Right),
+    ] {
+        let key = KeyEvent::new(code, KeyModifiers::NONE);
+        trie.insert_sequence(&[ctrl('w'), key], FocusWindow(dir));
+    }
+}
+
+fn ctrl(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
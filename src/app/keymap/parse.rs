@@ -0,0 +1,360 @@
+use std::{ffi::OsString, iter::Peekable, str::Chars};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::layout::Direction;
+
+use crate::app::{
+    buffer::{
+        HorizontalDirection as Horizontal, LineEnding, RectilinearDirection as Rectilinear,
+        VerticalDirection as Vertical,
+    },
+    editor::{
+        actions::EditorAction,
+        motions::{FindKind, Motion, WordType},
+        operators::Operator,
+        Mode,
+    },
+};
+
+/// Parses a key sequence written the way vim does, e.g. `gg`, `<C-s>` or
+/// `<Space>w`. A literal `<` is written `<lt>`, unless it is the only key.
+pub fn parse_keys(input: &str) -> Result<Vec<KeyEvent>, String> {
+    if input.is_empty() {
+        return Err("empty key sequence".to_owned());
+    }
+    if input == "<" {
+        return Ok(vec![KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE)]);
+    }
+    let mut keys = vec![];
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            continue;
+        }
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                Some('>') => break,
+                Some(c) => name.push(c),
+                None => return Err(format!("missing `>` after `<{name}`")),
+            }
+        }
+        keys.push(parse_special_key(&name)?);
+    }
+    Ok(keys)
+}
+
+fn parse_special_key(name: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    // A trailing `-` is the key itself, as in `<C-->`
+    while let Some((modifier, tail)) = rest.split_once('-').filter(|(_, tail)| !tail.is_empty()) {
+        modifiers |= match modifier.to_ascii_uppercase().as_str() {
+            "C" => KeyModifiers::CONTROL,
+            "A" | "M" => KeyModifiers::ALT,
+            "S" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier `{modifier}` in `<{name}>`")),
+        };
+        rest = tail;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            modifiers.remove(KeyModifiers::SHIFT);
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "lt" => KeyCode::Char('<'),
+            "esc" => KeyCode::Esc,
+            "cr" | "enter" | "return" => KeyCode::Enter,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::BackTab
+            }
+            "tab" => KeyCode::Tab,
+            "bs" | "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "insert" => KeyCode::Insert,
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key `<{name}>`")),
+            },
+        },
+    };
+    Ok(KeyEvent::new(code, modifiers))
+}
+
+/// An action as written in a keymap file: a name, optionally followed by
+/// arguments in parentheses, e.g. `ApplyOperator(Delete, WordStart(Word))`.
+#[derive(Debug)]
+struct ActionExpr {
+    name: String,
+    args: Vec<ActionExpr>,
+}
+
+fn parse_expr(chars: &mut Peekable<Chars>) -> Result<ActionExpr, String> {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    let mut name = String::new();
+    if chars.next_if_eq(&'\'').is_some() {
+        // A quoted character, which may be one of `(),`
+        name.extend(chars.next());
+        if chars.next() != Some('\'') {
+            return Err("unterminated character literal".to_owned());
+        }
+    } else {
+        while let Some(c) = chars.next_if(|c| !matches!(c, '(' | ')' | ',')) {
+            name.push(c);
+        }
+    }
+    let name = name.trim().to_owned();
+    if name.is_empty() {
+        return Err("missing action name".to_owned());
+    }
+    let mut args = vec![];
+    if chars.next_if_eq(&'(').is_some() {
+        loop {
+            args.push(parse_expr(chars)?);
+            match chars.next() {
+                Some(',') => continue,
+                Some(')') => break,
+                _ => return Err(format!("missing `)` after the arguments of `{name}`")),
+            }
+        }
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    Ok(ActionExpr { name, args })
+}
+
+/// Parses the name of an `EditorAction` along with its arguments.
+/// `cursor_mode` is the mode that `MoveCursor` moves the cursor in.
+pub fn parse_action(input: &str, cursor_mode: &Mode) -> Result<EditorAction, String> {
+    let mut chars = input.chars().peekable();
+    let expr = parse_expr(&mut chars)?;
+    if let Some(c) = chars.next() {
+        return Err(format!("unexpected `{c}` in `{input}`"));
+    }
+    to_action(&expr, cursor_mode)
+}
+
+/// Matches an action expression against the forms each action takes.
+/// Every name listed is known, so one given the wrong arguments is told
+/// apart from an unknown action without keeping a separate list of names.
+macro_rules! match_action {
+    ($expr:expr, { $(($name:literal, $args:pat) => $action:expr,)* }) => {
+        match ($expr.name.as_str(), $expr.args.as_slice()) {
+            $(($name, $args) => $action,)*
+            (name, args) if [$($name),*].contains(&name) => {
+                return Err(format!("wrong arguments for `{name}` (got {})", args.len()))
+            }
+            (name, _) => return Err(format!("unknown action `{name}`")),
+        }
+    };
+}
+
+fn to_action(expr: &ActionExpr, cursor_mode: &Mode) -> Result<EditorAction, String> {
+    use EditorAction::*;
+    let action = match_action!(expr, {
+        ("Append", []) => Append,
+        ("AppendAtEOL", []) => AppendAtEOL,
+        ("ApplyOperator", [op, motion]) => ApplyOperator(to_operator(op)?, to_motion(motion)?),
+        ("ApplyOperatorToSelection", [op]) => ApplyOperatorToSelection(to_operator(op)?),
+        ("Back", []) => Back,
+        ("BrowseCommandHistory", [dir]) => BrowseCommandHistory(to_vertical(dir)?),
+        ("ClearBookmarks", []) => ClearBookmarks,
+        ("ClearSearchHighlight", []) => ClearSearchHighlight,
+        ("CloseTab", []) => CloseTab,
+        ("CloseWindow", []) => CloseWindow,
+        ("CycleBuffer", [dir]) => CycleBuffer(to_horizontal(dir)?),
+        ("CycleLineNumbers", []) => CycleLineNumbers,
+        ("CycleTab", [dir]) => CycleTab(to_horizontal(dir)?),
+        ("CycleWindow", [dir]) => CycleWindow(to_horizontal(dir)?),
+        ("DeleteBuffer", []) => DeleteBuffer,
+        ("EndOfBuffer", []) => EndOfBuffer,
+        ("EnterBufferPicker", []) => EnterBufferPicker,
+        ("EnterCommand", []) => EnterCommand,
+        ("EnterInsert", []) => EnterInsert,
+        ("EnterMenu", []) => EnterMenu,
+        ("EnterSearch", [dir]) => EnterSearch(to_horizontal(dir)?),
+        ("EnterThemeMenu", []) => EnterThemeMenu,
+        ("EnterVisual", []) => EnterVisual,
+        ("EOL", []) => EOL,
+        ("FocusWindow", [dir]) => FocusWindow(to_rectilinear(dir)?),
+        ("GotoBookmark", [dir]) => GotoBookmark(to_vertical(dir)?),
+        ("ExecuteCommand", []) => ExecuteCommand,
+        ("ExitCommand", []) => ExitCommand,
+        ("ExitEditor", []) => ExitEditor,
+        ("ExitInsert", []) => ExitInsert,
+        ("ExitMenu", []) => ExitMenu,
+        ("ExitVisual", []) => ExitVisual,
+        ("Home", []) => Home,
+        ("InsertChar", [c]) => InsertChar(to_char(c)?),
+        ("InsertLineBreak", []) => InsertLineBreak,
+        ("InsertTab", []) => InsertTab,
+        ("InsertNewLine", [dir]) => InsertNewLine(to_vertical(dir)?),
+        ("Motion", [motion]) => Motion(to_motion(motion)?),
+        ("MoveCursor", [dir]) => MoveCursor(cursor_mode.to_owned(), to_rectilinear(dir)?),
+        ("MoveDisplayLine", [dir]) => MoveDisplayLine(to_vertical(dir)?),
+        ("MoveToHomeAndEnterInsert", []) => MoveToHomeAndEnterInsert,
+        ("NewTab", []) => NewTab,
+        ("NextLine", []) => NextLine,
+        ("OnlyWindow", []) => OnlyWindow,
+        ("OpenFile", [path]) => OpenFile(OsString::from(&path.name)),
+        ("OpenFileInTab", [path]) => OpenFileInTab(OsString::from(&path.name)),
+        ("Paste", [dir]) => Paste(to_horizontal(dir)?),
+        ("RemoveChar", [dir]) => RemoveChar(to_horizontal(dir)?),
+        ("RepeatSearch", [dir]) => RepeatSearch(to_horizontal(dir)?),
+        ("Redo", []) => Redo,
+        ("ReplaceLine", []) => ReplaceLine,
+        ("ResizeWindow", [direction, delta]) => {
+            ResizeWindow(to_direction(direction)?, to_int(delta)?)
+        },
+        ("SaveAndExit", []) => SaveAndExit,
+        ("SaveBuffer", []) => SaveBuffer,
+        ("SaveBufferAs", [path]) => SaveBufferAs(OsString::from(&path.name)),
+        ("SetLineEnding", [ending]) => SetLineEnding(to_line_ending(ending)?),
+        ("SetTheme", [name]) => SetTheme(name.name.to_owned()),
+        ("ShowMessages", []) => ShowMessages,
+        ("SplitWindow", [direction]) => SplitWindow(to_direction(direction)?),
+        ("StartCharSearch", [kind, dir]) => {
+            StartCharSearch(to_find_kind(kind)?, to_horizontal(dir)?)
+        },
+        ("StartOperator", [op]) => StartOperator(to_operator(op)?),
+        ("SwitchBuffer", [number]) => SwitchBuffer(to_int(number)?),
+        ("ToggleBookmark", []) => ToggleBookmark,
+        ("ToggleWrap", []) => ToggleWrap,
+        ("Undo", []) => Undo,
+    });
+    Ok(action)
+}
+
+fn no_args(expr: &ActionExpr) -> Result<&str, String> {
+    match expr.args.as_slice() {
+        [] => Ok(&expr.name),
+        _ => Err(format!("`{}` takes no arguments", expr.name)),
+    }
+}
+
+fn to_operator(expr: &ActionExpr) -> Result<Operator, String> {
+    match no_args(expr)? {
+        "Change" => Ok(Operator::Change),
+        "Delete" => Ok(Operator::Delete),
+        "Indent" => Ok(Operator::Indent),
+        "Outdent" => Ok(Operator::Outdent),
+        "Yank" => Ok(Operator::Yank),
+        other => Err(format!("unknown operator `{other}`")),
+    }
+}
+
+fn to_motion(expr: &ActionExpr) -> Result<Motion, String> {
+    let motion = match (expr.name.as_str(), expr.args.as_slice()) {
+        ("Left", []) => Motion::Left,
+        ("Right", []) => Motion::Right,
+        ("Up", []) => Motion::Up,
+        ("Down", []) => Motion::Down,
+        ("LineStart", []) => Motion::LineStart,
+        ("LineEnd", []) => Motion::LineEnd,
+        ("FirstLine", []) => Motion::FirstLine,
+        ("LastLine", []) => Motion::LastLine,
+        ("CurrentLine", []) => Motion::CurrentLine,
+        ("WordStart", [word]) => Motion::WordStart(to_word_type(word)?),
+        ("WordBackward", [word]) => Motion::WordBackward(to_word_type(word)?),
+        ("WordEnd", [word]) => Motion::WordEnd(to_word_type(word)?),
+        ("Paragraph", [dir]) => Motion::Paragraph(to_vertical(dir)?),
+        ("RepeatFind", []) => Motion::RepeatFind,
+        ("RepeatFindReversed", []) => Motion::RepeatFindReversed,
+        ("MatchingBracket", []) => Motion::MatchingBracket,
+        (name, _) => return Err(format!("unknown motion `{name}`")),
+    };
+    Ok(motion)
+}
+
+fn to_word_type(expr: &ActionExpr) -> Result<WordType, String> {
+    match no_args(expr)? {
+        "Word" => Ok(WordType::Word),
+        "BigWord" => Ok(WordType::BigWord),
+        other => Err(format!("expected `Word` or `BigWord`, found `{other}`")),
+    }
+}
+
+fn to_find_kind(expr: &ActionExpr) -> Result<FindKind, String> {
+    match no_args(expr)? {
+        "Find" => Ok(FindKind::Find),
+        "Till" => Ok(FindKind::Till),
+        other => Err(format!("expected `Find` or `Till`, found `{other}`")),
+    }
+}
+
+fn to_horizontal(expr: &ActionExpr) -> Result<Horizontal, String> {
+    match no_args(expr)? {
+        "Forward" => Ok(Horizontal::Forward),
+        "Backward" => Ok(Horizontal::Backward),
+        other => Err(format!("expected `Forward` or `Backward`, found `{other}`")),
+    }
+}
+
+fn to_line_ending(expr: &ActionExpr) -> Result<LineEnding, String> {
+    match no_args(expr)? {
+        "Lf" => Ok(LineEnding::Lf),
+        "Crlf" => Ok(LineEnding::Crlf),
+        other => Err(format!("expected `Lf` or `Crlf`, found `{other}`")),
+    }
+}
+
+fn to_vertical(expr: &ActionExpr) -> Result<Vertical, String> {
+    match no_args(expr)? {
+        "Up" => Ok(Vertical::Up),
+        "Down" => Ok(Vertical::Down),
+        other => Err(format!("expected `Up` or `Down`, found `{other}`")),
+    }
+}
+
+fn to_rectilinear(expr: &ActionExpr) -> Result<Rectilinear, String> {
+    match no_args(expr)? {
+        "Left" => Ok(Rectilinear::Left),
+        "Right" => Ok(Rectilinear::Right),
+        "Up" => Ok(Rectilinear::Up),
+        "Down" => Ok(Rectilinear::Down),
+        other => Err(format!(
+            "expected `Left`, `Right`, `Up` or `Down`, found `{other}`"
+        )),
+    }
+}
+
+/// The direction windows are laid out in: `Vertical` stacks them.
+fn to_direction(expr: &ActionExpr) -> Result<Direction, String> {
+    match no_args(expr)? {
+        "Horizontal" => Ok(Direction::Horizontal),
+        "Vertical" => Ok(Direction::Vertical),
+        other => Err(format!(
+            "expected `Horizontal` or `Vertical`, found `{other}`"
+        )),
+    }
+}
+
+fn to_int<T: std::str::FromStr>(expr: &ActionExpr) -> Result<T, String> {
+    no_args(expr)?
+        .parse()
+        .map_err(|_| format!("expected a number, found `{}`", expr.name))
+}
+
+fn to_char(expr: &ActionExpr) -> Result<char, String> {
+    let mut chars = no_args(expr)?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!(
+            "expected a single character, found `{}`",
+            expr.name
+        )),
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::app::editor::actions::EditorAction;
+
+/// The outcome of looking up a key sequence.
+pub enum Lookup {
+    /// The sequence is bound to an action and nothing longer starts with it.
+    Action(EditorAction),
+    /// The sequence is the start of a longer binding. `fallback` is the
+    /// action to perform if it ends up not being completed.
+    Prefix { fallback: Option<EditorAction> },
+    /// No binding starts with the sequence.
+    NoMatch,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    action: Option<EditorAction>,
+    children: HashMap<KeyEvent, TrieNode>,
+}
+
+/// Bindings from sequences of keys, modifiers included, to actions.
+#[derive(Default)]
+pub struct KeyTrie {
+    root: TrieNode,
+}
+
+/// Strips everything but the key code and modifiers from a key event. Shift
+/// is dropped for characters, since it is already reflected in their case.
+pub fn normalize(key: &KeyEvent) -> KeyEvent {
+    let mut modifiers = key.modifiers;
+    if let KeyCode::Char(_) | KeyCode::BackTab = key.code {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    KeyEvent::new(key.code, modifiers)
+}
+
+impl KeyTrie {
+    /// Binds a single key without modifiers.
+    pub fn insert(&mut self, code: KeyCode, action: EditorAction) {
+        self.insert_sequence(&[KeyEvent::new(code, KeyModifiers::NONE)], action);
+    }
+
+    pub fn insert_sequence(&mut self, keys: &[KeyEvent], action: EditorAction) {
+        let mut node = &mut self.root;
+        for key in keys {
+            node = node.children.entry(normalize(key)).or_default();
+        }
+        node.action = Some(action);
+    }
+
+    /// Removes the binding of a sequence, keeping longer bindings that start
+    /// with it.
+    pub fn remove_sequence(&mut self, keys: &[KeyEvent]) {
+        let mut node = &mut self.root;
+        for key in keys {
+            match node.children.get_mut(&normalize(key)) {
+                Some(child) => node = child,
+                None => return,
+            }
+        }
+        node.action = None;
+    }
+
+    pub fn lookup(&self, keys: &[KeyEvent]) -> Lookup {
+        let mut node = &self.root;
+        for key in keys {
+            match node.children.get(&normalize(key)) {
+                Some(child) => node = child,
+                None => return Lookup::NoMatch,
+            }
+        }
+        match (&node.action, node.children.is_empty()) {
+            (Some(action), true) => Lookup::Action(action.clone()),
+            (fallback, false) => Lookup::Prefix {
+                fallback: fallback.clone(),
+            },
+            (None, true) => Lookup::NoMatch,
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app::{
+        keymap::{KeyMap, KeymapError},
+        theme::{Theme, ThemeError},
+        ui::line_numbers::LineNumberType,
+    },
+    config::Config,
+};
+
+const KEYS: [&str; 11] = [
+    "theme",
+    "tab_width",
+    "tabstop",
+    "shiftwidth",
+    "expandtab",
+    "line_numbers",
+    "scrolloff",
+    "wrap",
+    "linebreak",
+    "showbreak",
+    "backup",
+];
+const MAX_TAB_WIDTH: usize = 16;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, io::Error),
+    Syntax(PathBuf, toml::de::Error),
+    Invalid(PathBuf, InvalidSetting),
+    Keymap(PathBuf, KeymapError),
+    Theme(ThemeError),
+}
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Io(path, err) => write!(f, "could not read {}: {err}", path.display()),
+            SettingsError::Syntax(path, err) => write!(f, "{}: {err}", path.display()),
+            SettingsError::Invalid(path, err) => write!(f, "{}: {err}", path.display()),
+            // Those already tell the file
+            SettingsError::Keymap(_, err @ (KeymapError::Io(..) | KeymapError::Syntax(..))) => {
+                write!(f, "{err}")
+            }
+            SettingsError::Keymap(path, err) => write!(f, "{}: {err}", path.display()),
+            SettingsError::Theme(err) => write!(f, "--theme: {err}"),
+        }
+    }
+}
+
+/// A setting with an unknown name or a value it cannot take.
+#[derive(Debug)]
+pub struct InvalidSetting {
+    pub key: String,
+    pub reason: String,
+}
+
+impl Display for InvalidSetting {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\": {}", self.key, self.reason)
+    }
+}
+
+/// Settings that every text window starts out with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSettings {
+    pub line_numbers: LineNumberType,
+    /// How many lines are kept visible above and below the cursor.
+    pub scrolloff: usize,
+    /// How many cells apart the stops that tabs reach are.
+    pub tabstop: usize,
+    /// Whether long lines continue on the following rows instead of
+    /// scrolling sideways.
+    pub wrap: bool,
+    /// Whether wrapped lines are broken between words where possible.
+    pub linebreak: bool,
+    /// Drawn at the start of the rows that wrapped lines continue on.
+    pub showbreak: String,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        WindowSettings {
+            line_numbers: LineNumberType::Hybrid,
+            scrolloff: 0,
+            tabstop: 4,
+            wrap: false,
+            linebreak: false,
+            showbreak: String::new(),
+        }
+    }
+}
+
+/// Everything the editor is configured with on startup.
+pub struct Settings {
+    pub theme: Theme,
+    pub keymap: KeyMap,
+    /// How many columns `>`, `<` and the Tab key indent by.
+    pub shiftwidth: usize,
+    /// Whether indenting inserts spaces rather than tabs.
+    pub expandtab: bool,
+    pub window: WindowSettings,
+    /// Whether saving keeps the previous contents of a file in `file~`.
+    pub backup: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: Theme::default(),
+            keymap: KeyMap::default(),
+            shiftwidth: 4,
+            expandtab: true,
+            window: WindowSettings::default(),
+            backup: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Applies the configuration files, the theme and the keymap file
+    /// picked by `config` over the defaults.
+    pub fn load(config: &Config) -> (Self, Vec<SettingsError>) {
+        Settings::load_from(
+            &config.settings_files(),
+            config.theme.as_deref(),
+            config.keymap_file().as_deref(),
+        )
+    }
+
+    /// Applies the settings files in order, then the theme and keymap
+    /// given. Whatever cannot be applied is left at its previous value and
+    /// reported, so that a mistake costs only the setting it is in.
+    pub fn load_from(
+        settings_files: &[PathBuf],
+        theme: Option<&str>,
+        keymap_file: Option<&Path>,
+    ) -> (Self, Vec<SettingsError>) {
+        let mut settings = Settings::default();
+        let mut errors = vec![];
+        for path in settings_files {
+            errors.extend(settings.merge_file(path));
+        }
+        if let Some(name) = theme {
+            match Theme::named(name) {
+                Ok(theme) => settings.theme = theme,
+                Err(err) => errors.push(SettingsError::Theme(err)),
+            }
+        }
+        if let Some(path) = keymap_file {
+            let (keymap, keymap_errors) = KeyMap::load(path);
+            settings.keymap = keymap;
+            errors.extend(
+                keymap_errors
+                    .into_iter()
+                    .map(|err| SettingsError::Keymap(path.to_owned(), err)),
+            );
+        }
+        (settings, errors)
+    }
+
+    fn merge_file(&mut self, path: &Path) -> Vec<SettingsError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => return vec![SettingsError::Io(path.to_owned(), err)],
+        };
+        let table = match toml::from_str::<toml::Table>(&contents) {
+            Ok(table) => table,
+            Err(err) => return vec![SettingsError::Syntax(path.to_owned(), err)],
+        };
+        self.merge(&table)
+            .into_iter()
+            .map(|err| SettingsError::Invalid(path.to_owned(), err))
+            .collect()
+    }
+
+    /// Overrides the settings present in `table`, leaving the others as
+    /// they are. Returns why each setting left as it was is invalid.
+    pub fn merge(&mut self, table: &toml::Table) -> Vec<InvalidSetting> {
+        let mut errors = vec![];
+        for (key, value) in table {
+            if let Err(err) = self.merge_setting(key, value) {
+                errors.push(err);
+            }
+        }
+        errors
+    }
+
+    fn merge_setting(&mut self, key: &str, value: &toml::Value) -> Result<(), InvalidSetting> {
+        let invalid = |reason: String| InvalidSetting {
+            key: key.to_owned(),
+            reason,
+        };
+        match key {
+            "theme" => {
+                let name = expect_str(value).map_err(invalid)?;
+                self.theme = Theme::named(name).map_err(|err| invalid(err.to_string()))?;
+            }
+            // Sets both, for tabs as wide as the indentation
+            "tab_width" => {
+                let width = expect_tab_width(value).map_err(invalid)?;
+                self.window.tabstop = width;
+                self.shiftwidth = width;
+            }
+            "tabstop" => self.window.tabstop = expect_tab_width(value).map_err(invalid)?,
+            "shiftwidth" => self.shiftwidth = expect_tab_width(value).map_err(invalid)?,
+            "expandtab" => self.expandtab = expect_bool(value).map_err(invalid)?,
+            "line_numbers" => {
+                self.window.line_numbers = match expect_str(value).map_err(invalid)? {
+                    "absolute" => LineNumberType::Absolute,
+                    "relative" => LineNumberType::Relative,
+                    "hybrid" => LineNumberType::Hybrid,
+                    "off" => LineNumberType::Off,
+                    other => {
+                        return Err(invalid(format!(
+                            "unknown style \"{other}\", expected \"absolute\", \"relative\", \"hybrid\" or \"off\""
+                        )))
+                    }
+                };
+            }
+            "scrolloff" => self.window.scrolloff = expect_int(value).map_err(invalid)?,
+            "wrap" => self.window.wrap = expect_bool(value).map_err(invalid)?,
+            "linebreak" => self.window.linebreak = expect_bool(value).map_err(invalid)?,
+            "showbreak" => {
+                self.window.showbreak = expect_str(value).map_err(invalid)?.to_owned();
+            }
+            "backup" => self.backup = expect_bool(value).map_err(invalid)?,
+            _ => {
+                return Err(invalid(format!(
+                    "unknown setting, expected one of: {}",
+                    KEYS.join(", ")
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expect_str(value: &toml::Value) -> Result<&str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("expected a string, found {}", value.type_str()))
+}
+
+fn expect_bool(value: &toml::Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("expected true or false, found {}", value.type_str()))
+}
+
+fn expect_int(value: &toml::Value) -> Result<usize, String> {
+    let int = value
+        .as_integer()
+        .ok_or_else(|| format!("expected a number, found {}", value.type_str()))?;
+    usize::try_from(int).map_err(|_| "must not be negative".to_owned())
+}
+
+fn expect_tab_width(value: &toml::Value) -> Result<usize, String> {
+    let width = expect_int(value)?;
+    if !(1..=MAX_TAB_WIDTH).contains(&width) {
+        return Err(format!("must be between 1 and {MAX_TAB_WIDTH}"));
+    }
+    Ok(width)
+}
@@ -0,0 +1,189 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{self, Path, PathBuf},
+    process,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::config::state_dir;
+
+use super::buffer::Buffer;
+
+/// Where swap files live, inside the state directory.
+const SWAP_DIR_NAME: &str = "swap";
+/// How long changes may go without being written to the swap files.
+const SWAP_INTERVAL: Duration = Duration::from_secs(2);
+
+pub fn swap_dir() -> PathBuf {
+    state_dir()
+        .unwrap_or_else(|| env::temp_dir().join("minimodal"))
+        .join(SWAP_DIR_NAME)
+}
+
+/// The swap file of the file at `path`: its absolute path with the
+/// separators escaped, so that files with the same name do not clash.
+pub fn swap_path(dir: &Path, path: &Path) -> PathBuf {
+    let path = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+    let mut name = OsString::new();
+    for component in path.to_string_lossy().split(path::MAIN_SEPARATOR) {
+        if !name.is_empty() || !component.is_empty() {
+            name.push(component.replace('%', "%%"));
+            name.push("%");
+        }
+    }
+    name.push("swp");
+    dir.join(name)
+}
+
+/// The swap file left behind for the file at `path`, if it holds changes
+/// more recent than the file itself.
+pub fn recoverable_swap(dir: &Path, path: &Path) -> Option<PathBuf> {
+    let swap = swap_path(dir, path);
+    let swap_time = fs::metadata(&swap).and_then(|meta| meta.modified()).ok()?;
+    match fs::metadata(path).and_then(|meta| meta.modified()) {
+        Ok(file_time) if file_time >= swap_time => None,
+        _ => Some(swap),
+    }
+}
+
+/// The swap file of the buffer with `id`, which has no file. The process id
+/// keeps it from clashing with those of another editor, and the leading `%`
+/// from those of files.
+pub fn untitled_swap_path(dir: &Path, id: usize) -> PathBuf {
+    dir.join(format!("%untitled%{}%{id}%swp", process::id()))
+}
+
+/// Keeps a copy of every modified buffer in the swap directory, updated
+/// periodically, so that changes survive the editor being killed.
+#[derive(Debug, Default)]
+pub struct SwapFiles {
+    /// Where swap files are written, if they are at all.
+    dir: Option<PathBuf>,
+    /// How long changes may wait before being written.
+    interval: Duration,
+    /// The swap file and the version last written of each buffer, by
+    /// buffer id.
+    written: HashMap<usize, (PathBuf, usize)>,
+    /// When the pending changes are due to be written.
+    due: Option<Instant>,
+    /// Whether writing has failed, so that it is only reported once.
+    failed: bool,
+}
+
+impl SwapFiles {
+    pub fn new(dir: PathBuf, interval: Duration) -> Self {
+        SwapFiles {
+            dir: Some(dir),
+            interval,
+            ..SwapFiles::default()
+        }
+    }
+
+    /// Swap files in the state directory, written a little after changes.
+    pub fn in_state_dir() -> Self {
+        SwapFiles::new(swap_dir(), SWAP_INTERVAL)
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// How long until the pending changes are written, if there are any.
+    pub fn time_left(&self) -> Option<Duration> {
+        Some(self.due?.saturating_duration_since(Instant::now()))
+    }
+
+    /// Schedules writing the buffers changed since their swap files were
+    /// last written.
+    pub fn note_changes(&mut self, buffers: &[Rc<RefCell<Buffer>>]) {
+        if self.due.is_none() && buffers.iter().any(|buffer| self.is_stale(&buffer.borrow())) {
+            self.due = Some(Instant::now() + self.interval);
+        }
+    }
+
+    /// Where the swap file of `buffer` goes, which changes along with its
+    /// file.
+    fn swap_path(&self, buffer: &Buffer) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        Some(match buffer.path() {
+            Some(path) => swap_path(dir, Path::new(path)),
+            None => untitled_swap_path(dir, buffer.id()),
+        })
+    }
+
+    fn is_stale(&self, buffer: &Buffer) -> bool {
+        let Some(swap) = self.swap_path(buffer) else {
+            return false;
+        };
+        match (buffer.is_modified(), self.written.get(&buffer.id())) {
+            (true, Some((written, version))) => *written != swap || *version != buffer.version(),
+            (true, None) | (false, Some(_)) => true,
+            (false, None) => false,
+        }
+    }
+
+    /// Writes the swap files of the changed buffers once they are due, and
+    /// removes those of the buffers that were saved. Returns the first error
+    /// met, unless writing already failed before.
+    pub fn update(&mut self, buffers: &[Rc<RefCell<Buffer>>]) -> io::Result<()> {
+        if self.time_left().is_none_or(|left| !left.is_zero()) {
+            return Ok(());
+        }
+        self.due = None;
+        let mut result = Ok(());
+        for buffer in buffers {
+            let buffer = buffer.borrow();
+            if !self.is_stale(&buffer) {
+                continue;
+            }
+            let written = match buffer.is_modified() {
+                true => self.write(&buffer),
+                false => self.remove(&buffer),
+            };
+            if let Err(err) = written {
+                if result.is_ok() && !self.failed {
+                    result = Err(err);
+                }
+                self.failed = true;
+            }
+        }
+        result
+    }
+
+    fn write(&mut self, buffer: &Buffer) -> io::Result<()> {
+        let (Some(dir), Some(swap)) = (&self.dir, self.swap_path(buffer)) else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        buffer.write_copy(&swap)?;
+        let previous = self
+            .written
+            .insert(buffer.id(), (swap.clone(), buffer.version()));
+        // The buffer was saved under another name since
+        if let Some((previous, _)) = previous.filter(|(previous, _)| *previous != swap) {
+            let _ = fs::remove_file(previous);
+        }
+        Ok(())
+    }
+
+    /// Deletes the swap file of `buffer`, if one was written.
+    pub fn remove(&mut self, buffer: &Buffer) -> io::Result<()> {
+        if let Some((swap, _)) = self.written.remove(&buffer.id()) {
+            fs::remove_file(swap)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes every swap file written, when the editor exits normally.
+    pub fn remove_all(&mut self) {
+        for (swap, _) in self.written.values() {
+            let _ = fs::remove_file(swap);
+        }
+        self.written.clear();
+    }
+}
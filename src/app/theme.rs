@@ -1,9 +1,59 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    str::FromStr,
+};
+
 use ratatui::style::{Color, Modifier, Style};
 
+use crate::{app::cleanup::CleanUnwrap, config::config_dir};
+
+/// The themes that ship with the editor. Each theme file is applied over
+/// the first one, so it only needs to list what it changes.
+const BUILTIN_THEMES: [(&str, &str); 3] = [
+    ("default", include_str!("theme/default.toml")),
+    ("light", include_str!("theme/light.toml")),
+    ("ansi16", include_str!("theme/ansi16.toml")),
+];
+/// Where user themes live, inside the configuration directory.
+const THEMES_DIR_NAME: &str = "themes";
+const MODIFIERS: [(&str, Modifier); 9] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underlined", Modifier::UNDERLINED),
+    ("slow_blink", Modifier::SLOW_BLINK),
+    ("rapid_blink", Modifier::RAPID_BLINK),
+    ("reversed", Modifier::REVERSED),
+    ("hidden", Modifier::HIDDEN),
+    ("crossed_out", Modifier::CROSSED_OUT),
+];
+
 #[derive(Debug)]
+pub enum ThemeError {
+    NotFound(String),
+    Io(PathBuf, io::Error),
+    Syntax(PathBuf, toml::de::Error),
+    Invalid { theme: String, reason: String },
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeError::NotFound(name) => write!(f, "unknown theme \"{name}\""),
+            ThemeError::Io(path, err) => write!(f, "could not read {}: {err}", path.display()),
+            ThemeError::Syntax(path, err) => write!(f, "{}: {err}", path.display()),
+            ThemeError::Invalid { theme, reason } => write!(f, "theme {theme}: {reason}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Styles {
     pub regular_text: Style,
     pub selected_text: Style,
+    pub search_match: Style,
     pub line_numbers_normal: Style,
     pub line_numbers_selected: Style,
     pub status_mode_normal: Style,
@@ -11,6 +61,11 @@ pub struct Styles {
     pub status_mode_insert: Style,
     pub status_mode_menu: Style,
     pub status_mode_select: Style,
+    pub command_line: Style,
+    pub command_line_error: Style,
+    pub command_line_warning: Style,
+    pub window_separator: Style,
+    pub bookmark_sign: Style,
 }
 
 #[derive(Debug)]
@@ -21,6 +76,8 @@ pub struct Theme {
     pub selected_text_foreground: Color,
     pub selected_line_background: Color,
     pub selected_line_foreground: Color,
+    pub search_match_background: Color,
+    pub search_match_foreground: Color,
     pub tabline_foreground: Color,
     pub tabline_background: Color,
     pub tabline_border_foreground: Color,
@@ -35,8 +92,10 @@ pub struct Theme {
     pub status_mode_command_foreground: Color,
     pub status_mode_insert_background: Color,
     pub status_mode_insert_foreground: Color,
-    pub status_mode_select_foreground: Color,
+    pub status_mode_menu_background: Color,
+    pub status_mode_menu_foreground: Color,
     pub status_mode_select_background: Color,
+    pub status_mode_select_foreground: Color,
     pub status_background: Color,
     pub status_foreground: Color,
     pub menu_background: Color,
@@ -44,113 +103,378 @@ pub struct Theme {
     pub menu_key_foreground: Color,
     pub menu_separator_foreground: Color,
     pub menu_action_foreground: Color,
+    pub error_foreground: Color,
+    pub warning_foreground: Color,
     pub styles: Styles,
 }
 
 impl Default for Theme {
     fn default() -> Self {
-        let text_background = Color::Rgb(35, 35, 40);
-        let text_foreground = Color::Rgb(220, 200, 180);
-        let selected_text_background = Color::Rgb(88, 60, 10);
-        let selected_text_foreground = text_foreground;
-        let selected_line_background = Color::Rgb(50, 50, 55);
-        let selected_line_foreground = text_foreground;
-        let tabline_foreground = Color::Rgb(144, 190, 255);
-        let tabline_background = Color::Rgb(20, 20, 40);
-        let tabline_border_foreground = Color::Rgb(80, 120, 180);
-        let tabline_border_background = Color::Rgb(20, 20, 40);
-        let line_numbers_normal_foreground = Color::Rgb(90, 90, 90);
-        let line_numbers_normal_background = text_background;
-        let line_numbers_selected_foreground = text_foreground;
-        let line_numbers_selected_background = text_background;
-        let status_mode_normal_background = tabline_foreground;
-        let status_mode_normal_foreground = text_background;
-        let status_mode_command_background = text_foreground;
-        let status_mode_command_foreground = text_background;
-        let status_mode_insert_background = Color::Rgb(120, 240, 140);
-        let status_mode_insert_foreground = text_background;
-        let status_mode_menu_background = Color::Rgb(240, 60, 170);
-        let status_mode_menu_foreground = text_background;
-        let status_mode_select_background = Color::Rgb(220, 150, 20);
-        let status_mode_select_foreground = text_background;
-        let status_background = Color::Rgb(10, 10, 10);
-        let status_foreground = text_foreground;
-        let menu_background = Color::Rgb(20, 20, 20);
-        let menu_border = Color::Rgb(60, 60, 50);
-        let menu_key_foreground = Color::Rgb(80, 240, 120);
-        let menu_separator_foreground = Color::Rgb(80, 80, 80);
-        let menu_action_foreground = Color::Rgb(200, 160, 200);
-
-        let regular_text = Style::default().fg(text_foreground).bg(text_background);
-        let selected_text = Style::default()
-            .fg(selected_text_foreground)
-            .bg(selected_text_background);
-        let line_numbers_normal = Style::default()
-            .fg(line_numbers_normal_foreground)
-            .bg(line_numbers_normal_background);
-        let line_numbers_selected = Style::default()
-            .fg(line_numbers_selected_foreground)
-            .bg(line_numbers_selected_background)
-            .add_modifier(Modifier::BOLD);
-        let status_mode_normal = Style::default()
-            .fg(status_mode_normal_foreground)
-            .bg(status_mode_normal_background);
-        let status_mode_command = Style::default()
-            .fg(status_mode_command_foreground)
-            .bg(status_mode_command_background);
-        let status_mode_insert = Style::default()
-            .fg(status_mode_insert_foreground)
-            .bg(status_mode_insert_background);
-        let status_mode_menu = Style::default()
-            .fg(status_mode_menu_foreground)
-            .bg(status_mode_menu_background);
-        let status_mode_select = Style::default()
-            .fg(status_mode_select_foreground)
-            .bg(status_mode_select_background);
-
-        let styles = Styles {
-            regular_text,
-            selected_text,
-            line_numbers_normal,
-            line_numbers_selected,
-            status_mode_normal,
-            status_mode_command,
-            status_mode_insert,
-            status_mode_menu,
-            status_mode_select,
+        Theme::builtin(BUILTIN_THEMES[0].0).clean_expect("built-in theme is invalid!")
+    }
+}
+
+impl Styles {
+    /// The styles a theme gets unless its file overrides them.
+    fn derive(theme: &Theme) -> Self {
+        let style = |fg: Color, bg: Color| Style::default().fg(fg).bg(bg);
+        Styles {
+            regular_text: style(theme.text_foreground, theme.text_background),
+            selected_text: style(
+                theme.selected_text_foreground,
+                theme.selected_text_background,
+            ),
+            search_match: style(theme.search_match_foreground, theme.search_match_background),
+            line_numbers_normal: style(
+                theme.line_numbers_normal_foreground,
+                theme.line_numbers_normal_background,
+            ),
+            line_numbers_selected: style(
+                theme.line_numbers_selected_foreground,
+                theme.line_numbers_selected_background,
+            )
+            .add_modifier(Modifier::BOLD),
+            status_mode_normal: style(
+                theme.status_mode_normal_foreground,
+                theme.status_mode_normal_background,
+            ),
+            status_mode_command: style(
+                theme.status_mode_command_foreground,
+                theme.status_mode_command_background,
+            ),
+            status_mode_insert: style(
+                theme.status_mode_insert_foreground,
+                theme.status_mode_insert_background,
+            ),
+            status_mode_menu: style(
+                theme.status_mode_menu_foreground,
+                theme.status_mode_menu_background,
+            ),
+            status_mode_select: style(
+                theme.status_mode_select_foreground,
+                theme.status_mode_select_background,
+            ),
+            command_line: style(theme.status_foreground, theme.status_background),
+            command_line_error: style(theme.error_foreground, theme.status_background)
+                .add_modifier(Modifier::BOLD),
+            command_line_warning: style(theme.warning_foreground, theme.status_background)
+                .add_modifier(Modifier::BOLD),
+            window_separator: style(theme.tabline_border_foreground, theme.text_background),
+            bookmark_sign: style(theme.search_match_background, theme.text_background),
+        }
+    }
+
+    fn style_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "regular_text" => &mut self.regular_text,
+            "selected_text" => &mut self.selected_text,
+            "search_match" => &mut self.search_match,
+            "line_numbers_normal" => &mut self.line_numbers_normal,
+            "line_numbers_selected" => &mut self.line_numbers_selected,
+            "status_mode_normal" => &mut self.status_mode_normal,
+            "status_mode_command" => &mut self.status_mode_command,
+            "status_mode_insert" => &mut self.status_mode_insert,
+            "status_mode_menu" => &mut self.status_mode_menu,
+            "status_mode_select" => &mut self.status_mode_select,
+            "command_line" => &mut self.command_line,
+            "command_line_error" => &mut self.command_line_error,
+            "command_line_warning" => &mut self.command_line_warning,
+            "window_separator" => &mut self.window_separator,
+            "bookmark_sign" => &mut self.bookmark_sign,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+impl Theme {
+    /// Looks up a theme by name: a built-in one, one in the user's themes
+    /// directory, or a path to a theme file.
+    pub fn named(name: &str) -> Result<Theme, ThemeError> {
+        if BUILTIN_THEMES.iter().any(|&(builtin, _)| builtin == name) {
+            return Theme::builtin(name);
+        }
+        if name.ends_with(".toml") || name.contains(MAIN_SEPARATOR) {
+            return Theme::load(Path::new(name));
+        }
+        match config_dir().map(|dir| user_theme_path(&dir, name)) {
+            Some(path) if path.is_file() => Theme::load(&path),
+            _ => Err(ThemeError::NotFound(name.to_owned())),
+        }
+    }
+
+    /// Reads a theme file.
+    pub fn load(path: &Path) -> Result<Theme, ThemeError> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| ThemeError::Io(path.to_owned(), err))?;
+        let table = toml::from_str::<toml::Table>(&contents)
+            .map_err(|err| ThemeError::Syntax(path.to_owned(), err))?;
+        Theme::from_table(&path.display().to_string(), &table)
+    }
+
+    fn builtin(name: &str) -> Result<Theme, ThemeError> {
+        Theme::from_table(name, &builtin_table(name))
+    }
+
+    /// Builds a theme from the default one, the built-in theme named by the
+    /// optional `base` key, and finally `table` itself. Styles not given in
+    /// any of them are derived from the resulting colors.
+    pub fn from_table(name: &str, table: &toml::Table) -> Result<Theme, ThemeError> {
+        let invalid = |reason: String| ThemeError::Invalid {
+            theme: name.to_owned(),
+            reason,
+        };
+        let mut tables = vec![builtin_table(BUILTIN_THEMES[0].0)];
+        match table.get("base") {
+            None => (),
+            Some(toml::Value::String(base))
+                if BUILTIN_THEMES.iter().any(|&(builtin, _)| builtin == base) =>
+            {
+                tables.push(builtin_table(base))
+            }
+            Some(base) => {
+                return Err(invalid(format!(
+                    "base must name a built-in theme, found {base}; built-in themes are: {}",
+                    builtin_names().join(", ")
+                )))
+            }
+        }
+        tables.push(table.to_owned());
+
+        let mut theme = Theme::blank();
+        for table in &tables {
+            theme.merge_colors(table).map_err(invalid)?;
+        }
+        theme.styles = Styles::derive(&theme);
+        for table in &tables {
+            theme.merge_styles(table).map_err(invalid)?;
+        }
+        Ok(theme)
+    }
+
+    fn merge_colors(&mut self, table: &toml::Table) -> Result<(), String> {
+        for (section, entries) in table {
+            if !["base", "colors", "styles"].contains(&section.as_str()) {
+                return Err(format!(
+                    "unknown section [{section}], expected colors or styles"
+                ));
+            }
+            if section != "colors" {
+                continue;
+            }
+            for (name, value) in expect_table(section, entries)? {
+                let color = parse_color(value).map_err(|err| format!("{name}: {err}"))?;
+                *self
+                    .color_mut(name)
+                    .ok_or_else(|| format!("unknown color \"{name}\""))? = color;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_styles(&mut self, table: &toml::Table) -> Result<(), String> {
+        let Some(entries) = table.get("styles") else {
+            return Ok(());
+        };
+        for (name, value) in expect_table("styles", entries)? {
+            let style = self
+                .parse_style(value)
+                .map_err(|err| format!("{name}: {err}"))?;
+            *self
+                .styles
+                .style_mut(name)
+                .ok_or_else(|| format!("unknown style \"{name}\""))? = style;
+        }
+        Ok(())
+    }
+
+    /// Reads a style such as `{ fg = "text_foreground", bg = "#202020",
+    /// modifiers = ["bold"] }`, where colors may name those of the theme.
+    fn parse_style(&mut self, value: &toml::Value) -> Result<Style, String> {
+        let toml::Value::Table(fields) = value else {
+            return Err("expected a table with fg, bg and modifiers".to_owned());
+        };
+        let mut style = Style::default();
+        for (field, value) in fields {
+            match field.as_str() {
+                "fg" => style = style.fg(self.resolve_color(value)?),
+                "bg" => style = style.bg(self.resolve_color(value)?),
+                "modifiers" => style = style.add_modifier(parse_modifiers(value)?),
+                _ => return Err(format!("unknown field \"{field}\"")),
+            }
+        }
+        Ok(style)
+    }
+
+    fn resolve_color(&mut self, value: &toml::Value) -> Result<Color, String> {
+        if let Some(color) = value.as_str().and_then(|name| self.color_mut(name)) {
+            return Ok(*color);
+        }
+        parse_color(value)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        let color = match name {
+            "text_background" => &mut self.text_background,
+            "text_foreground" => &mut self.text_foreground,
+            "selected_text_background" => &mut self.selected_text_background,
+            "selected_text_foreground" => &mut self.selected_text_foreground,
+            "selected_line_background" => &mut self.selected_line_background,
+            "selected_line_foreground" => &mut self.selected_line_foreground,
+            "search_match_background" => &mut self.search_match_background,
+            "search_match_foreground" => &mut self.search_match_foreground,
+            "tabline_foreground" => &mut self.tabline_foreground,
+            "tabline_background" => &mut self.tabline_background,
+            "tabline_border_foreground" => &mut self.tabline_border_foreground,
+            "tabline_border_background" => &mut self.tabline_border_background,
+            "line_numbers_normal_foreground" => &mut self.line_numbers_normal_foreground,
+            "line_numbers_normal_background" => &mut self.line_numbers_normal_background,
+            "line_numbers_selected_foreground" => &mut self.line_numbers_selected_foreground,
+            "line_numbers_selected_background" => &mut self.line_numbers_selected_background,
+            "status_mode_normal_background" => &mut self.status_mode_normal_background,
+            "status_mode_normal_foreground" => &mut self.status_mode_normal_foreground,
+            "status_mode_command_background" => &mut self.status_mode_command_background,
+            "status_mode_command_foreground" => &mut self.status_mode_command_foreground,
+            "status_mode_insert_background" => &mut self.status_mode_insert_background,
+            "status_mode_insert_foreground" => &mut self.status_mode_insert_foreground,
+            "status_mode_menu_background" => &mut self.status_mode_menu_background,
+            "status_mode_menu_foreground" => &mut self.status_mode_menu_foreground,
+            "status_mode_select_background" => &mut self.status_mode_select_background,
+            "status_mode_select_foreground" => &mut self.status_mode_select_foreground,
+            "status_background" => &mut self.status_background,
+            "status_foreground" => &mut self.status_foreground,
+            "menu_background" => &mut self.menu_background,
+            "menu_border" => &mut self.menu_border,
+            "menu_key_foreground" => &mut self.menu_key_foreground,
+            "menu_separator_foreground" => &mut self.menu_separator_foreground,
+            "menu_action_foreground" => &mut self.menu_action_foreground,
+            "error_foreground" => &mut self.error_foreground,
+            "warning_foreground" => &mut self.warning_foreground,
+            _ => return None,
         };
+        Some(color)
+    }
 
+    /// A theme in the terminal's own colors, for theme files to fill in.
+    fn blank() -> Self {
+        let reset = Color::Reset;
         Theme {
-            text_background,
-            text_foreground,
-            selected_text_background,
-            selected_text_foreground,
-            selected_line_background,
-            selected_line_foreground,
-            tabline_foreground,
-            tabline_background,
-            tabline_border_foreground,
-            tabline_border_background,
-            line_numbers_normal_foreground,
-            line_numbers_normal_background,
-            line_numbers_selected_foreground,
-            line_numbers_selected_background,
-            status_mode_normal_background,
-            status_mode_normal_foreground,
-            status_mode_command_background,
-            status_mode_command_foreground,
-            status_mode_insert_background,
-            status_mode_insert_foreground,
-            status_mode_select_background,
-            status_mode_select_foreground,
-            status_background,
-            status_foreground,
-            menu_background,
-            menu_border,
-            menu_key_foreground,
-            menu_separator_foreground,
-            menu_action_foreground,
-            styles,
+            text_background: reset,
+            text_foreground: reset,
+            selected_text_background: reset,
+            selected_text_foreground: reset,
+            selected_line_background: reset,
+            selected_line_foreground: reset,
+            search_match_background: reset,
+            search_match_foreground: reset,
+            tabline_foreground: reset,
+            tabline_background: reset,
+            tabline_border_foreground: reset,
+            tabline_border_background: reset,
+            line_numbers_normal_foreground: reset,
+            line_numbers_normal_background: reset,
+            line_numbers_selected_foreground: reset,
+            line_numbers_selected_background: reset,
+            status_mode_normal_background: reset,
+            status_mode_normal_foreground: reset,
+            status_mode_command_background: reset,
+            status_mode_command_foreground: reset,
+            status_mode_insert_background: reset,
+            status_mode_insert_foreground: reset,
+            status_mode_menu_background: reset,
+            status_mode_menu_foreground: reset,
+            status_mode_select_background: reset,
+            status_mode_select_foreground: reset,
+            status_background: reset,
+            status_foreground: reset,
+            menu_background: reset,
+            menu_border: reset,
+            menu_key_foreground: reset,
+            menu_separator_foreground: reset,
+            menu_action_foreground: reset,
+            error_foreground: reset,
+            warning_foreground: reset,
+            styles: Styles::default(),
         }
     }
 }
+
+/// The names of the built-in themes, followed by those of the user's
+/// themes that do not clash with them.
+pub fn available_themes() -> Vec<String> {
+    let mut user_themes: Vec<String> = config_dir()
+        .and_then(|dir| fs::read_dir(dir.join(THEMES_DIR_NAME)).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let is_theme = path.extension().is_some_and(|ext| ext == "toml");
+            is_theme.then(|| path.file_stem()?.to_str().map(str::to_owned))?
+        })
+        .filter(|name| !builtin_names().contains(&name.as_str()))
+        .collect();
+    user_themes.sort();
+    builtin_names()
+        .into_iter()
+        .map(str::to_owned)
+        .chain(user_themes)
+        .collect()
+}
+
+fn builtin_names() -> Vec<&'static str> {
+    BUILTIN_THEMES.iter().map(|&(name, _)| name).collect()
+}
+
+fn builtin_table(name: &str) -> toml::Table {
+    let source = BUILTIN_THEMES
+        .iter()
+        .find(|&&(builtin, _)| builtin == name)
+        .map(|&(_, source)| source)
+        .clean_expect("no such built-in theme!");
+    toml::from_str(source).clean_expect("built-in theme is not valid TOML!")
+}
+
+fn user_theme_path(config_dir: &Path, name: &str) -> PathBuf {
+    config_dir
+        .join(THEMES_DIR_NAME)
+        .join(format!("{name}.toml"))
+}
+
+fn expect_table<'a>(section: &str, value: &'a toml::Value) -> Result<&'a toml::Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("[{section}] must be a table"))
+}
+
+/// Reads a `"#rrggbb"` color, a palette index or a terminal color name.
+fn parse_color(value: &toml::Value) -> Result<Color, String> {
+    match value {
+        toml::Value::Integer(index) => u8::try_from(*index)
+            .map(Color::Indexed)
+            .map_err(|_| format!("palette index {index} is not between 0 and 255")),
+        toml::Value::String(color) => {
+            Color::from_str(color).map_err(|_| format!("\"{color}\" is not a color"))
+        }
+        _ => Err(format!("expected a color, found {}", value.type_str())),
+    }
+}
+
+fn parse_modifiers(value: &toml::Value) -> Result<Modifier, String> {
+    let Some(names) = value.as_array() else {
+        return Err("modifiers must be a list".to_owned());
+    };
+    names.iter().try_fold(Modifier::empty(), |modifiers, name| {
+        let modifier = MODIFIERS
+            .iter()
+            .find(|&&(modifier, _)| Some(modifier) == name.as_str())
+            .map(|&(_, modifier)| modifier)
+            .ok_or_else(|| {
+                let known: Vec<_> = MODIFIERS.iter().map(|&(name, _)| name).collect();
+                format!(
+                    "unknown modifier {name}, expected one of: {}",
+                    known.join(", ")
+                )
+            })?;
+        Ok(modifiers | modifier)
+    })
+}
@@ -1,19 +1,27 @@
+pub mod command_line;
 pub mod floating_window;
 pub mod leader_menu;
-mod line_numbers;
+pub mod line_numbers;
+pub mod notification;
+pub mod sign_column;
+pub mod split_tree;
 pub mod status_bar;
 pub mod text_window;
 
-use super::{buffer::Buffer, editor::Mode, theme::Theme};
+use super::{
+    buffer::{Buffer, LineShift, RectilinearDirection as Rectilinear},
+    cleanup::CleanUnwrap,
+    editor::Mode,
+    settings::WindowSettings,
+    theme::Theme,
+};
 use ratatui::{
     buffer::Buffer as TUI_Buffer,
-    layout::{Position, Rect},
+    layout::{Direction, Position, Rect},
     widgets::StatefulWidget,
 };
-use std::{
-    cell::RefCell,
-    rc::{Rc, Weak},
-};
+use split_tree::SplitTree;
+use std::{cell::RefCell, rc::Weak};
 use text_window::{TextWindow, TextWindowState};
 
 #[derive(Debug, Clone)]
@@ -21,31 +29,250 @@ pub struct Tab {}
 
 #[derive(Debug)]
 pub struct TabState {
-    pub window_states: TextWindowState,
-    pub buffer: Rc<RefCell<Buffer>>,
-    pub windows: TextWindow,
-    pub mode: Mode,
+    pub window_states: Vec<TextWindowState>,
+    pub windows: Vec<TextWindow>,
+    layout: SplitTree,
     current_window: usize,
+    /// Where the tab was last drawn.
+    area: Option<Rect>,
+    /// Where each window was last drawn.
+    window_areas: Vec<Rect>,
+    theme: Weak<Theme>,
 }
 
 impl TabState {
-    pub fn new(buf: Buffer, theme: Weak<Theme>, mode: Mode) -> Self {
-        let buf_rc = Rc::new(RefCell::new(buf));
+    pub fn new(
+        buffer: Weak<RefCell<Buffer>>,
+        theme: Weak<Theme>,
+        mode: Mode,
+        settings: WindowSettings,
+    ) -> Self {
         TabState {
-            buffer: Rc::clone(&buf_rc),
-            window_states: TextWindowState::new(Rc::downgrade(&buf_rc), mode.clone()),
-            windows: TextWindow::new(Rc::downgrade(&buf_rc), theme.clone()),
+            window_states: vec![TextWindowState::new(buffer.clone(), mode, settings)],
+            windows: vec![TextWindow::new(buffer, theme.clone())],
+            layout: SplitTree::Window(0),
             current_window: 0,
-            mode,
+            area: None,
+            window_areas: vec![],
+            theme,
         }
     }
 
+    pub fn current_window(&self) -> usize {
+        self.current_window
+    }
+
+    pub fn current_winstate(&self) -> &TextWindowState {
+        &self.window_states[self.current_window]
+    }
+
+    pub fn current_winstate_mut(&mut self) -> &mut TextWindowState {
+        &mut self.window_states[self.current_window]
+    }
+
+    /// Where the cursor of the current window is on the screen.
     pub fn get_cursor_pos(&self) -> Position {
-        self.window_states.get_cursor_pos()
+        let Position { x, y } = self.current_winstate().get_cursor_pos();
+        let area = self
+            .window_areas
+            .get(self.current_window)
+            .copied()
+            .unwrap_or_default();
+        Position {
+            x: x + area.x,
+            y: y + area.y,
+        }
     }
 
+    /// Shows the mode in the current window. The others are left in normal
+    /// mode, so that only the current one shows a selection.
     pub fn propagate_mode(&mut self, mode: Mode) {
-        self.window_states.set_mode(mode);
+        for (i, state) in self.window_states.iter_mut().enumerate() {
+            match i == self.current_window {
+                true => state.set_mode(mode.clone()),
+                false => state.set_mode(Mode::Normal),
+            }
+        }
+    }
+
+    pub fn set_theme(&mut self, theme: Weak<Theme>) {
+        for window in &mut self.windows {
+            window.set_theme(theme.clone());
+        }
+        self.theme = theme;
+    }
+
+    /// Splits the current window in two, the new window showing the same
+    /// part of the buffer and becoming the current one. The windows are laid
+    /// out one after the other in `direction`. Returns false if the current
+    /// window is too small to leave both a row or column.
+    pub fn split_window(&mut self, direction: Direction) -> bool {
+        if let Some(area) = self.window_areas.get(self.current_window) {
+            let length = match direction {
+                Direction::Horizontal => area.width,
+                Direction::Vertical => area.height,
+            };
+            // One cell for each window and one for the separator
+            if length < 3 {
+                return false;
+            }
+        }
+        let new = self.windows.len();
+        self.windows.push(self.windows[self.current_window].clone());
+        self.window_states
+            .push(self.window_states[self.current_window].clone());
+        self.layout.split(self.current_window, new, direction);
+        self.current_window = new;
+        // Splitting again before the next draw goes by the new sizes
+        if let Some(area) = self.area {
+            self.lay_out(area);
+        }
+        true
+    }
+
+    /// Closes the current window, unless it is the last one.
+    pub fn close_window(&mut self) -> bool {
+        if !self.layout.remove(self.current_window) {
+            return false;
+        }
+        self.windows.remove(self.current_window);
+        self.window_states.remove(self.current_window);
+        if self.current_window < self.window_areas.len() {
+            self.window_areas.remove(self.current_window);
+        }
+        self.current_window = self.current_window.saturating_sub(1);
+        true
+    }
+
+    /// Closes every window but the current one.
+    pub fn only_window(&mut self) {
+        let current = self.current_window;
+        self.windows.swap(0, current);
+        self.windows.truncate(1);
+        self.window_states.swap(0, current);
+        self.window_states.truncate(1);
+        self.window_areas.clear();
+        self.layout = SplitTree::Window(0);
+        self.current_window = 0;
+    }
+
+    /// Shares `area` between the windows, returning the separators to draw
+    /// between them.
+    fn lay_out(&mut self, area: Rect) -> Vec<(Rect, Direction)> {
+        let layout = self.layout.layout(area);
+        self.area = Some(area);
+        self.window_areas = vec![Rect::default(); self.windows.len()];
+        for (i, window_area) in layout.windows {
+            self.window_areas[i] = window_area;
+        }
+        layout.separators
+    }
+
+    /// Grows the current window by `delta` lines or columns, depending on
+    /// `direction`, or shrinks it if `delta` is negative.
+    pub fn resize_window(&mut self, direction: Direction, delta: isize) {
+        self.layout.resize(self.current_window, direction, delta);
+    }
+
+    /// Moves to the next or previous window, wrapping around.
+    pub fn cycle_window(&mut self, forward: bool) {
+        let count = self.windows.len();
+        self.current_window = match forward {
+            true => (self.current_window + 1) % count,
+            false => (self.current_window + count - 1) % count,
+        };
+    }
+
+    /// Moves to the closest window in `dir`, preferring the one next to
+    /// the cursor.
+    pub fn focus_window(&mut self, dir: Rectilinear) {
+        let Some(&current) = self.window_areas.get(self.current_window) else {
+            return;
+        };
+        let cursor = self.get_cursor_pos();
+        let closest = self
+            .window_areas
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != self.current_window)
+            .filter_map(|(i, area)| {
+                // How far the window is in `dir`, and how far it is from the
+                // cursor across it
+                let (distance, across) = match dir {
+                    Rectilinear::Left if area.right() <= current.x => (
+                        current.x - area.right(),
+                        gap(cursor.y, area.y, area.bottom()),
+                    ),
+                    Rectilinear::Right if area.x >= current.right() => (
+                        area.x - current.right(),
+                        gap(cursor.y, area.y, area.bottom()),
+                    ),
+                    Rectilinear::Up if area.bottom() <= current.y => (
+                        current.y - area.bottom(),
+                        gap(cursor.x, area.x, area.right()),
+                    ),
+                    Rectilinear::Down if area.y >= current.bottom() => (
+                        area.y - current.bottom(),
+                        gap(cursor.x, area.x, area.right()),
+                    ),
+                    _ => return None,
+                };
+                Some((i, (distance, across)))
+            })
+            .min_by_key(|&(_, key)| key);
+        if let Some((i, _)) = closest {
+            self.current_window = i;
+        }
+    }
+
+    /// Shows another buffer in the current window.
+    pub fn show_buffer(&mut self, buffer: Weak<RefCell<Buffer>>) {
+        self.windows[self.current_window].set_buffer(buffer.clone());
+        self.window_states[self.current_window].set_buffer(buffer);
+    }
+
+    /// Shows `replacement` in every window showing `buffer`.
+    pub fn replace_buffer(
+        &mut self,
+        buffer: &Weak<RefCell<Buffer>>,
+        replacement: &Weak<RefCell<Buffer>>,
+    ) {
+        for (window, state) in self.windows.iter_mut().zip(&mut self.window_states) {
+            if state.buffer().ptr_eq(buffer) {
+                window.set_buffer(replacement.clone());
+                state.set_buffer(replacement.clone());
+            }
+        }
+    }
+
+    pub fn shows_buffer(&self, buffer: &Weak<RefCell<Buffer>>) -> bool {
+        self.window_states
+            .iter()
+            .any(|state| state.buffer().ptr_eq(buffer))
+    }
+
+    /// Moves the cursors of the windows on `buffer` other than `editing`
+    /// past lines inserted or removed from another window.
+    pub fn shift_lines(
+        &mut self,
+        buffer: &Weak<RefCell<Buffer>>,
+        shift: &LineShift,
+        editing: Option<usize>,
+    ) {
+        for (i, state) in self.window_states.iter_mut().enumerate() {
+            if Some(i) != editing && state.buffer().ptr_eq(buffer) {
+                state.shift_lines(shift);
+            }
+        }
+    }
+}
+
+/// How far `pos` is from the range of cells from `start` to `end`.
+fn gap(pos: u16, start: u16, end: u16) -> u16 {
+    match pos {
+        pos if pos < start => start - pos,
+        pos if pos >= end => pos + 1 - end,
+        _ => 0,
     }
 }
 
@@ -59,9 +286,26 @@ impl StatefulWidget for Tab {
     type State = TabState;
 
     fn render(self, area: Rect, buf: &mut TUI_Buffer, state: &mut Self::State) {
-        state
-            .windows
-            .clone()
-            .render(area, buf, &mut state.window_states);
+        let separators = state.lay_out(area);
+        for (i, window_area) in state.window_areas.clone().into_iter().enumerate() {
+            state.windows[i]
+                .clone()
+                .render(window_area, buf, &mut state.window_states[i]);
+        }
+        let theme = state
+            .theme
+            .upgrade()
+            .clean_expect("referencing dropped theme!");
+        for (separator, direction) in separators {
+            let symbol = match direction {
+                Direction::Horizontal => "│",
+                Direction::Vertical => "─",
+            };
+            for position in separator.positions() {
+                buf[position]
+                    .set_symbol(symbol)
+                    .set_style(theme.styles.window_separator);
+            }
+        }
     }
 }
@@ -0,0 +1,197 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Layout, Position, Rect},
+    style::Style,
+    text::Span,
+    widgets::Block,
+    Frame,
+};
+use tui_textarea::TextArea;
+
+use crate::app::{
+    buffer::{HorizontalDirection, VerticalDirection},
+    theme::Theme,
+};
+
+use super::notification::Notification;
+
+/// What the command line is being used to type.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Prompt {
+    #[default]
+    Command,
+    Search(HorizontalDirection),
+}
+
+impl Prompt {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Prompt::Command => ":",
+            Prompt::Search(HorizontalDirection::Forward) => "/",
+            Prompt::Search(HorizontalDirection::Backward) => "?",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    input: TextArea<'static>,
+    prompt: Prompt,
+    history: Vec<String>,
+    search_history: Vec<String>,
+    history_index: Option<usize>,
+    notification: Option<Notification>,
+    /// A question shown instead of the input, such as the confirmation
+    /// asked by `:s///c`.
+    message: Option<String>,
+    last_area: Rect,
+}
+
+impl CommandLine {
+    pub fn input(&self) -> &str {
+        &self.input.lines()[0]
+    }
+
+    pub fn prompt(&self) -> &Prompt {
+        &self.prompt
+    }
+
+    /// Clears the input to type something new after `prompt`.
+    pub fn start(&mut self, prompt: Prompt) {
+        self.clear();
+        self.prompt = prompt;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input().is_empty()
+    }
+
+    pub fn handle_input(&mut self, key: &KeyEvent) {
+        let inserts_newline = match key.code {
+            KeyCode::Enter | KeyCode::Char('\n' | '\r') => true,
+            KeyCode::Char('m') => key.modifiers.contains(KeyModifiers::CONTROL),
+            _ => false,
+        };
+        if !inserts_newline {
+            self.input.input(*key);
+        }
+    }
+
+    /// Clears the input and returns what was typed, remembering it in the
+    /// history if it is not empty.
+    pub fn take_input(&mut self) -> String {
+        let input = self.input().to_owned();
+        let history = self.history_mut();
+        if !input.is_empty() && history.last() != Some(&input) {
+            history.push(input.clone());
+        }
+        self.clear();
+        input
+    }
+
+    pub fn clear(&mut self) {
+        self.input = TextArea::default();
+        self.history_index = None;
+    }
+
+    /// Commands and searches are remembered separately.
+    fn history_mut(&mut self) -> &mut Vec<String> {
+        match self.prompt {
+            Prompt::Command => &mut self.history,
+            Prompt::Search(_) => &mut self.search_history,
+        }
+    }
+
+    pub fn browse_history(&mut self, dir: VerticalDirection) {
+        let history = match self.prompt {
+            Prompt::Command => &self.history,
+            Prompt::Search(_) => &self.search_history,
+        };
+        if history.is_empty() {
+            return;
+        }
+        let last = history.len() - 1;
+        self.history_index = match (self.history_index, dir) {
+            (None, VerticalDirection::Up) => Some(last),
+            (None, VerticalDirection::Down) => return,
+            (Some(0), VerticalDirection::Up) => Some(0),
+            (Some(i), VerticalDirection::Up) => Some(i - 1),
+            (Some(i), VerticalDirection::Down) if i < last => Some(i + 1),
+            (Some(_), VerticalDirection::Down) => None,
+        };
+        let entry = self
+            .history_index
+            .map_or(String::new(), |i| history[i].to_owned());
+        self.input = TextArea::new(vec![entry]);
+        self.input.move_cursor(tui_textarea::CursorMove::End);
+    }
+
+    pub fn notification(&self) -> Option<&Notification> {
+        self.notification.as_ref()
+    }
+
+    /// The message of the notification shown, if it is an error.
+    #[cfg(test)]
+    pub fn error(&self) -> Option<&str> {
+        self.notification
+            .as_ref()
+            .filter(|notification| notification.severity == super::notification::Severity::Error)
+            .map(|notification| notification.message.as_str())
+    }
+
+    pub fn notify(&mut self, notification: Notification) {
+        self.notification = Some(notification);
+    }
+
+    pub fn clear_notification(&mut self) {
+        self.notification = None;
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, msg: String) {
+        self.message = Some(msg);
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    pub fn cursor_pos(&self) -> Position {
+        let (_, col) = self.input.cursor();
+        Position {
+            x: self.last_area.x + (self.prompt.symbol().len() + col) as u16,
+            y: self.last_area.y,
+        }
+    }
+
+    pub fn render(&mut self, area: Rect, frame: &mut Frame, theme: &Theme) {
+        self.last_area = area;
+        frame.render_widget(Block::new().style(theme.styles.command_line), area);
+        if let Some(ref notification) = self.notification {
+            frame.render_widget(
+                Span::styled(notification.message.to_owned(), notification.style(theme)),
+                area,
+            );
+            return;
+        }
+        if let Some(ref message) = self.message {
+            frame.render_widget(
+                Span::styled(message.to_owned(), theme.styles.command_line),
+                area,
+            );
+            return;
+        }
+        let symbol = self.prompt.symbol();
+        let layout =
+            Layout::horizontal([Constraint::Length(symbol.len() as u16), Constraint::Fill(1)])
+                .split(area);
+        frame.render_widget(Span::styled(symbol, theme.styles.command_line), layout[0]);
+        self.input.set_style(theme.styles.command_line);
+        self.input.set_cursor_style(theme.styles.command_line);
+        self.input.set_cursor_line_style(Style::default());
+        frame.render_widget(&self.input, layout[1]);
+    }
+}
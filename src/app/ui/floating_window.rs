@@ -5,7 +5,11 @@ use ratatui::{layout::Rect, Frame};
 
 use crate::app::{editor::Editor, theme::Theme};
 
+pub(crate) mod buffer_picker;
+pub(crate) mod messages;
 pub(crate) mod saving_unnamed;
+pub(crate) mod swap_recovery;
+pub(crate) mod unsaved_changes;
 
 type EditorCallback = Box<dyn FnOnce(&mut Editor)>;
 
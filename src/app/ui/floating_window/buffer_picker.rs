@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Margin, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders},
+    Frame,
+};
+
+use crate::app::{editor::Editor, theme::Theme};
+
+use super::{EditorCallback, FloatingContent};
+
+/// A buffer as listed in the picker.
+#[derive(Clone, Debug)]
+pub(crate) struct BufferEntry {
+    pub name: String,
+    /// Whether a window shows the buffer.
+    pub shown: bool,
+}
+
+/// Lists the open buffers to switch the current window to one of them.
+#[derive(Clone, Debug)]
+pub(crate) struct BufferPicker {
+    entries: Vec<BufferEntry>,
+    selected: usize,
+}
+
+impl BufferPicker {
+    pub(crate) fn new(entries: Vec<BufferEntry>, selected: usize) -> Self {
+        BufferPicker { entries, selected }
+    }
+}
+
+impl FloatingContent for BufferPicker {
+    fn handle_input(&mut self, input: &KeyEvent) -> Option<EditorCallback> {
+        match input.code {
+            KeyCode::Enter => {
+                let index = self.selected;
+                Some(Box::new(move |ed: &mut Editor| {
+                    ed.clear_floating_window();
+                    ed.switch_buffer(index);
+                }))
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                Some(Box::new(|ed: &mut Editor| ed.clear_floating_window()))
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % self.entries.len();
+                None
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self, area: &Rect, frame: &mut Frame, theme: Rc<Theme>) {
+        let background = Block::new()
+            .borders(Borders::all())
+            .border_type(BorderType::Rounded)
+            .title(" Buffers ")
+            .bg(theme.menu_background)
+            .fg(theme.text_foreground);
+        frame.render_widget(background, *area);
+        let inner_area = area.inner(Margin::new(2, 1));
+        let rows =
+            Layout::vertical(vec![Constraint::Length(1); self.entries.len()]).split(inner_area);
+        // Keep the selected buffer in view when the list does not fit
+        let first = (self.selected + 1).saturating_sub(inner_area.height.into());
+        for (row, (i, entry)) in rows.iter().zip(self.entries.iter().enumerate().skip(first)) {
+            let style = match i == self.selected {
+                true => theme.styles.selected_text,
+                false => Style::default(),
+            };
+            let hidden = if entry.shown { "" } else { " (hidden)" };
+            let line = Line::from(vec![
+                Span::from(format!("{:>3} ", i + 1)).fg(theme.menu_key_foreground),
+                Span::from(format!("{}{hidden}", entry.name)),
+            ])
+            .style(style);
+            frame.render_widget(line, *row);
+        }
+    }
+
+    fn clone_as_box(&self) -> Box<dyn FloatingContent> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,69 @@
+use std::rc::Rc;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Margin, Rect},
+    style::Stylize,
+    text::{Line, Text},
+    widgets::{Block, BorderType, Borders},
+    Frame,
+};
+
+use crate::app::{
+    editor::Editor,
+    theme::Theme,
+    ui::notification::{Notification, Severity},
+};
+
+use super::{EditorCallback, FloatingContent};
+
+/// Shows the notifications given so far, the latest at the bottom.
+#[derive(Clone, Debug)]
+pub(crate) struct Messages {
+    messages: Vec<Notification>,
+}
+
+impl Messages {
+    pub(crate) fn new(messages: Vec<Notification>) -> Self {
+        Messages { messages }
+    }
+}
+
+impl FloatingContent for Messages {
+    fn handle_input(&mut self, input: &KeyEvent) -> Option<EditorCallback> {
+        match input.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                Some(Box::new(|ed: &mut Editor| ed.clear_floating_window()))
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self, area: &Rect, frame: &mut Frame, theme: Rc<Theme>) {
+        let background = Block::new()
+            .borders(Borders::all())
+            .border_type(BorderType::Rounded)
+            .title(" Messages ")
+            .bg(theme.menu_background)
+            .fg(theme.text_foreground);
+        frame.render_widget(background, *area);
+        let inner_area = area.inner(Margin::new(2, 1));
+        let first = self.messages.len().saturating_sub(inner_area.height.into());
+        let lines: Vec<Line> = self.messages[first..]
+            .iter()
+            .map(|notification| {
+                let fg = match notification.severity {
+                    Severity::Info => theme.text_foreground,
+                    Severity::Warning => theme.warning_foreground,
+                    Severity::Error => theme.error_foreground,
+                };
+                Line::from(notification.to_string()).fg(fg)
+            })
+            .collect();
+        frame.render_widget(Text::from(lines), inner_area);
+    }
+
+    fn clone_as_box(&self) -> Box<dyn FloatingContent> {
+        Box::new(self.clone())
+    }
+}
@@ -15,27 +15,42 @@ use crate::app::{
     theme::Theme,
 };
 
-use super::{EditorCallback, FloatingContent};
+use super::{unsaved_changes::Closing, EditorCallback, FloatingContent};
 
 const STANDARD_WINDOW_HEIGHT: u16 = 6;
 
 #[derive(Clone, Default)]
 pub(crate) struct SavingUnnamed<'a> {
     filename: TextArea<'a>,
+    /// What was being closed when the name was asked for, which goes on
+    /// closing once the buffer is saved.
+    closing: Option<Closing>,
 }
 
-impl<'b> SavingUnnamed<'b> {}
+impl<'b> SavingUnnamed<'b> {
+    pub(crate) fn then_close(closing: Closing) -> Self {
+        SavingUnnamed {
+            closing: Some(closing),
+            ..SavingUnnamed::default()
+        }
+    }
+}
 
 impl<'b> FloatingContent for SavingUnnamed<'b> {
     fn handle_input(&mut self, input: &KeyEvent) -> Option<EditorCallback> {
         match input.code {
             KeyCode::Enter => {
                 let new_name: OsString = self.filename.lines()[0].to_owned().into();
-                Some(Box::new(|ed: &mut Editor| {
+                let closing = self.closing;
+                Some(Box::new(move |ed: &mut Editor| {
                     ed.current_buffer_mut().set_name(new_name.clone());
                     ed.current_buffer_mut().set_path(new_name);
                     ed.execute_editor_action(EditorAction::SaveBuffer);
-                    ed.clear_floating_window()
+                    ed.clear_floating_window();
+                    let saved = !ed.current_buffer().is_modified();
+                    if let Some(closing) = closing.filter(|_| saved) {
+                        ed.close_unsaved(closing, true);
+                    }
                 }))
             }
             KeyCode::Esc => Some(Box::new(|ed: &mut Editor| ed.clear_floating_window())),
@@ -81,6 +96,9 @@ impl<'b> FloatingContent for SavingUnnamed<'b> {
     fn clone_as_box(&self) -> Box<dyn FloatingContent> {
         let mut filename = TextArea::new(self.filename.lines().to_vec());
         filename.set_style(self.filename.style());
-        Box::new(SavingUnnamed { filename })
+        Box::new(SavingUnnamed {
+            filename,
+            closing: self.closing,
+        })
     }
 }
@@ -0,0 +1,123 @@
+use std::{path::PathBuf, rc::Rc};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Margin, Rect},
+    style::Stylize,
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders},
+    Frame,
+};
+
+use crate::app::{editor::Editor, theme::Theme};
+
+use super::{EditorCallback, FloatingContent};
+
+/// Offers to recover the changes kept in the swap file of a buffer, left
+/// behind by an editor that did not exit normally.
+#[derive(Clone, Debug)]
+pub(crate) struct SwapRecovery {
+    /// The buffer's index in the buffer list.
+    buffer: usize,
+    name: String,
+    swap: PathBuf,
+    /// How the swap file differs from the file, as lines starting with `-`
+    /// for the file and `+` for the swap file.
+    diff: Vec<String>,
+    show_diff: bool,
+}
+
+impl SwapRecovery {
+    pub(crate) fn new(buffer: usize, name: String, swap: PathBuf, diff: Vec<String>) -> Self {
+        SwapRecovery {
+            buffer,
+            name,
+            swap,
+            diff,
+            show_diff: false,
+        }
+    }
+}
+
+impl FloatingContent for SwapRecovery {
+    fn handle_input(&mut self, input: &KeyEvent) -> Option<EditorCallback> {
+        let (buffer, swap) = (self.buffer, self.swap.clone());
+        match input.code {
+            KeyCode::Char('r') => Some(Box::new(move |ed: &mut Editor| {
+                ed.recover_swap(buffer, &swap);
+            })),
+            KeyCode::Char('d') => {
+                self.show_diff = !self.show_diff;
+                None
+            }
+            KeyCode::Char('x') => Some(Box::new(move |ed: &mut Editor| {
+                ed.delete_swap(&swap);
+            })),
+            KeyCode::Esc | KeyCode::Char('e') => {
+                Some(Box::new(|ed: &mut Editor| ed.offer_next_recovery()))
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self, area: &Rect, frame: &mut Frame, theme: Rc<Theme>) {
+        let background = Block::new()
+            .borders(Borders::all())
+            .border_type(BorderType::Rounded)
+            .title(" Swap file found ")
+            .bg(theme.menu_background)
+            .fg(theme.text_foreground);
+        frame.render_widget(background, *area);
+        let inner_area = area.inner(Margin::new(2, 1));
+        let [message_area, choices_area, _, diff_area] = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(inner_area);
+        let message = Text::from(vec![
+            Line::from(format!(
+                "{} has unsaved changes from a previous session",
+                self.name
+            )),
+            Line::from(format!("in {}", self.swap.display())),
+        ]);
+        frame.render_widget(message, message_area);
+        let choices = Line::from(
+            [
+                ("r", "Recover"),
+                ("d", "Diff"),
+                ("x", "Delete"),
+                ("e", "Edit anyway"),
+            ]
+            .into_iter()
+            .flat_map(|(key, name)| {
+                [
+                    Span::from(key).fg(theme.menu_key_foreground),
+                    Span::from(format!(" {name}  ")),
+                ]
+            })
+            .collect::<Vec<_>>(),
+        );
+        frame.render_widget(choices, choices_area);
+        if !self.show_diff {
+            return;
+        }
+        let lines: Vec<Line> = self
+            .diff
+            .iter()
+            .take(diff_area.height.into())
+            .map(|line| match line.chars().next() {
+                Some('-') => Line::from(line.as_str()).fg(theme.error_foreground),
+                Some('+') => Line::from(line.as_str()).fg(theme.menu_key_foreground),
+                _ => Line::from(line.as_str()).fg(theme.menu_separator_foreground),
+            })
+            .collect();
+        frame.render_widget(Text::from(lines), diff_area);
+    }
+
+    fn clone_as_box(&self) -> Box<dyn FloatingContent> {
+        Box::new(self.clone())
+    }
+}
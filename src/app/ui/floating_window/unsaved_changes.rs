@@ -0,0 +1,98 @@
+use std::rc::Rc;
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Margin, Rect},
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders},
+    Frame,
+};
+
+use crate::app::{editor::Editor, theme::Theme};
+
+use super::{EditorCallback, FloatingContent};
+
+const STANDARD_WINDOW_HEIGHT: u16 = 6;
+
+/// What is being closed when there are unsaved changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Closing {
+    Editor,
+    /// The current buffer, deleted from the buffer list.
+    Buffer,
+}
+
+/// Asks whether to save, discard or keep the unsaved changes that closing
+/// something would lose.
+#[derive(Clone, Debug)]
+pub(crate) struct UnsavedChanges {
+    closing: Closing,
+    message: String,
+}
+
+impl UnsavedChanges {
+    pub(crate) fn new(closing: Closing, message: String) -> Self {
+        UnsavedChanges { closing, message }
+    }
+}
+
+impl FloatingContent for UnsavedChanges {
+    fn handle_input(&mut self, input: &KeyEvent) -> Option<EditorCallback> {
+        let closing = self.closing;
+        match input.code {
+            KeyCode::Char('s') | KeyCode::Char('y') | KeyCode::Enter => {
+                Some(Box::new(move |ed: &mut Editor| {
+                    ed.clear_floating_window();
+                    ed.close_unsaved(closing, true);
+                }))
+            }
+            KeyCode::Char('d') | KeyCode::Char('n') => Some(Box::new(move |ed: &mut Editor| {
+                ed.clear_floating_window();
+                ed.close_unsaved(closing, false);
+            })),
+            KeyCode::Char('c') | KeyCode::Esc => {
+                Some(Box::new(|ed: &mut Editor| ed.clear_floating_window()))
+            }
+            _ => None,
+        }
+    }
+
+    fn render(&self, area: &Rect, frame: &mut Frame, theme: Rc<Theme>) {
+        if area.height < STANDARD_WINDOW_HEIGHT {
+            return;
+        }
+        let outer_layout = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(STANDARD_WINDOW_HEIGHT),
+            Constraint::Fill(1),
+        ]);
+        let window_area = outer_layout.split(*area)[1];
+        let background = Block::new()
+            .borders(Borders::all())
+            .border_type(BorderType::Rounded)
+            .bg(theme.menu_background)
+            .fg(theme.text_foreground);
+        frame.render_widget(background, window_area);
+        let inner_area = window_area.inner(Margin::new(2, 2));
+        let inner_layout =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).split(inner_area);
+        frame.render_widget(Line::from(self.message.as_str()), inner_layout[0]);
+        let choices = Line::from(
+            [("s", "Save"), ("d", "Discard"), ("c", "Cancel")]
+                .into_iter()
+                .flat_map(|(key, name)| {
+                    [
+                        Span::from(key).fg(theme.menu_key_foreground),
+                        Span::from(format!(" {name}  ")),
+                    ]
+                })
+                .collect::<Vec<_>>(),
+        );
+        frame.render_widget(choices, inner_layout[1]);
+    }
+
+    fn clone_as_box(&self) -> Box<dyn FloatingContent> {
+        Box::new(self.clone())
+    }
+}
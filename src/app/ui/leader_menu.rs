@@ -7,7 +7,6 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Widget},
 };
-use SubMenu::*;
 
 use crate::app::theme::Theme;
 
@@ -17,11 +16,13 @@ const MINIMUM_COLUMN_SPACING: u16 = 2;
 #[derive(Debug, Clone, PartialEq)]
 pub enum SubMenu {
     Root,
+    /// The themes to switch to, listed by the editor rather than the keymap.
+    Themes,
 }
 
 #[derive(Debug)]
 pub struct LeaderMenu {
-    sub_menu: SubMenu,
+    items: Vec<KeyHint>,
     menu_background: Color,
     menu_border: Color,
     key_hint_style: KeyHintStyle,
@@ -35,7 +36,7 @@ pub struct KeyHintStyle {
     action: Color,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct KeyHint {
     key: String,
     action: String,
@@ -49,6 +50,10 @@ impl KeyHint {
         }
     }
 
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
     pub fn styled<'a>(self, style: &KeyHintStyle) -> Line<'a> {
         let base = Style::default().bg(style.background);
         let key = Span::styled(self.key.clone(), base.fg(style.key));
@@ -63,9 +68,9 @@ impl KeyHint {
 }
 
 impl LeaderMenu {
-    pub fn new(sub_menu: &SubMenu, theme: &Theme) -> Self {
+    pub fn new(items: &[KeyHint], theme: &Theme) -> Self {
         LeaderMenu {
-            sub_menu: sub_menu.clone(),
+            items: items.to_vec(),
             menu_background: theme.menu_background,
             menu_border: theme.menu_border,
             key_hint_style: KeyHintStyle {
@@ -77,9 +82,7 @@ impl LeaderMenu {
         }
     }
 
-    pub fn required_height(sub_menu: &SubMenu, width: u16) -> u16 {
-        let items = Self::menu_items(sub_menu);
-
+    pub fn required_height(items: &[KeyHint], width: u16) -> u16 {
         let mut height: usize = 1;
         loop {
             let mut col_widths: Vec<u16> = vec![];
@@ -105,16 +108,6 @@ impl LeaderMenu {
     fn style_keyhints<'a>(hints: Vec<KeyHint>, style: &KeyHintStyle) -> Vec<Line<'a>> {
         hints.into_iter().map(move |h| h.styled(style)).collect()
     }
-
-    pub fn menu_items(sub_menu: &SubMenu) -> Vec<KeyHint> {
-        match sub_menu {
-            Root => Self::root_menu_items(),
-        }
-    }
-
-    fn root_menu_items() -> Vec<KeyHint> {
-        vec![KeyHint::new("q", "Quit"), KeyHint::new("w", "Save Buffer")]
-    }
 }
 
 impl Widget for LeaderMenu {
@@ -140,7 +133,7 @@ impl Widget for LeaderMenu {
         }
         let width = area.width;
 
-        let items = Self::style_keyhints(Self::menu_items(&self.sub_menu), &self.key_hint_style);
+        let items = Self::style_keyhints(self.items, &self.key_hint_style);
         let mut columns: Vec<Paragraph> = vec![];
         let mut minimum_width = MINIMUM_COLUMN_SPACING;
         while let Some(col) = items.get(height * columns.len()..height * (columns.len() + 1)) {
@@ -1,3 +1,5 @@
+use std::{cmp::max, fmt::Display};
+
 use ratatui::{
     buffer::Buffer as TUI_Buffer,
     layout::Rect,
@@ -6,9 +8,51 @@ use ratatui::{
     widgets::{Paragraph, Widget},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LineNumberType {
     Absolute,
+    /// Distances from the cursor line, which is numbered 0.
     Relative,
+    /// Distances from the cursor line, which shows its own number.
+    Hybrid,
+    Off,
+}
+
+impl LineNumberType {
+    /// The mode after this one, in the order the leader menu goes through
+    /// them.
+    pub fn next(self) -> Self {
+        match self {
+            LineNumberType::Absolute => LineNumberType::Relative,
+            LineNumberType::Relative => LineNumberType::Hybrid,
+            LineNumberType::Hybrid => LineNumberType::Off,
+            LineNumberType::Off => LineNumberType::Absolute,
+        }
+    }
+
+    /// The cells taken by the numbers of a buffer of `lines_count` lines in a
+    /// window `height` rows tall, including the space after them.
+    pub fn width(self, lines_count: usize, height: usize) -> usize {
+        let digits = |n: usize| n.max(1).to_string().len();
+        match self {
+            LineNumberType::Absolute => digits(lines_count) + 1,
+            LineNumberType::Relative => digits(height) + 1,
+            LineNumberType::Hybrid => max(digits(lines_count), digits(height)) + 1,
+            LineNumberType::Off => 0,
+        }
+    }
+}
+
+impl Display for LineNumberType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LineNumberType::Absolute => "absolute",
+            LineNumberType::Relative => "relative",
+            LineNumberType::Hybrid => "hybrid",
+            LineNumberType::Off => "off",
+        };
+        write!(f, "{name}")
+    }
 }
 
 struct LineNumberStyles {
@@ -18,23 +62,18 @@ struct LineNumberStyles {
 
 pub struct LineNumbers {
     number_type: LineNumberType,
-    first_line: usize,
-    last_line: usize,
+    /// The number of the line starting on each row, or none for the rows
+    /// that wrapped lines continue on.
+    rows: Vec<Option<usize>>,
     selected: usize,
     style: LineNumberStyles,
 }
 
 impl LineNumbers {
-    pub fn new(
-        number_type: LineNumberType,
-        first_line: usize,
-        last_line: usize,
-        selected: usize,
-    ) -> Self {
+    pub fn new(number_type: LineNumberType, rows: Vec<Option<usize>>, selected: usize) -> Self {
         LineNumbers {
             number_type,
-            first_line,
-            last_line,
+            rows,
             selected,
             style: LineNumberStyles {
                 normal: Style::default(),
@@ -43,21 +82,13 @@ impl LineNumbers {
         }
     }
 
-    pub fn generate_numbers(&self) -> Vec<usize> {
-        let numbers: Vec<usize> = (self.first_line..self.last_line + 1).collect();
-        match self.number_type {
-            LineNumberType::Absolute => numbers,
-            LineNumberType::Relative => numbers
-                .iter()
-                .map(|&x| {
-                    if x == self.selected {
-                        x
-                    } else {
-                        x.abs_diff(self.selected)
-                    }
-                })
-                .collect(),
-        }
+    pub fn generate_numbers(&self) -> Vec<Option<usize>> {
+        let number = |x: usize| match self.number_type {
+            LineNumberType::Absolute | LineNumberType::Off => x,
+            LineNumberType::Hybrid if x == self.selected => x,
+            LineNumberType::Relative | LineNumberType::Hybrid => x.abs_diff(self.selected),
+        };
+        self.rows.iter().map(|row| row.map(number)).collect()
     }
 
     pub fn set_styles(mut self, normal: Style, selected: Style) -> Self {
@@ -71,14 +102,20 @@ impl Widget for &LineNumbers {
     fn render(self, area: Rect, buf: &mut TUI_Buffer) {
         let numbers = &self.generate_numbers();
         let width = area.width as usize;
-        let mut lines: Vec<Line> = numbers
+        let lines: Vec<Line> = numbers
             .iter()
-            .map(|&x| Line::styled(format!("{x: >width$}"), self.style.normal))
+            .zip(&self.rows)
+            .map(|(number, &row)| {
+                let style = match row == Some(self.selected) {
+                    true => self.style.selected,
+                    false => self.style.normal,
+                };
+                match number {
+                    Some(x) => Line::styled(format!("{x: >width$}"), style),
+                    None => Line::styled(" ".repeat(width), style),
+                }
+            })
             .collect();
-        if self.selected >= self.first_line && self.selected <= self.last_line {
-            let selected_index = self.selected - self.first_line;
-            lines[selected_index] = lines[selected_index].clone().style(self.style.selected);
-        }
         Paragraph::new(lines).render(area, buf);
     }
 }
@@ -0,0 +1,44 @@
+use std::fmt::Display;
+
+use ratatui::style::Style;
+
+use crate::app::theme::Theme;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message for the user about something that happened, shown on the
+/// command line until the next key press and kept in the message history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Notification { severity, message }
+    }
+
+    pub fn style(&self, theme: &Theme) -> Style {
+        match self.severity {
+            Severity::Info => theme.styles.command_line,
+            Severity::Warning => theme.styles.command_line_warning,
+            Severity::Error => theme.styles.command_line_error,
+        }
+    }
+}
+
+impl Display for Notification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Info => write!(f, "{}", self.message),
+            Severity::Warning => write!(f, "Warning: {}", self.message),
+            Severity::Error => write!(f, "Error: {}", self.message),
+        }
+    }
+}
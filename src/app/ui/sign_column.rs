@@ -0,0 +1,47 @@
+use ratatui::{
+    buffer::Buffer as TUI_Buffer,
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::app::buffer::signs::Sign;
+
+/// The cells taken by the sign column.
+pub const WIDTH: u16 = 2;
+
+pub struct SignColumn {
+    /// The sign drawn on each row, if any.
+    rows: Vec<Option<Sign>>,
+    style: Style,
+}
+
+impl SignColumn {
+    /// A column showing `rows`, over a background of `style`.
+    pub fn new(rows: Vec<Option<Sign>>, style: Style) -> Self {
+        SignColumn { rows, style }
+    }
+}
+
+impl Widget for &SignColumn {
+    fn render(self, area: Rect, buf: &mut TUI_Buffer) {
+        let width = area.width as usize;
+        let lines: Vec<Line> = self
+            .rows
+            .iter()
+            .map(|sign| match sign {
+                Some(sign) if sign.text.width() <= width => {
+                    let padding = " ".repeat(width - sign.text.width());
+                    Line::from(vec![
+                        Span::styled(sign.text.as_str(), sign.style),
+                        Span::raw(padding),
+                    ])
+                }
+                _ => Line::raw(""),
+            })
+            .collect();
+        Paragraph::new(lines).style(self.style).render(area, buf);
+    }
+}
@@ -0,0 +1,230 @@
+use ratatui::layout::{Direction, Rect};
+
+/// How the windows of a tab share its area. Windows are identified by their
+/// index in the tab.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitTree {
+    Window(usize),
+    /// Subtrees laid out one after the other in `direction`, each with the
+    /// size it was last drawn at, along `direction`.
+    Split {
+        direction: Direction,
+        children: Vec<(SplitTree, u16)>,
+    },
+}
+
+/// The result of laying out a tree: the area of each window and the lines
+/// separating them.
+#[derive(Debug, Default)]
+pub struct SplitLayout {
+    pub windows: Vec<(usize, Rect)>,
+    pub separators: Vec<(Rect, Direction)>,
+}
+
+/// Splits `total` cells between parts in proportion to `weights`, giving
+/// each at least one cell when there is room for it.
+fn distribute(weights: &[u16], total: u16) -> Vec<u16> {
+    let count = weights.len() as u32;
+    let weight_sum: u32 = weights.iter().map(|&w| u32::from(w)).sum();
+    let mut sizes: Vec<u16> = weights
+        .iter()
+        .map(|&weight| match weight_sum {
+            0 => (u32::from(total) / count) as u16,
+            _ => (u32::from(total) * u32::from(weight) / weight_sum) as u16,
+        })
+        .collect();
+    if u32::from(total) >= count {
+        for i in 0..sizes.len() {
+            if sizes[i] == 0 {
+                sizes[i] = 1;
+                let largest = (0..sizes.len()).max_by_key(|&j| sizes[j]).unwrap_or(i);
+                sizes[largest] -= 1;
+            }
+        }
+    }
+    let used: u16 = sizes.iter().sum();
+    if let Some(last) = sizes.last_mut() {
+        *last += total.saturating_sub(used);
+    }
+    sizes
+}
+
+impl SplitTree {
+    /// Splits window `window` in two, putting window `new` before it.
+    pub fn split(&mut self, window: usize, new: usize, direction: Direction) {
+        match self {
+            SplitTree::Window(id) if *id == window => {
+                *self = SplitTree::Split {
+                    direction,
+                    children: vec![(SplitTree::Window(new), 1), (SplitTree::Window(window), 1)],
+                }
+            }
+            SplitTree::Window(_) => (),
+            SplitTree::Split {
+                direction: split_direction,
+                children,
+            } => {
+                let same_direction = *split_direction == direction;
+                let leaf = children
+                    .iter()
+                    .position(|(child, _)| *child == SplitTree::Window(window));
+                match leaf {
+                    Some(i) if same_direction => {
+                        // Share the space of the window being split
+                        let size = children[i].1;
+                        children[i].1 = size - size / 2;
+                        children.insert(i, (SplitTree::Window(new), size / 2));
+                    }
+                    _ => {
+                        for (child, _) in children {
+                            child.split(window, new, direction);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes window `window`, giving its space to a neighbour, and
+    /// renumbers the windows after it. Returns false if it is the last one.
+    pub fn remove(&mut self, window: usize) -> bool {
+        if *self == SplitTree::Window(window) {
+            return false;
+        }
+        self.remove_leaf(window);
+        self.renumber(window);
+        true
+    }
+
+    fn remove_leaf(&mut self, window: usize) {
+        let SplitTree::Split { children, .. } = self else {
+            return;
+        };
+        match children
+            .iter()
+            .position(|(child, _)| *child == SplitTree::Window(window))
+        {
+            Some(i) => {
+                let (_, size) = children.remove(i);
+                let neighbour = i.min(children.len() - 1);
+                children[neighbour].1 += size;
+            }
+            None => {
+                for (child, _) in children.iter_mut() {
+                    child.remove_leaf(window);
+                }
+            }
+        }
+        if children.len() == 1 {
+            let (only, _) = children.remove(0);
+            *self = only;
+        }
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            SplitTree::Window(id) if *id > removed => *id -= 1,
+            SplitTree::Window(_) => (),
+            SplitTree::Split { children, .. } => {
+                for (child, _) in children {
+                    child.renumber(removed);
+                }
+            }
+        }
+    }
+
+    fn contains(&self, window: usize) -> bool {
+        match self {
+            SplitTree::Window(id) => *id == window,
+            SplitTree::Split { children, .. } => {
+                children.iter().any(|(child, _)| child.contains(window))
+            }
+        }
+    }
+
+    /// Grows window `window` by `delta` cells in `direction`, taking them
+    /// from a neighbour, or shrinks it for a negative `delta`. Returns false
+    /// if no split in that direction contains the window.
+    pub fn resize(&mut self, window: usize, direction: Direction, delta: isize) -> bool {
+        let SplitTree::Split {
+            direction: split_direction,
+            children,
+        } = self
+        else {
+            return false;
+        };
+        let Some(i) = children
+            .iter()
+            .position(|(child, _)| child.contains(window))
+        else {
+            return false;
+        };
+        if children[i].0.resize(window, direction, delta) {
+            return true;
+        }
+        if *split_direction != direction {
+            return false;
+        }
+        let neighbour = if i + 1 < children.len() { i + 1 } else { i - 1 };
+        let (size, other) = (children[i].1 as isize, children[neighbour].1 as isize);
+        // Both windows keep at least one cell, which may leave no room to
+        // resize them at all
+        let (min, max) = (1 - size, other - 1);
+        if min > max {
+            return false;
+        }
+        let delta = delta.clamp(min, max);
+        children[i].1 = (size + delta) as u16;
+        children[neighbour].1 = (other - delta) as u16;
+        true
+    }
+
+    /// Lays the tree out over `area`, leaving a line between windows, and
+    /// remembers the sizes it was drawn at.
+    pub fn layout(&mut self, area: Rect) -> SplitLayout {
+        let mut layout = SplitLayout::default();
+        self.layout_into(area, &mut layout);
+        layout
+    }
+
+    fn layout_into(&mut self, area: Rect, layout: &mut SplitLayout) {
+        let (direction, children) = match self {
+            SplitTree::Window(id) => {
+                layout.windows.push((*id, area));
+                return;
+            }
+            SplitTree::Split {
+                direction,
+                children,
+            } => (direction.to_owned(), children),
+        };
+        let length = match direction {
+            Direction::Horizontal => area.width,
+            Direction::Vertical => area.height,
+        };
+        let separators = children.len() as u16 - 1;
+        let weights: Vec<u16> = children.iter().map(|&(_, size)| size).collect();
+        let sizes = distribute(&weights, length.saturating_sub(separators));
+        let mut offset = 0;
+        for (i, ((child, size), &new_size)) in children.iter_mut().zip(&sizes).enumerate() {
+            *size = new_size;
+            let (child_area, separator) = match direction {
+                Direction::Horizontal => (
+                    Rect::new(area.x + offset, area.y, new_size, area.height),
+                    Rect::new(area.x + offset + new_size, area.y, 1, area.height),
+                ),
+                Direction::Vertical => (
+                    Rect::new(area.x, area.y + offset, area.width, new_size),
+                    Rect::new(area.x, area.y + offset + new_size, area.width, 1),
+                ),
+            };
+            child.layout_into(child_area, layout);
+            if i + 1 < sizes.len() {
+                layout
+                    .separators
+                    .push((separator.intersection(area), direction));
+            }
+            offset += new_size + 1;
+        }
+    }
+}
@@ -14,11 +14,24 @@ pub struct StatusBar {
     col: usize,
     percent: u8,
     mode: Mode,
+    /// Whether the buffer has unsaved changes.
+    modified: bool,
+    /// How the buffer's file is laid out, such as `CRLF BOM`.
+    format: String,
+    pending_keys: String,
+    /// Which search match the cursor is at, out of how many.
+    search_count: Option<(usize, usize)>,
     theme: Weak<Theme>,
 }
 
 impl StatusBar {
-    pub fn new(win: &TextWindowState, mode: Mode, theme: Weak<Theme>) -> Self {
+    pub fn new(
+        win: &TextWindowState,
+        mode: Mode,
+        pending_keys: String,
+        search_count: Option<(usize, usize)>,
+        theme: Weak<Theme>,
+    ) -> Self {
         let line = win.cursor.line;
         let total_lines = win.lines_count();
         let percent = if total_lines == 0 {
@@ -27,11 +40,20 @@ impl StatusBar {
             (100.0 * line as f32 / (total_lines - 1) as f32).round() as u8
         };
         let col = win.cursor.col;
+        let buffer = win.buffer().upgrade();
+        let modified = buffer
+            .as_ref()
+            .is_some_and(|buffer| buffer.borrow().is_modified());
+        let format = buffer.map_or(String::new(), |buffer| buffer.borrow().format().to_string());
         StatusBar {
             line,
             col,
             percent,
             mode,
+            modified,
+            format,
+            pending_keys,
+            search_count,
             theme,
         }
     }
@@ -44,7 +66,9 @@ impl Widget for &StatusBar {
             .upgrade()
             .clean_expect("referencing a dead theme!");
         let mode_span = match self.mode {
-            Mode::Normal => Span::styled(" NORMAL ", theme.styles.status_mode_normal),
+            Mode::Normal | Mode::OperatorPending(_) => {
+                Span::styled(" NORMAL ", theme.styles.status_mode_normal)
+            }
             Mode::Command => Span::styled(" COMMAND ", theme.styles.status_mode_command),
             Mode::Insert => Span::styled(" INSERT ", theme.styles.status_mode_insert),
             Mode::Visual(_) => Span::styled(" VISUAL ", theme.styles.status_mode_select),
@@ -54,6 +78,48 @@ impl Widget for &StatusBar {
             .width()
             .try_into()
             .clean_expect("mode span too long!");
+        let modified_span = Span::styled(
+            if self.modified { " [+]" } else { "" },
+            Style::default()
+                .bg(theme.status_background)
+                .fg(theme.status_foreground),
+        );
+        let modified_width = modified_span
+            .width()
+            .try_into()
+            .clean_expect("modified span too long!");
+        let pending_span = Span::styled(
+            self.pending_keys.as_str(),
+            Style::default()
+                .bg(theme.status_background)
+                .fg(theme.status_foreground),
+        );
+        let pending_width = pending_span
+            .width()
+            .try_into()
+            .clean_expect("pending keys span too long!");
+        let search_span = Span::styled(
+            self.search_count.map_or(String::new(), |(current, total)| {
+                format!("{current}/{total}")
+            }),
+            Style::default()
+                .bg(theme.status_background)
+                .fg(theme.status_foreground),
+        );
+        let search_width = search_span
+            .width()
+            .try_into()
+            .clean_expect("search count span too long!");
+        let format_span = Span::styled(
+            self.format.as_str(),
+            Style::default()
+                .bg(theme.status_background)
+                .fg(theme.status_foreground),
+        );
+        let format_width = format_span
+            .width()
+            .try_into()
+            .clean_expect("file format span too long!");
         let pos_span = Span::styled(
             format!("{}:{}", self.line + 1, self.col + 1),
             Style::default()
@@ -80,7 +146,14 @@ impl Widget for &StatusBar {
             Direction::Horizontal,
             vec![
                 Constraint::Length(mode_width),
+                Constraint::Length(modified_width),
                 Constraint::Fill(1),
+                Constraint::Length(pending_width),
+                Constraint::Length(2),
+                Constraint::Length(search_width),
+                Constraint::Length(2),
+                Constraint::Length(format_width),
+                Constraint::Length(2),
                 Constraint::Length(pos_width),
                 Constraint::Length(1),
                 Constraint::Length(percent_width),
@@ -90,17 +163,25 @@ impl Widget for &StatusBar {
         )
         .split(area);
         let mode_area = layout[0];
-        let pos_area = layout[2];
-        let percent_area = layout[4];
-        let rightmost_padding = layout[6];
+        let modified_area = layout[1];
+        let pending_area = layout[3];
+        let search_area = layout[5];
+        let format_area = layout[7];
+        let pos_area = layout[9];
+        let percent_area = layout[11];
+        let rightmost_padding = layout[13];
 
         Block::new().bg(theme.status_background).render(area, buf);
         mode_span.render(mode_area, buf);
+        modified_span.render(modified_area, buf);
+        pending_span.render(pending_area, buf);
+        search_span.render(search_area, buf);
+        format_span.render(format_area, buf);
         pos_span.render(pos_area, buf);
         percent_span.render(percent_area, buf);
         Block::new()
             .bg(match self.mode {
-                Mode::Normal => theme.status_mode_normal_background,
+                Mode::Normal | Mode::OperatorPending(_) => theme.status_mode_normal_background,
                 Mode::Command => theme.status_mode_command_background,
                 Mode::Insert => theme.status_mode_insert_background,
                 Mode::Visual(_) => theme.status_mode_select_background,
@@ -1,9 +1,17 @@
-use super::line_numbers::LineNumberType::Relative;
-use super::line_numbers::LineNumbers;
+use super::{
+    line_numbers::{LineNumberType, LineNumbers},
+    sign_column::{self, SignColumn},
+};
 use crate::app::{
-    buffer::{Buffer, BufferPosition, RectilinearDirection as Rectilinear},
+    buffer::{
+        graphemes::{self, visible_text},
+        signs::Sign,
+        Buffer, BufferPosition, LineShift, RectilinearDirection as Rectilinear,
+        VerticalDirection as Vertical,
+    },
     cleanup::{graceful_exit, CleanUnwrap},
-    editor::Mode,
+    editor::{search::line_matches, Mode},
+    settings::WindowSettings,
     theme::Theme,
 };
 use ratatui::{
@@ -13,6 +21,7 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Paragraph, StatefulWidget, Widget},
 };
+use regex::Regex;
 use selection::Selection;
 use std::{
     cell::RefCell,
@@ -20,8 +29,12 @@ use std::{
     iter::repeat_n,
     rc::Weak,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+use wrap::{wrap_line, Row};
 
 pub(crate) mod selection;
+mod wrap;
 
 #[derive(Debug, Clone)]
 pub struct TextWindow {
@@ -37,9 +50,12 @@ pub struct ScreenBounds {
     rightmost_col: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TextWindowState {
     pub top_line: usize,
+    /// With wrapping, the rows of the top line scrolled out of view above
+    /// the window, so that lines taller than it can be seen to their end.
+    pub skip_rows: usize,
     pub leftmost_col: usize,
     pub last_height: usize,
     pub last_width: usize,
@@ -47,14 +63,21 @@ pub struct TextWindowState {
     pub cursor: BufferPosition,
     pub last_manual_col: usize,
     pub stick_to_EOL: bool,
+    pub settings: WindowSettings,
+    /// The pattern whose matches are highlighted, if any.
+    pub search_highlight: Option<Regex>,
+    /// A match singled out among the highlighted ones, as its start and the
+    /// column it ends at.
+    pub current_match: Option<(BufferPosition, usize)>,
     mode: Mode,
     buffer: Weak<RefCell<Buffer>>,
 }
 
 impl TextWindowState {
-    pub fn new(buffer: Weak<RefCell<Buffer>>, mode: Mode) -> Self {
+    pub fn new(buffer: Weak<RefCell<Buffer>>, mode: Mode, settings: WindowSettings) -> Self {
         TextWindowState {
             top_line: 0,
+            skip_rows: 0,
             leftmost_col: 0,
             last_height: 2,
             last_width: 2,
@@ -62,6 +85,9 @@ impl TextWindowState {
             cursor: BufferPosition { line: 0, col: 0 },
             last_manual_col: 0,
             stick_to_EOL: false,
+            settings,
+            search_highlight: None,
+            current_match: None,
             buffer,
             mode,
         }
@@ -71,11 +97,37 @@ impl TextWindowState {
         &self.mode
     }
 
+    pub fn buffer(&self) -> &Weak<RefCell<Buffer>> {
+        &self.buffer
+    }
+
+    /// Shows another buffer from its start.
+    pub fn set_buffer(&mut self, buffer: Weak<RefCell<Buffer>>) {
+        self.buffer = buffer;
+        self.top_line = 0;
+        self.skip_rows = 0;
+        self.leftmost_col = 0;
+        self.cur_vertical_percent = 0.0;
+        self.cursor = BufferPosition { line: 0, col: 0 };
+        self.last_manual_col = 0;
+        self.stick_to_EOL = false;
+    }
+
     pub fn set_mode(&mut self, mode: Mode) {
         self.mode = mode;
     }
 
     pub fn move_cursor(&mut self, mode: &Mode, dir: Rectilinear) {
+        let top = (self.top_line, self.skip_rows);
+        self.step_cursor(mode, dir);
+        if self.settings.wrap {
+            // Wrapped lines scroll by rows rather than by lines
+            (self.top_line, self.skip_rows) = top;
+        }
+        self.keep_scrolloff();
+    }
+
+    fn step_cursor(&mut self, mode: &Mode, dir: Rectilinear) {
         match (mode, dir) {
             (Mode::Normal | Mode::Visual(_) | Mode::Insert, Rectilinear::Up) => {
                 if self.cursor.line == 0 {
@@ -89,7 +141,7 @@ impl TextWindowState {
                 } else {
                     relative_line -= 1;
                     self.cur_vertical_percent =
-                        relative_line as f32 / (self.last_height - 1) as f32;
+                        relative_line as f32 / self.last_height.saturating_sub(1) as f32;
                 }
 
                 let new_line_length = self.line_length(self.cursor.line);
@@ -129,7 +181,7 @@ impl TextWindowState {
                 } else {
                     relative_line += 1;
                     self.cur_vertical_percent =
-                        relative_line as f32 / (self.last_height - 1) as f32;
+                        relative_line as f32 / self.last_height.saturating_sub(1) as f32;
                 }
 
                 let new_line_length = self.line_length(self.cursor.line);
@@ -167,9 +219,7 @@ impl TextWindowState {
                 self.stick_to_EOL = false;
                 self.cursor.col += 1;
                 self.last_manual_col = self.cursor.col;
-                if self.cursor.col >= self.leftmost_col + self.last_width {
-                    self.leftmost_col += 1;
-                }
+                self.scroll_to_cursor();
             }
 
             (Mode::Insert, Rectilinear::Right) => {
@@ -190,9 +240,7 @@ impl TextWindowState {
                     self.stick_to_EOL = false;
                     self.cursor.col += 1;
                     self.last_manual_col = self.cursor.col;
-                    if self.cursor.col >= self.leftmost_col + self.last_width {
-                        self.leftmost_col += 1;
-                    }
+                    self.scroll_to_cursor();
                 }
             }
 
@@ -203,9 +251,7 @@ impl TextWindowState {
                 self.stick_to_EOL = false;
                 self.cursor.col -= 1;
                 self.last_manual_col = self.cursor.col;
-                if self.cursor.col < self.leftmost_col {
-                    self.leftmost_col = self.cursor.col;
-                }
+                self.scroll_to_cursor();
             }
 
             (Mode::Insert, Rectilinear::Left) => {
@@ -228,13 +274,11 @@ impl TextWindowState {
                     self.stick_to_EOL = false;
                     self.cursor.col -= 1;
                     self.last_manual_col = self.cursor.col;
-                    if self.cursor.col < self.leftmost_col {
-                        self.leftmost_col = self.cursor.col;
-                    }
+                    self.scroll_to_cursor();
                 }
             }
 
-            (Mode::Command, _) => (),
+            (Mode::Command | Mode::OperatorPending(_), _) => (),
         }
     }
 
@@ -244,16 +288,46 @@ impl TextWindowState {
         }
         self.cursor.col += 1;
         self.last_manual_col = self.cursor.col;
-        if self.cursor.col >= self.leftmost_col + self.last_width {
-            self.leftmost_col += 1;
+        self.scroll_to_cursor();
+    }
+
+    /// The screen columns, counted from the start of the line, taken by the
+    /// character at `pos`, from the first one to the one after the last.
+    fn display_span(&self, pos: &BufferPosition) -> (usize, usize) {
+        let buffer = self
+            .buffer
+            .upgrade()
+            .clean_expect("measuring text in a dead buffer!");
+        let buffer = buffer.borrow();
+        let tabstop = self.settings.tabstop;
+        let next = BufferPosition {
+            col: pos.col + 1,
+            ..*pos
+        };
+        (
+            buffer.display_col(pos, tabstop),
+            buffer.display_col(&next, tabstop),
+        )
+    }
+
+    /// Scrolls sideways just enough for the cursor to be visible.
+    fn scroll_to_cursor(&mut self) {
+        if self.settings.wrap {
+            return;
+        }
+        let (start, end) = self.display_span(&self.cursor);
+        if start < self.leftmost_col {
+            self.leftmost_col = start;
+        } else if end > self.leftmost_col + self.last_width {
+            self.leftmost_col = min(start, end - self.last_width);
         }
     }
 
     fn screen_bounds(&self) -> ScreenBounds {
         let top_line = self.top_line;
-        let bottom_line = top_line + self.last_height - 1;
+        let bottom_line = (top_line + self.last_height).saturating_sub(1);
         let leftmost_col = self.leftmost_col;
-        let rightmost_col = leftmost_col + self.last_width - 1;
+        let rightmost_col = (leftmost_col + self.last_width).saturating_sub(1);
         ScreenBounds {
             top_line,
             bottom_line,
@@ -269,9 +343,17 @@ impl TextWindowState {
             leftmost_col,
             rightmost_col,
         } = self.screen_bounds();
-        let BufferPosition { line, col } = *pos;
+        let line = pos.line;
+        if self.settings.wrap {
+            let target = (line, self.row_of(&self.line_rows(line), pos.col));
+            let top = (top_line, self.skip_rows);
+            return target >= top
+                && target <= self.rows_below(top, self.last_height.saturating_sub(1));
+        }
+        let (start, end) = self.display_span(pos);
         let within_vertically = line >= top_line && line <= bottom_line;
-        let within_horizontally = col >= leftmost_col && col <= rightmost_col;
+        let within_horizontally =
+            self.settings.wrap || (start >= leftmost_col && end <= rightmost_col + 1);
         within_vertically && within_horizontally
     }
 
@@ -300,15 +382,26 @@ impl TextWindowState {
         self.cursor.line = pos.line;
         self.cursor.col = pos.col;
         let relative_line = pos.line - self.top_line;
-        self.cur_vertical_percent = relative_line as f32 / (self.last_height - 1) as f32;
+        self.cur_vertical_percent =
+            relative_line as f32 / self.last_height.saturating_sub(1) as f32;
     }
 
     pub fn jump(&mut self, pos: &BufferPosition) {
         if self.is_on_screen(pos) {
             self.jump_within_screen(pos);
+            self.keep_scrolloff();
             return;
         }
-        let BufferPosition { line, col } = *pos;
+        if self.settings.wrap {
+            self.cursor = pos.to_owned();
+            self.last_manual_col = self.cursor.col;
+            let cursor = (pos.line, self.cursor_row(&self.line_rows(pos.line)));
+            (self.top_line, self.skip_rows) = self.rows_above(cursor, self.last_height / 2);
+            self.keep_scrolloff();
+            return;
+        }
+        let line = pos.line;
+        let (start, end) = self.display_span(pos);
         let ScreenBounds {
             top_line,
             bottom_line,
@@ -320,16 +413,105 @@ impl TextWindowState {
         if self.lines_count() > 0 && vertically_out_of_bounds {
             let line = min(line, self.lines_count() - 1);
             let relative_line = min(self.last_height / 2, line);
-            self.cur_vertical_percent = relative_line as f32 / (self.last_height - 1) as f32;
+            self.top_line = line - relative_line;
+            self.cur_vertical_percent =
+                relative_line as f32 / self.last_height.saturating_sub(1) as f32;
         }
 
-        if col < leftmost_col || col > rightmost_col {
-            let relative_col = min(self.last_width * 3 / 4, col);
-            self.leftmost_col = col - relative_col;
+        if !self.settings.wrap && (start < leftmost_col || end > rightmost_col + 1) {
+            let relative_col = min(self.last_width * 3 / 4, start);
+            self.leftmost_col = start - relative_col;
         }
 
         self.cursor = pos.to_owned();
         self.last_manual_col = self.cursor.col;
+        self.keep_scrolloff();
+    }
+
+    /// Scrolls just enough for `scrolloff` lines to stay visible above and
+    /// below the cursor, short of scrolling past either end of the buffer.
+    fn keep_scrolloff(&mut self) {
+        if self.settings.wrap {
+            self.keep_scrolloff_rows();
+            return;
+        }
+        if self.last_height < 2 {
+            return;
+        }
+        let margin = min(
+            self.settings.scrolloff,
+            self.last_height.saturating_sub(1) / 2,
+        );
+        let line = self.cursor.line;
+        let highest_top = line.saturating_sub(margin);
+        let lowest_top = min(
+            (line + margin + 1).saturating_sub(self.last_height),
+            self.lines_count().saturating_sub(self.last_height),
+        );
+        self.top_line = self
+            .top_line
+            .clamp(min(lowest_top, highest_top), highest_top);
+        let relative_line = line - self.top_line;
+        self.cur_vertical_percent =
+            relative_line as f32 / self.last_height.saturating_sub(1) as f32;
+    }
+
+    /// Does what `keep_scrolloff` does when lines are wrapped, counting
+    /// screen rows rather than lines.
+    fn keep_scrolloff_rows(&mut self) {
+        if self.last_height == 0 {
+            return;
+        }
+        let margin = min(
+            self.settings.scrolloff,
+            self.last_height.saturating_sub(1) / 2,
+        );
+        let cursor = (
+            self.cursor.line,
+            self.cursor_row(&self.line_rows(self.cursor.line)),
+        );
+        let top_rows = self.line_rows(self.top_line).len();
+        let top = (self.top_line, min(self.skip_rows, top_rows - 1));
+        let highest_top = self.rows_above(cursor, margin);
+        let lowest_top = self.rows_above(
+            self.rows_below(cursor, margin),
+            self.last_height.saturating_sub(1),
+        );
+        (self.top_line, self.skip_rows) = max(lowest_top, min(top, highest_top));
+        let relative_line = self.cursor.line - self.top_line;
+        self.cur_vertical_percent =
+            (relative_line as f32 / max(self.last_height.saturating_sub(1), 1) as f32).min(1.0);
+    }
+
+    /// The row `count` rows above the row `row` of line `line`, or the first
+    /// row of the buffer.
+    fn rows_above(&self, (mut line, mut row): (usize, usize), mut count: usize) -> (usize, usize) {
+        while count > row {
+            if line == 0 {
+                return (0, 0);
+            }
+            count -= row + 1;
+            line -= 1;
+            row = self.line_rows(line).len() - 1;
+        }
+        (line, row - count)
+    }
+
+    /// The row `count` rows below the row `row` of line `line`, or the last
+    /// row of the buffer.
+    fn rows_below(&self, (mut line, mut row): (usize, usize), mut count: usize) -> (usize, usize) {
+        loop {
+            let rows = self.line_rows(line).len();
+            if row + count < rows {
+                return (line, row + count);
+            }
+            if line + 1 >= self.lines_count() {
+                return (line, rows - 1);
+            }
+            count -= rows - row;
+            line += 1;
+            row = 0;
+        }
     }
 
     pub fn sticky_jump_to_EOL(&mut self) {
@@ -349,15 +531,11 @@ impl TextWindowState {
             return;
         }
         self.cursor.col = line_length - 1;
-        let to_the_right = self.cursor.col >= self.leftmost_col + self.last_width;
-        let out_of_bounds = to_the_right || self.cursor.col < self.leftmost_col;
-        if !out_of_bounds {
-            return;
-        }
-        if to_the_right || self.cursor.col >= self.last_width {
-            self.leftmost_col = self.cursor.col + 1 - self.last_width;
-        } else {
-            self.leftmost_col = 0;
+        let (start, end) = self.display_span(&self.cursor);
+        let to_the_right = end > self.leftmost_col + self.last_width;
+        let out_of_bounds = to_the_right || start < self.leftmost_col;
+        if out_of_bounds && !self.settings.wrap {
+            self.leftmost_col = min(start, end.saturating_sub(self.last_width));
         }
     }
 
@@ -388,11 +566,14 @@ impl TextWindowState {
         } else {
             0
         };
+        self.skip_rows = 0;
         self.cursor.line = line;
         let relative_line = line - self.top_line;
-        self.cur_vertical_percent = relative_line as f32 / (self.last_height - 1) as f32;
+        self.cur_vertical_percent =
+            relative_line as f32 / self.last_height.saturating_sub(1) as f32;
         self.snap_to_EOL();
         self.last_manual_col = self.cursor.col;
+        self.keep_scrolloff();
     }
 
     pub fn lines_count(&self) -> usize {
@@ -400,8 +581,7 @@ impl TextWindowState {
             .upgrade()
             .clean_expect("counting lines in a dead buffer!")
             .borrow()
-            .lines
-            .len()
+            .lines_count()
     }
 
     fn line_length(&self, line: usize) -> usize {
@@ -409,21 +589,220 @@ impl TextWindowState {
             .upgrade()
             .clean_expect("checking line length in a dead buffer!")
             .borrow()
-            .lines[line]
-            .len()
+            .line_length(line)
+            .unwrap_or(0)
+    }
+
+    /// Keeps the cursor and the top of the window on the same text after
+    /// an edit made from another window.
+    pub fn shift_lines(&mut self, shift: &LineShift) {
+        let shifted = |line: usize| match line {
+            line if line <= shift.line => line,
+            line if line <= shift.line + shift.removed => shift.line,
+            line => line - shift.removed + shift.added,
+        };
+        self.cursor.line = shifted(self.cursor.line);
+        self.top_line = shifted(self.top_line);
+        let lines_count = self.lines_count();
+        self.cursor.line = min(self.cursor.line, lines_count.saturating_sub(1));
+        self.top_line = min(self.top_line, self.cursor.line);
+        self.skip_rows = 0;
+        self.snap_to_EOL();
+        self.keep_scrolloff();
     }
 
     pub fn get_cursor_pos(&self) -> Position {
-        let BufferPosition { line, col } = self.cursor;
-        let line_numbers_width = format!("{}", self.lines_count()).chars().count() + 1;
-        if self.is_on_screen(&self.cursor) {
-            Position {
-                x: (col - self.leftmost_col + line_numbers_width + 2) as u16,
-                y: (line - self.top_line) as u16,
-            }
+        let line_numbers_width = self.line_numbers_width(self.last_height);
+        let (x, _) = self.display_span(&self.cursor);
+        let (x, y) = if self.settings.wrap {
+            let rows = self.line_rows(self.cursor.line);
+            let row = self.cursor_row(&rows);
+            let rows_above: usize = (self.top_line..self.cursor.line)
+                .map(|line| self.line_rows(line).len())
+                .sum();
+            let Some(y) = (rows_above + row).checked_sub(self.skip_rows) else {
+                return Position { x: 0, y: 0 };
+            };
+            let x = min(
+                x.max(rows[row].start) - rows[row].start,
+                rows[row].end - rows[row].start,
+            );
+            let x = min(x + rows[row].indent, self.last_width.saturating_sub(1));
+            (x, y)
+        } else if self.is_on_screen(&self.cursor) {
+            (x - self.leftmost_col, self.cursor.line - self.top_line)
+        } else {
+            return Position { x: 0, y: 0 };
+        };
+        if self.cursor.line < self.top_line || y >= self.last_height {
+            return Position { x: 0, y: 0 };
+        }
+        Position {
+            x: (x + line_numbers_width + sign_column::WIDTH as usize) as u16,
+            y: y as u16,
+        }
+    }
+
+    /// The cells taken by the line numbers of a window `height` rows tall.
+    fn line_numbers_width(&self, height: usize) -> usize {
+        self.settings.line_numbers.width(self.lines_count(), height)
+    }
+
+    /// Switches the line numbers to the next mode, and returns it.
+    pub fn cycle_line_numbers(&mut self) -> LineNumberType {
+        self.settings.line_numbers = self.settings.line_numbers.next();
+        self.settings.line_numbers
+    }
+
+    /// Turns wrapping long lines on or off.
+    pub fn toggle_wrap(&mut self) {
+        self.settings.wrap = !self.settings.wrap;
+        self.leftmost_col = 0;
+        self.skip_rows = 0;
+        self.scroll_to_cursor();
+    }
+
+    /// The rows that `line` is drawn on: several if it is wrapped, or the
+    /// part of it scrolled into view.
+    fn line_rows(&self, line: usize) -> Vec<Row> {
+        if !self.settings.wrap {
+            return vec![Row {
+                line,
+                col: 0,
+                start: self.leftmost_col,
+                end: self.leftmost_col + self.last_width,
+                indent: 0,
+            }];
+        }
+        let text = self
+            .buffer
+            .upgrade()
+            .clean_expect("wrapping a line of a dead buffer!")
+            .borrow()
+            .line(line);
+        wrap_line(
+            &text,
+            line,
+            self.last_width,
+            self.settings.showbreak.width(),
+            self.settings.tabstop,
+            self.settings.linebreak,
+        )
+    }
+
+    /// The index of the row the cursor is on among `rows`, those of its line.
+    fn cursor_row(&self, rows: &[Row]) -> usize {
+        self.row_of(rows, self.cursor.col)
+    }
+
+    /// The index of the row column `col` is on among `rows`, those of its
+    /// line.
+    fn row_of(&self, rows: &[Row], col: usize) -> usize {
+        rows.iter().rposition(|row| row.col <= col).unwrap_or(0)
+    }
+
+    /// The rows shown in the window, from the top line down.
+    fn screen_rows(&self) -> Vec<Row> {
+        let skip_rows = if self.settings.wrap {
+            self.skip_rows
         } else {
-            Position { x: 0, y: 0 }
+            0
+        };
+        let mut rows = vec![];
+        for line in self.top_line..self.lines_count() {
+            if rows.len() >= skip_rows + self.last_height {
+                break;
+            }
+            rows.extend(self.line_rows(line));
+        }
+        rows.drain(..min(skip_rows, rows.len()));
+        rows.truncate(self.last_height);
+        rows
+    }
+
+    /// The sign shown next to each of `rows`, on the first row of its line.
+    fn signs(&self, rows: &[Row]) -> Vec<Option<Sign>> {
+        let buffer = self
+            .buffer
+            .upgrade()
+            .clean_expect("drawing the signs of a dead buffer!");
+        let buffer = buffer.borrow();
+        rows.iter()
+            .map(|row| match row.is_first() {
+                true => buffer.signs().top(row.line).cloned(),
+                false => None,
+            })
+            .collect()
+    }
+
+    /// Takes in the size of the window about to be drawn, and scrolls so
+    /// that the cursor stays in view.
+    fn fit_to_area(&mut self, height: usize, width: usize) {
+        self.last_height = height;
+        self.last_width = width;
+        if !self.settings.wrap {
+            let cursor_rel_line =
+                (self.cur_vertical_percent * height.saturating_sub(1) as f32).round() as usize;
+            self.top_line = self.cursor.line.saturating_sub(cursor_rel_line);
+            return;
         }
+        // Rows may have been rewrapped to another width
+        self.keep_scrolloff_rows();
+    }
+
+    /// Moves the cursor to the row above or below on the screen, which is
+    /// another part of the same line if it is wrapped.
+    pub fn move_display_line(&mut self, mode: &Mode, dir: Vertical) {
+        if !self.settings.wrap || self.lines_count() == 0 {
+            let dir = match dir {
+                Vertical::Up => Rectilinear::Up,
+                Vertical::Down => Rectilinear::Down,
+            };
+            self.move_cursor(mode, dir);
+            return;
+        }
+        let rows = self.line_rows(self.cursor.line);
+        let row = self.cursor_row(&rows);
+        let (x, _) = self.display_span(&self.cursor);
+        let screen_x = x.saturating_sub(rows[row].start) + rows[row].indent;
+        let (target_rows, target) = match dir {
+            Vertical::Up if row > 0 => (rows, row - 1),
+            Vertical::Down if row + 1 < rows.len() => (rows, row + 1),
+            Vertical::Up if self.cursor.line > 0 => {
+                let rows = self.line_rows(self.cursor.line - 1);
+                let last = rows.len() - 1;
+                (rows, last)
+            }
+            Vertical::Down if self.cursor.line + 1 < self.lines_count() => {
+                (self.line_rows(self.cursor.line + 1), 0)
+            }
+            _ => return,
+        };
+        let target_row = &target_rows[target];
+        let text = self
+            .buffer
+            .upgrade()
+            .clean_expect("moving in a dead buffer!")
+            .borrow()
+            .line(target_row.line);
+        let x = target_row.start + screen_x.saturating_sub(target_row.indent);
+        let col = graphemes::col_at_display(&text, x, self.settings.tabstop);
+        // Stay on the row, short of the end of the line outside insert mode
+        let last_col = match target_rows.get(target + 1) {
+            Some(next) => next.col.saturating_sub(1),
+            None if matches!(mode, Mode::Insert) => graphemes::count(&text),
+            None => graphemes::count(&text).saturating_sub(1),
+        };
+        self.stick_to_EOL = false;
+        // Scroll a row at a time, rather than bringing the cursor to the
+        // middle of the window
+        let top = (self.top_line, self.skip_rows);
+        self.jump(&BufferPosition {
+            line: target_row.line,
+            col: col.clamp(target_row.col, max(last_col, target_row.col)),
+        });
+        (self.top_line, self.skip_rows) = top;
+        self.keep_scrolloff();
     }
 }
 
@@ -432,7 +811,15 @@ impl TextWindow {
         TextWindow { buffer, theme }
     }
 
-    fn build_lines(&self, height: u16, width: usize, state: &mut TextWindowState) -> Vec<Line<'_>> {
+    pub fn set_theme(&mut self, theme: Weak<Theme>) {
+        self.theme = theme;
+    }
+
+    pub fn set_buffer(&mut self, buffer: Weak<RefCell<Buffer>>) {
+        self.buffer = buffer;
+    }
+
+    fn build_lines(&self, rows: &[Row], state: &TextWindowState) -> Vec<Line<'_>> {
         let buffer = self
             .buffer
             .upgrade()
@@ -441,45 +828,40 @@ impl TextWindow {
             .theme
             .upgrade()
             .clean_expect("referencing dropped theme!");
-
-        state.last_height = height.into();
-        state.last_width = width;
-        let cursor_rel_line: usize =
-            (state.cur_vertical_percent * (height - 1) as f32).round() as usize;
-        let top_line: usize = state.cursor.line.saturating_sub(cursor_rel_line);
-        let last_line: usize = min(top_line + height as usize, state.lines_count());
         let line_style = Style::default()
             .fg(theme.text_foreground)
             .bg(theme.text_background);
-        return buffer.borrow().lines[top_line..last_line]
-            .iter()
-            .map(|line| {
-                if state.leftmost_col < line.len() {
-                    line[state.leftmost_col..].to_string()
-                } else {
-                    "".to_string()
+        let buffer = buffer.borrow();
+        let width = state.last_width;
+        rows.iter()
+            .map(|row| {
+                let text = buffer.line(row.line);
+                let shown = row.end - row.start;
+                let mut visible = visible_text(&text, row.start, shown, state.settings.tabstop);
+                visible.extend(repeat_n(' ', width.saturating_sub(row.indent + shown)));
+                match row.indent {
+                    0 => Line::styled(visible, line_style),
+                    _ => Line::from(vec![
+                        Span::styled(
+                            state.settings.showbreak.clone(),
+                            theme.styles.line_numbers_normal,
+                        ),
+                        Span::raw(visible),
+                    ])
+                    .style(line_style),
                 }
             })
-            .map(|line| Line::styled(format!("{line: <width$}"), line_style))
-            .collect();
+            .collect()
     }
 
-    fn highlight_cursor(&self, lines: &mut Vec<Line>, state: &mut TextWindowState) {
+    fn highlight_cursor(&self, lines: &mut Vec<Line>, rows: &[Row], state: &TextWindowState) {
         if lines.is_empty() {
             lines.push(Line::from(String::from_iter(repeat_n(
                 " ",
-                state.last_width - 1,
+                state.last_width.saturating_sub(1),
             ))));
         }
 
-        if state.cursor.line < state.top_line {
-            return;
-        }
-        let line = state.cursor.line - state.top_line;
-        if line >= lines.len() {
-            return;
-        }
-
         let theme = self
             .theme
             .upgrade()
@@ -487,16 +869,14 @@ impl TextWindow {
         let line_style = Style::default()
             .bg(theme.selected_line_background)
             .fg(theme.selected_line_foreground);
-
-        let old_line: String = lines[line].to_owned().into();
-        if old_line.is_empty() {
-            lines[line] = Line::styled(" ", line_style);
-            return;
+        for (line, row) in lines.iter_mut().zip(rows) {
+            if row.line == state.cursor.line {
+                *line = restyle(line, row.indent, usize::MAX, line_style);
+            }
         }
-        lines[line] = Line::from(Span::styled(old_line, line_style));
     }
 
-    fn highlight_selection(&self, lines: &mut [Line], state: &mut TextWindowState) {
+    fn highlight_selection(&self, lines: &mut [Line], rows: &[Row], state: &TextWindowState) {
         if lines.is_empty() {
             graceful_exit(Some("attempted to highlight selection in empty buffer"));
         }
@@ -512,79 +892,137 @@ impl TextWindow {
 
         let upper = min(fixed_point, moving_point);
         let lower = max(fixed_point, moving_point);
-        let selection_off_screen =
-            lower.line < state.top_line || upper.line >= state.top_line + lines.len();
-        if selection_off_screen {
+        let buffer = self
+            .buffer
+            .upgrade()
+            .clean_expect("highlighting selection in a dead buffer!");
+        let buffer = buffer.borrow();
+        let tabstop = state.settings.tabstop;
+        let theme = self
+            .theme
+            .upgrade()
+            .clean_expect("referencing dropped theme!");
+        for (line, row) in lines.iter_mut().zip(rows) {
+            let index = row.line;
+            if index < upper.line || index > lower.line {
+                continue;
+            }
+            let start = if index == upper.line {
+                buffer.display_col(upper, tabstop)
+            } else {
+                0
+            };
+            let end = if index == lower.line {
+                let after = BufferPosition {
+                    line: index,
+                    col: lower.col + 1,
+                };
+                buffer.display_col(&after, tabstop)
+            } else {
+                usize::MAX
+            };
+            let (start, end) = row.cells(start, end);
+            *line = restyle(line, start, end, theme.styles.selected_text);
+        }
+    }
+
+    fn highlight_matches(&self, lines: &mut [Line], rows: &[Row], state: &TextWindowState) {
+        let Some(ref regex) = state.search_highlight else {
             return;
+        };
+        let buffer = self
+            .buffer
+            .upgrade()
+            .clean_expect("highlighting matches in a dead buffer!");
+        let theme = self
+            .theme
+            .upgrade()
+            .clean_expect("referencing dropped theme!");
+        for (line, row) in lines.iter_mut().zip(rows) {
+            let text = buffer.borrow().line(row.line);
+            for (start, end) in line_matches(&text, regex) {
+                let start = graphemes::display_col(&text, start, state.settings.tabstop);
+                let end = graphemes::display_col(&text, end, state.settings.tabstop);
+                let (start, end) = row.cells(start, end);
+                *line = restyle(line, start, end, theme.styles.search_match);
+            }
         }
+    }
 
+    fn highlight_current_match(&self, lines: &mut [Line], rows: &[Row], state: &TextWindowState) {
+        let Some((ref start, end)) = state.current_match else {
+            return;
+        };
+        let text = self
+            .buffer
+            .upgrade()
+            .clean_expect("highlighting a match in a dead buffer!")
+            .borrow()
+            .line(start.line);
+        let tabstop = state.settings.tabstop;
+        let first = graphemes::display_col(&text, start.col, tabstop);
+        let last = graphemes::display_col(&text, end.max(start.col + 1), tabstop);
         let theme = self
             .theme
             .upgrade()
             .clean_expect("referencing dropped theme!");
-        let selection_style = theme.styles.selected_text;
-
-        if upper.line == lower.line {
-            let line = upper.line - state.top_line;
-            let old_line = lines[line].to_owned().to_string();
-            if old_line.is_empty() {
-                lines[line] = Line::styled(" ", selection_style);
-                return;
+        for (line, row) in lines.iter_mut().zip(rows) {
+            if row.line == start.line {
+                let (first, last) = row.cells(first, last);
+                *line = restyle(line, first, last, theme.styles.selected_text);
             }
-            let old_line: Vec<_> = old_line.chars().collect();
-            let start_unselected = Span::styled(
-                old_line[..upper.col].iter().collect::<String>(),
-                theme.styles.regular_text,
-            );
-            let selected = Span::styled(
-                old_line[upper.col..=lower.col].iter().collect::<String>(),
-                selection_style,
-            );
-            let end_unselected = Span::styled(
-                old_line[lower.col + 1..].iter().collect::<String>(),
-                theme.styles.regular_text,
-            );
-            lines[line] = Line::from(vec![start_unselected, selected, end_unselected]);
-        } else {
-            for i in 0..lines.len() {
-                let index = i + state.top_line;
-                if index == upper.line {
-                    let old_line = lines[index].to_string();
-                    let start_unselected =
-                        Span::styled(old_line[..upper.col].to_string(), theme.styles.regular_text);
-                    let selected = Span::styled(old_line[upper.col..].to_string(), selection_style);
-                    lines[index] = Line::from(vec![start_unselected, selected]);
-                } else if index > upper.line && index < lower.line {
-                    let old_line = lines[index].to_string();
-                    let new_line = Line::styled(old_line, selection_style);
-                    lines[index] = new_line;
-                } else if index == lower.line {
-                    let old_line = lines[index].to_string();
-                    let selected =
-                        Span::styled(old_line[..=lower.col].to_string(), selection_style);
-                    let end_unselected = Span::styled(
-                        old_line[lower.col + 1..].to_string(),
-                        theme.styles.regular_text,
-                    );
-                    lines[index] = Line::from(vec![selected, end_unselected]);
-                }
+        }
+    }
+}
+
+/// Applies `style` over the cells of `line` from screen column `start` up
+/// to, but not including, screen column `end`.
+fn restyle(line: &Line, start: usize, end: usize, style: Style) -> Line<'static> {
+    let mut spans = vec![];
+    let mut x = 0;
+    for span in &line.spans {
+        let mut pieces: [String; 3] = Default::default();
+        for grapheme in span.content.graphemes(true) {
+            let piece = match x {
+                x if x < start => 0,
+                x if x < end => 1,
+                _ => 2,
+            };
+            pieces[piece].push_str(grapheme);
+            x += grapheme.width();
+        }
+        for (i, content) in pieces.into_iter().enumerate() {
+            if content.is_empty() {
+                continue;
             }
+            let piece = Span::styled(content, span.style);
+            spans.push(if i == 1 {
+                piece.patch_style(style)
+            } else {
+                piece
+            });
         }
     }
+    Line {
+        spans,
+        style: line.style,
+        alignment: line.alignment,
+    }
 }
 
 impl StatefulWidget for TextWindow {
     type State = TextWindowState;
 
     fn render(self, area: Rect, tui_buf: &mut TUI_Buffer, state: &mut Self::State) {
-        let line_numbers_width: u16 = (format!("{}", state.lines_count()).chars().count() + 1)
+        let line_numbers_width: u16 = state
+            .line_numbers_width(area.height.into())
             .try_into()
             .clean_expect("line number too large!");
         let window_layout = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![
                 Constraint::Length(line_numbers_width),
-                Constraint::Length(2),
+                Constraint::Length(sign_column::WIDTH),
                 Constraint::Fill(1),
             ])
             .split(area);
@@ -593,28 +1031,43 @@ impl StatefulWidget for TextWindow {
             .upgrade()
             .clean_expect("referencing dropped theme!");
         let lines_area = window_layout[2];
-        let mut lines = self.build_lines(lines_area.height, lines_area.width.into(), state);
+        state.fit_to_area(lines_area.height.into(), lines_area.width.into());
+        let rows = state.screen_rows();
+        let mut lines = self.build_lines(&rows, state);
         if matches!(state.mode(), Mode::Visual(_)) {
-            self.highlight_selection(&mut lines, state);
+            self.highlight_matches(&mut lines, &rows, state);
+            self.highlight_selection(&mut lines, &rows, state);
         } else {
-            self.highlight_cursor(&mut lines, state);
+            self.highlight_cursor(&mut lines, &rows, state);
+            self.highlight_matches(&mut lines, &rows, state);
+            self.highlight_current_match(&mut lines, &rows, state);
         }
         let line_numbers_area = window_layout[0];
-        let line_hints_area = window_layout[1];
-        let line_hints = Paragraph::new("").style(Style::default().bg(theme.text_background));
-        let line_numbers = LineNumbers::new(
-            Relative,
-            state.top_line + 1,
-            state.top_line + area.height as usize,
-            state.cursor.line + 1,
-        )
-        .set_styles(
-            theme.styles.line_numbers_normal,
-            theme.styles.line_numbers_selected,
+        let sign_column_area = window_layout[1];
+        let sign_column = SignColumn::new(
+            state.signs(&rows),
+            Style::default().bg(theme.text_background),
+        );
+        // Rows past the end of the buffer go on counting lines
+        let mut numbers: Vec<Option<usize>> = rows
+            .iter()
+            .map(|row| row.is_first().then_some(row.line + 1))
+            .collect();
+        let next = rows.last().map_or(state.top_line, |row| row.line + 1);
+        numbers.extend(
+            (next + 1..)
+                .take(area.height as usize - rows.len())
+                .map(Some),
         );
+        let line_numbers =
+            LineNumbers::new(state.settings.line_numbers, numbers, state.cursor.line + 1)
+                .set_styles(
+                    theme.styles.line_numbers_normal,
+                    theme.styles.line_numbers_selected,
+                );
 
         line_numbers.render(line_numbers_area, tui_buf);
-        line_hints.render(line_hints_area, tui_buf);
+        sign_column.render(sign_column_area, tui_buf);
         if lines.len() < lines_area.height as usize {
             let gap = lines_area.height - lines.len() as u16;
             let gap_area = Layout::default()
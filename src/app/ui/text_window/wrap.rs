@@ -0,0 +1,88 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::app::buffer::graphemes;
+
+/// A screen row of a text window, showing part of a buffer line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub line: usize,
+    /// The first column of the line shown on the row.
+    pub col: usize,
+    /// The screen columns of the line, counted from its start, that the row
+    /// shows: from `start` up to, but not including, `end`.
+    pub start: usize,
+    pub end: usize,
+    /// The cells taken by the wrap indicator in front of the text.
+    pub indent: usize,
+}
+
+impl Row {
+    /// Where the screen columns `start..end` of the line fall on the row,
+    /// counted from its left edge. An `end` of `usize::MAX` reaches the
+    /// right edge of the window.
+    pub fn cells(&self, start: usize, end: usize) -> (usize, usize) {
+        let clip = |x: usize| x.clamp(self.start, self.end) - self.start + self.indent;
+        match end {
+            usize::MAX => (clip(start), usize::MAX),
+            end => (clip(start), clip(end)),
+        }
+    }
+
+    /// Whether this is the row a line starts on.
+    pub fn is_first(&self) -> bool {
+        self.col == 0
+    }
+}
+
+/// Splits `text`, the contents of line `line`, into rows at most `width`
+/// cells wide. Rows after the first start with `indent` cells for the wrap
+/// indicator. With `word_breaks`, lines are broken after a blank rather than
+/// within a word where possible.
+pub fn wrap_line(
+    text: &str,
+    line: usize,
+    width: usize,
+    indent: usize,
+    tabstop: usize,
+    word_breaks: bool,
+) -> Vec<Row> {
+    let indent = if indent < width { indent } else { 0 };
+    // The screen columns each column of the line starts and ends at, and
+    // whether it is blank
+    let mut cells = vec![];
+    let mut x = 0;
+    for grapheme in text.graphemes(true) {
+        let end = x + graphemes::width(grapheme, x, tabstop);
+        cells.push((x, end, grapheme.chars().all(char::is_whitespace)));
+        x = end;
+    }
+    let line_end = x;
+
+    let mut rows: Vec<Row> = vec![];
+    let mut col = 0;
+    while rows.is_empty() || col < cells.len() {
+        let indent = if rows.is_empty() { 0 } else { indent };
+        let room = width - indent;
+        let start = cells.get(col).map_or(line_end, |&(x, _, _)| x);
+        // Every row shows at least one column, even if it does not fit
+        let mut next = col + 1;
+        while next < cells.len() && cells[next].1 - start <= room {
+            next += 1;
+        }
+        if word_breaks && next < cells.len() {
+            if let Some(word_start) = (col + 1..=next).rfind(|&i| cells[i - 1].2 && !cells[i].2) {
+                next = word_start;
+            }
+        }
+        let end = cells.get(next).map_or(line_end, |&(x, _, _)| x);
+        rows.push(Row {
+            line,
+            col,
+            start,
+            end,
+            indent,
+        });
+        col = next;
+    }
+    rows
+}
@@ -1,26 +1,102 @@
-use clap::{arg, command, ArgAction};
-use std::error::Error;
+use clap::{arg, command, value_parser, ArgAction};
+use std::{env, error::Error, path::PathBuf};
+
+const CONFIG_DIR_NAME: &str = "minimodal";
+const KEYMAP_FILE_NAME: &str = "keymap.toml";
+const SETTINGS_FILE_NAME: &str = "config.toml";
+/// Looked up in the working directory and its ancestors.
+const PROJECT_SETTINGS_FILE_NAME: &str = ".minimodal.toml";
 
 #[derive(Debug)]
 pub struct Config {
     pub file_names: Vec<String>,
+    /// A keymap file given on the command line, which must exist.
+    pub keymap_path: Option<PathBuf>,
+    /// A configuration file given on the command line, which must exist.
+    pub settings_path: Option<PathBuf>,
+    /// A theme name or file given on the command line, overriding the one
+    /// in the configuration files.
+    pub theme: Option<String>,
 }
 
 impl Config {
     fn new() -> Self {
-        Config { file_names: vec![] }
+        Config {
+            file_names: vec![],
+            keymap_path: None,
+            settings_path: None,
+            theme: None,
+        }
+    }
+
+    /// The keymap file to load, if any: the one given on the command line,
+    /// or the one in the user's configuration directory if it exists.
+    pub fn keymap_file(&self) -> Option<PathBuf> {
+        if let Some(ref path) = self.keymap_path {
+            return Some(path.to_owned());
+        }
+        let path = config_dir()?.join(KEYMAP_FILE_NAME);
+        path.exists().then_some(path)
+    }
+
+    /// The configuration files to apply, later ones taking precedence: the
+    /// one given on the command line alone, or else the user's one followed
+    /// by the closest project one, each only if it exists.
+    pub fn settings_files(&self) -> Vec<PathBuf> {
+        if let Some(ref path) = self.settings_path {
+            return vec![path.to_owned()];
+        }
+        let user_file = config_dir()
+            .map(|dir| dir.join(SETTINGS_FILE_NAME))
+            .filter(|path| path.exists());
+        let project_file = env::current_dir().ok().and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(PROJECT_SETTINGS_FILE_NAME))
+                .find(|path| path.is_file())
+        });
+        user_file.into_iter().chain(project_file).collect()
     }
 }
 
+/// `$XDG_CONFIG_HOME/minimodal`, falling back to `~/.config/minimodal`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+/// `$XDG_STATE_HOME/minimodal`, falling back to `~/.local/state/minimodal`.
+pub fn state_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
 pub fn parse_command_line() -> Result<Config, Box<dyn Error>> {
     let mut config = Config::new();
     let arg_matches = command!()
         .arg(arg!([files] "List of files to open").action(ArgAction::Append))
+        .arg(
+            arg!(--config <FILE> "Configuration file to use instead of the user and project ones")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(arg!(--theme <THEME> "Built-in theme, user theme or theme file to use"))
+        .arg(
+            arg!(--keymap <FILE> "Keymap file to merge over the default bindings")
+                .value_parser(value_parser!(PathBuf)),
+        )
         .get_matches();
 
     if let Some(file_names) = arg_matches.get_many::<String>("files") {
         config.file_names = file_names.map(|x| x.to_owned()).collect();
     }
+    config.keymap_path = arg_matches.get_one::<PathBuf>("keymap").cloned();
+    config.settings_path = arg_matches.get_one::<PathBuf>("config").cloned();
+    config.theme = arg_matches.get_one::<String>("theme").cloned();
 
     Ok(config)
 }
@@ -1,11 +1,25 @@
 use crate::app::{
-    buffer::{Buffer, BufferPosition},
+    buffer::{signs::Sign, Buffer, BufferPosition},
+    cleanup::CleanUnwrap,
+    crash::{self, TrackedBuffer},
     editor::{actions::EditorAction, Editor, Mode},
-    theme::Theme,
+    keymap::KeyMap,
+    settings::Settings,
+    swap::{swap_path, untitled_swap_path, SwapFiles},
+    theme::{available_themes, Theme},
+    ui::leader_menu::SubMenu,
+    ui::line_numbers::LineNumberType,
+    ui::notification::Severity,
 };
-use std::{ffi::OsString, str::FromStr};
+use std::{ffi::OsString, str::FromStr, time::Duration};
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    backend::TestBackend,
+    layout::Position,
+    style::{Color, Modifier, Style},
+    Terminal,
+};
 
 fn type_lines(editor: &mut Editor, lines: &[&str]) {
     editor.execute_editor_action(EditorAction::EnterInsert);
@@ -22,7 +36,7 @@ fn type_lines(editor: &mut Editor, lines: &[&str]) {
 
 #[test]
 fn test_create_unnamed_editor() {
-    let editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     assert_eq!(editor.get_mode(), &Mode::Normal);
     assert!(editor.current_buffer().read_name().is_none());
@@ -36,7 +50,7 @@ fn test_create_named_editor() {
             OsString::from_str("newfile.txt").unwrap(),
             OsString::from_str("newdir/newfile.txt").unwrap(),
         )],
-        Theme::default(),
+        Settings::default(),
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     assert_eq!(editor.get_mode(), &Mode::Normal);
@@ -56,7 +70,7 @@ fn test_create_named_editor() {
 
 #[test]
 fn test_simple_one_line_input() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE));
@@ -71,12 +85,12 @@ fn test_simple_one_line_input() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 7 });
     assert_eq!(editor.get_mode(), &Mode::Normal);
-    assert_eq!(editor.current_buffer().lines, &["abcd123!".to_owned()]);
+    assert_eq!(editor.current_buffer().lines(), &["abcd123!".to_owned()]);
 }
 
 #[test]
 fn test_multi_line_input() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
@@ -101,12 +115,15 @@ fn test_multi_line_input() {
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 1 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
-    assert_eq!(editor.current_buffer().lines, &["", "Hello", "World", "!"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        &["", "Hello", "World", "!"]
+    );
 }
 
 #[test]
 fn test_text_with_multiple_line_breaks() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
@@ -134,24 +151,24 @@ fn test_text_with_multiple_line_breaks() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 3 });
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         &["abc", "defgh", "", "", "ijkl"]
     );
 }
 
 #[test]
 fn test_forward_deletion_in_empty_buffer_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
-    assert!(editor.current_buffer().lines.is_empty());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
-    assert!(editor.current_buffer().lines.is_empty());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
 }
 
 #[test]
 fn test_forward_deletion_at_the_end_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -163,11 +180,11 @@ fn test_forward_deletion_at_the_end_in_normal_mode() {
         }
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "qrst"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 3 });
@@ -175,16 +192,22 @@ fn test_forward_deletion_at_the_end_in_normal_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop", ""]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklmnop", ""]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop", ""]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklmnop", ""]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
 }
 
 #[test]
 fn test_forward_deletion_in_the_middle_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -196,35 +219,38 @@ fn test_forward_deletion_in_the_middle_in_normal_mode() {
         }
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('h'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 3 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijkmnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 3 });
     for _ in 0..4 {
         editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     }
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijk", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijk", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 2 });
     for _ in 0..3 {
         editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     }
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "", "qrstu"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "", "qrstu"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "", "qrstu"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "", "qrstu"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
 }
 
 #[test]
 fn test_forward_deletion_at_the_start_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -236,56 +262,59 @@ fn test_forward_deletion_at_the_start_in_normal_mode() {
         }
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "jklmnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     for _ in 0..3 {
         editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     }
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "mnop", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "mnop", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     for _ in 0..4 {
         editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
     }
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "", "qrstu"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "", "qrstu"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_in_empty_buffer_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
-    assert!(editor.current_buffer().lines.is_empty());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert!(editor.current_buffer().lines.is_empty());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
 }
 
 #[test]
 fn test_deletion_in_empty_buffer_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
-    assert!(editor.current_buffer().lines.is_empty());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
-    assert!(editor.current_buffer().lines.is_empty());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
-    assert!(editor.current_buffer().lines.is_empty());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_at_the_start_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -300,16 +329,16 @@ fn test_backward_deletion_at_the_start_in_normal_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_middle_and_start_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -322,27 +351,33 @@ fn test_backward_deletion_middle_and_start_in_normal_mode() {
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijkmnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 3 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "mnop", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "mnop", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "mnop", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "mnop", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_in_empty_line_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -355,16 +390,16 @@ fn test_backward_deletion_in_empty_line_in_normal_mode() {
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_at_the_end_in_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -376,27 +411,33 @@ fn test_backward_deletion_at_the_end_in_normal_mode() {
         }
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "qrsu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 3 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop", "u"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklmnop", "u"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('X'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop", "u"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklmnop", "u"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
 }
 
 #[test]
 fn test_forward_deletion_at_the_end_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -407,16 +448,16 @@ fn test_forward_deletion_at_the_end_in_insert_mode() {
             editor.handle_key_press(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
         }
     }
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 5 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 5 });
 }
 
 #[test]
 fn test_forward_deletion_middle_and_end_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -430,27 +471,30 @@ fn test_forward_deletion_middle_and_end_in_insert_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijkl", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijkl", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklqrstu"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "ijklqrstu"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
 }
 
 #[test]
 fn test_forward_deletion_at_the_start_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -466,11 +510,11 @@ fn test_forward_deletion_at_the_start_in_insert_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('0'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["bcdefgh", "ijklmnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
@@ -478,7 +522,7 @@ fn test_forward_deletion_at_the_start_in_insert_mode() {
 
 #[test]
 fn test_forward_deletion_appending_empty_line() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -494,11 +538,11 @@ fn test_forward_deletion_appending_empty_line() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 8 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 8 });
@@ -506,7 +550,7 @@ fn test_forward_deletion_appending_empty_line() {
 
 #[test]
 fn test_forward_deletion_appending_to_empty_line() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -520,11 +564,11 @@ fn test_forward_deletion_appending_to_empty_line() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
@@ -532,7 +576,7 @@ fn test_forward_deletion_appending_to_empty_line() {
 
 #[test]
 fn test_forward_deletion_joining_empty_lines() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -547,11 +591,11 @@ fn test_forward_deletion_joining_empty_lines() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
@@ -559,7 +603,7 @@ fn test_forward_deletion_joining_empty_lines() {
 
 #[test]
 fn test_backward_deletion_at_the_end_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -570,27 +614,30 @@ fn test_backward_deletion_at_the_end_in_insert_mode() {
             editor.handle_key_press(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
         }
     }
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 5 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "qrst"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 4 });
     for _ in 0..4 {
         editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     }
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop", ""]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklmnop", ""]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "ijklmnop"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "ijklmnop"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 8 });
 }
 
 #[test]
 fn test_backward_deletion_in_the_middle_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -603,28 +650,31 @@ fn test_backward_deletion_in_the_middle_in_insert_mode() {
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijkmnop", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 3 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefgh", "mnop", "qrstu"]);
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "mnop", "qrstu"]
+    );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, ["abcdefghmnop", "qrstu"]);
+    assert_eq!(editor.current_buffer().lines(), ["abcdefghmnop", "qrstu"]);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 8 });
 }
 
 #[test]
 fn test_backward_deletion_at_the_start_in_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -639,16 +689,16 @@ fn test_backward_deletion_at_the_start_in_insert_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('I'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
 }
 
 #[test]
 fn test_backward_deletion_appending_to_empty_line() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -661,11 +711,11 @@ fn test_backward_deletion_appending_to_empty_line() {
     }
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('I'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
@@ -673,7 +723,7 @@ fn test_backward_deletion_appending_to_empty_line() {
 
 #[test]
 fn test_backward_deletion_joining_empty_lines() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefgh", "ijklmnop", "", "", "qrstu"];
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     for (i, line) in lines.iter().enumerate() {
@@ -687,11 +737,11 @@ fn test_backward_deletion_joining_empty_lines() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('k'), KeyModifiers::NONE));
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
     assert_eq!(
-        editor.current_buffer().lines,
+        editor.current_buffer().lines(),
         ["abcdefgh", "ijklmnop", "", "qrstu"]
     );
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
@@ -699,10 +749,10 @@ fn test_backward_deletion_joining_empty_lines() {
 
 #[test]
 fn test_normal_mode_arrow_movement() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefg", "hij", "klmnop", "", "qrstu"];
     type_lines(&mut editor, &lines);
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 4 });
@@ -746,13 +796,13 @@ fn test_normal_mode_arrow_movement() {
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 2 });
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
 }
 
 #[test]
 fn test_empty_buffer_normal_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
-    assert!(editor.current_buffer().lines.is_empty());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
@@ -766,8 +816,8 @@ fn test_empty_buffer_normal_mode() {
 
 #[test]
 fn test_empty_buffer_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
-    assert!(editor.current_buffer().lines.is_empty());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    assert!(editor.current_buffer().lines().is_empty());
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('i'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
@@ -789,10 +839,10 @@ fn test_empty_buffer_insert_mode() {
 
 #[test]
 fn test_horizontal_movement_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefg", "hij", "klmnop", "", "qrstu"];
     type_lines(&mut editor, &lines);
-    assert_eq!(editor.current_buffer().lines, lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 4 });
     editor.handle_key_press(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 5 });
@@ -844,7 +894,7 @@ fn test_horizontal_movement_insert_mode() {
 
 #[test]
 fn test_vertical_movement_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefg", "hij", "klmnop", "", "qrstu"];
     type_lines(&mut editor, &lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 4 });
@@ -889,7 +939,7 @@ fn test_vertical_movement_insert_mode() {
 
 #[test]
 fn test_home_and_end_insert_mode() {
-    let mut editor = Editor::new(vec![Buffer::untitled()], Theme::default());
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
     let lines = ["abcdefg", "hij", "klmnop", "", "qrstu"];
     type_lines(&mut editor, &lines);
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 4 });
@@ -939,3 +989,1953 @@ fn test_home_and_end_insert_mode() {
     editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
     assert_eq!(editor.current_bufpos(), BufferPosition { line: 4, col: 0 });
 }
+
+fn type_command(editor: &mut Editor, command: &str) {
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
+    for c in command.chars() {
+        editor.handle_key_press(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+    editor.handle_key_press(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+}
+
+#[test]
+fn test_command_mode_editing_and_cancelling() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
+    assert_eq!(editor.get_mode(), &Mode::Command);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "wq");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "w");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert!(editor.is_active());
+    assert!(editor.current_buffer().lines().is_empty());
+
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+}
+
+#[test]
+fn test_command_mode_errors_are_shown_inline() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_command(&mut editor, "frobnicate");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert!(editor.is_active());
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Not an editor command: frobnicate")
+    );
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('l'), KeyModifiers::NONE));
+    assert_eq!(editor.command_line().error(), None);
+
+    type_command(&mut editor, "e");
+    assert_eq!(editor.command_line().error(), Some("Argument required: e"));
+    type_command(&mut editor, "q now");
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Trailing characters: now")
+    );
+    assert!(editor.is_active());
+}
+
+#[test]
+fn test_command_mode_tabs_and_quit() {
+    let mut editor = Editor::new(
+        vec![Buffer::untitled(), Buffer::untitled()],
+        Settings::default(),
+    );
+    assert_eq!(editor.current_tab_index(), 0);
+    type_command(&mut editor, "tabnext");
+    assert_eq!(editor.current_tab_index(), 1);
+    type_command(&mut editor, "tabn");
+    assert_eq!(editor.current_tab_index(), 0);
+    type_command(&mut editor, "tabp");
+    assert_eq!(editor.current_tab_index(), 1);
+    type_command(&mut editor, "tabedit newdir/newfile.txt");
+    assert_eq!(editor.tab_count(), 3);
+    assert_eq!(editor.current_tab_index(), 2);
+    assert_eq!(
+        editor.current_buffer().read_name(),
+        Some(OsString::from_str("newfile.txt").unwrap().as_os_str())
+    );
+    type_command(&mut editor, "q");
+    assert!(!editor.is_active());
+}
+
+#[test]
+fn test_command_history() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_command(&mut editor, "tabn");
+    type_command(&mut editor, "nope");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char(':'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "nope");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "tabn");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "nope");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+    assert_eq!(editor.command_line().input(), "");
+}
+
+#[test]
+fn test_undo_groups_insert_session() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let lines = ["abc", "def"];
+    type_lines(&mut editor, &lines);
+    assert_eq!(editor.current_buffer().lines(), lines);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert!(editor.current_buffer().lines().is_empty());
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert!(editor.current_buffer().lines().is_empty());
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+    assert_eq!(editor.current_buffer().lines(), lines);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
+}
+
+#[test]
+fn test_undo_replace_line_and_deletions() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let lines = ["abcdefgh", "ijklmnop", "qrstu"];
+    type_lines(&mut editor, &lines);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('S'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["abcdefgh", "z", "qrstu"]);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), lines);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 4 });
+
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklop", "qrstu"]
+    );
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklnop", "qrstu"]
+    );
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), lines);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklnop", "qrstu"]
+    );
+
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('!'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklnop", "!", "qrstu"]
+    );
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklnop", "!", "qrstu"]
+    );
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::NONE));
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdefgh", "ijklnop", "qrstu"]
+    );
+}
+
+#[test]
+fn test_buffer_load_and_save_round_trip() {
+    let path = std::env::temp_dir().join("minimodal_test_round_trip.txt");
+    std::fs::write(&path, "first\nsecond\n\nfourth\n").unwrap();
+    let mut buffer = Buffer::open(&path).unwrap();
+    assert_eq!(buffer.lines(), ["first", "second", "", "fourth"]);
+    assert_eq!(buffer.lines_count(), 4);
+    assert_eq!(buffer.line_length(3), Some(6));
+    assert_eq!(buffer.line_length(4), None);
+    assert_eq!(buffer.line_slice(1, 2, 3), "con");
+    assert_eq!(buffer.line_slice(1, 4, 100), "nd");
+    buffer.join_with_next_line(0);
+    buffer.split_line(&BufferPosition { line: 0, col: 5 });
+    buffer.insert_char('!', &BufferPosition { line: 2, col: 0 });
+    buffer.save(false).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "first\nsecond\n!\nfourth\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_editing_multibyte_characters() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["naïve", "çà"]);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["nïve", "çà"]);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('A'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('é'), KeyModifiers::NONE));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["nïveé", "çà"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 4 });
+}
+
+fn press_keys(editor: &mut Editor, keys: &str) {
+    for c in keys.chars() {
+        let code = match c {
+            '\x1b' => KeyCode::Esc,
+            '\n' => KeyCode::Enter,
+            '\x08' => KeyCode::Backspace,
+            c => KeyCode::Char(c),
+        };
+        editor.handle_key_press(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+}
+
+#[test]
+fn test_delete_operator_with_motions() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let lines = ["abcdefgh", "ijklmnop", "qrstu", "vwxyz"];
+    type_lines(&mut editor, &lines);
+    press_keys(&mut editor, "kkk0ll");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 2 });
+    press_keys(&mut editor, "d$");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["ab", "ijklmnop", "qrstu", "vwxyz"]
+    );
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 1 });
+    press_keys(&mut editor, "jdl");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["ab", "iklmnop", "qrstu", "vwxyz"]
+    );
+    press_keys(&mut editor, "d0");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["ab", "klmnop", "qrstu", "vwxyz"]
+    );
+    press_keys(&mut editor, "dj");
+    assert_eq!(editor.current_buffer().lines(), ["ab", "vwxyz"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
+    press_keys(&mut editor, "dd");
+    assert_eq!(editor.current_buffer().lines(), ["ab"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 0 });
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines(), ["ab", "vwxyz"]);
+    press_keys(&mut editor, "dy");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_buffer().lines(), ["ab", "vwxyz"]);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('d'), KeyModifiers::NONE));
+    assert!(matches!(editor.get_mode(), Mode::OperatorPending(_)));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_buffer().lines(), ["ab", "vwxyz"]);
+}
+
+#[test]
+fn test_operators_to_end_of_an_empty_line() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["a", "", "b"]);
+    press_keys(&mut editor, "ggjD");
+    assert_eq!(editor.current_buffer().lines(), ["a", "", "b"]);
+    press_keys(&mut editor, "d$");
+    assert_eq!(editor.current_buffer().lines(), ["a", "", "b"]);
+    press_keys(&mut editor, "Cx\x1b");
+    assert_eq!(editor.current_buffer().lines(), ["a", "x", "b"]);
+}
+
+#[test]
+fn test_change_operator() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["hello world", "second"]);
+    press_keys(&mut editor, "k0lllllc$");
+    assert_eq!(editor.get_mode(), &Mode::Insert);
+    press_keys(&mut editor, " there");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["hello there", "second"]);
+    press_keys(&mut editor, "jcc");
+    assert_eq!(editor.current_buffer().lines(), ["hello there", ""]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
+    press_keys(&mut editor, "new");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["hello there", "new"]);
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines(), ["hello there", "second"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 5 });
+    press_keys(&mut editor, "hhC");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE));
+    assert_eq!(editor.current_buffer().lines(), ["hello there", "sec"]);
+}
+
+#[test]
+fn test_yank_and_paste() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one", "two"]);
+    press_keys(&mut editor, "kyyjp");
+    assert_eq!(editor.current_buffer().lines(), ["one", "two", "one"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 2, col: 0 });
+    press_keys(&mut editor, "P");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["one", "two", "one", "one"]
+    );
+    press_keys(&mut editor, "0y$jp");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["one", "two", "one", "oonene"]
+    );
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 3, col: 3 });
+    press_keys(&mut editor, "0Pu");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["one", "two", "one", "oonene"]
+    );
+}
+
+#[test]
+fn test_visual_mode_operators() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["abcdef", "ghijkl"]);
+    press_keys(&mut editor, "k0lvjd");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_buffer().lines(), ["aijkl"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 1 });
+    press_keys(&mut editor, "vly$p");
+    assert_eq!(editor.current_buffer().lines(), ["aijklij"]);
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 6 });
+}
+
+#[test]
+fn test_indent_operators() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["fn main() {", "body", "", "}"]);
+    press_keys(&mut editor, "kk>j");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["fn main() {", "    body", "", "}"]
+    );
+    press_keys(&mut editor, ">>");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["fn main() {", "        body", "", "}"]
+    );
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 8 });
+    press_keys(&mut editor, "<<");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["fn main() {", "    body", "", "}"]
+    );
+    press_keys(&mut editor, "u");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["fn main() {", "        body", "", "}"]
+    );
+}
+
+fn pos(line: usize, col: usize) -> BufferPosition {
+    BufferPosition { line, col }
+}
+
+#[test]
+fn test_word_motions() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["foo.bar(baz) qux", "", "  last word"]);
+    press_keys(&mut editor, "gg");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(0, 3));
+    press_keys(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    press_keys(&mut editor, "W");
+    assert_eq!(editor.current_bufpos(), pos(0, 13));
+    press_keys(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(2, 2));
+    press_keys(&mut editor, "e");
+    assert_eq!(editor.current_bufpos(), pos(2, 5));
+    press_keys(&mut editor, "ee");
+    assert_eq!(editor.current_bufpos(), pos(2, 10));
+    press_keys(&mut editor, "bb");
+    assert_eq!(editor.current_bufpos(), pos(2, 2));
+    press_keys(&mut editor, "b");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "B");
+    assert_eq!(editor.current_bufpos(), pos(0, 13));
+    press_keys(&mut editor, "B");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "E");
+    assert_eq!(editor.current_bufpos(), pos(0, 11));
+}
+
+#[test]
+fn test_word_motions_with_operators() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one two three", "four"]);
+    press_keys(&mut editor, "ggdw");
+    assert_eq!(editor.current_buffer().lines(), ["two three", "four"]);
+    press_keys(&mut editor, "de");
+    assert_eq!(editor.current_buffer().lines(), [" three", "four"]);
+    press_keys(&mut editor, "wdw");
+    assert_eq!(editor.current_buffer().lines(), [" ", "four"]);
+    press_keys(&mut editor, "jcwfive");
+    press_keys(&mut editor, "\x1b");
+    assert_eq!(editor.current_buffer().lines(), [" ", "five"]);
+    press_keys(&mut editor, "0db");
+    assert_eq!(editor.current_buffer().lines(), ["", "five"]);
+}
+
+#[test]
+fn test_paragraph_motions() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["a", "b", "", "", "c", "d"]);
+    press_keys(&mut editor, "gg}");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+    press_keys(&mut editor, "}");
+    assert_eq!(editor.current_bufpos(), pos(5, 0));
+    press_keys(&mut editor, "{");
+    assert_eq!(editor.current_bufpos(), pos(3, 0));
+    press_keys(&mut editor, "{");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "d}");
+    assert_eq!(editor.current_buffer().lines(), ["", "", "", "c", "d"]);
+}
+
+#[test]
+fn test_find_char_motions() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["a,b,c,d"]);
+    press_keys(&mut editor, "0f,");
+    assert_eq!(editor.current_bufpos(), pos(0, 1));
+    press_keys(&mut editor, ";");
+    assert_eq!(editor.current_bufpos(), pos(0, 3));
+    press_keys(&mut editor, ",");
+    assert_eq!(editor.current_bufpos(), pos(0, 1));
+    press_keys(&mut editor, "t,");
+    assert_eq!(editor.current_bufpos(), pos(0, 2));
+    press_keys(&mut editor, ";");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    press_keys(&mut editor, "Fa");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "fz");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "$T,");
+    assert_eq!(editor.current_bufpos(), pos(0, 6));
+    press_keys(&mut editor, "0dt,");
+    assert_eq!(editor.current_buffer().lines(), [",b,c,d"]);
+    press_keys(&mut editor, "df,");
+    assert_eq!(editor.current_buffer().lines(), ["c,d"]);
+    press_keys(&mut editor, "d\x1b");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+}
+
+#[test]
+fn test_first_line_and_matching_bracket() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["  fn f(a[0]) {", "    g();", "}"]);
+    press_keys(&mut editor, "gg");
+    assert_eq!(editor.current_bufpos(), pos(0, 2));
+    press_keys(&mut editor, "%");
+    assert_eq!(editor.current_bufpos(), pos(0, 11));
+    press_keys(&mut editor, "%");
+    assert_eq!(editor.current_bufpos(), pos(0, 6));
+    press_keys(&mut editor, "l%");
+    assert_eq!(editor.current_bufpos(), pos(0, 10));
+    press_keys(&mut editor, "$%");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+    press_keys(&mut editor, "%");
+    assert_eq!(editor.current_bufpos(), pos(0, 13));
+    press_keys(&mut editor, "Gdgg");
+    assert!(editor.current_buffer().is_empty());
+}
+
+#[test]
+fn test_motions_extend_visual_selection() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one two three"]);
+    press_keys(&mut editor, "0vwd");
+    assert_eq!(editor.current_buffer().lines(), ["wo three"]);
+    press_keys(&mut editor, "vf d");
+    assert_eq!(editor.current_buffer().lines(), ["three"]);
+    press_keys(&mut editor, "ve");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+}
+
+#[test]
+fn test_count_prefixes() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let lines: Vec<String> = (1..=12).map(|i| format!("line {i}")).collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    type_lines(&mut editor, &lines);
+    press_keys(&mut editor, "gg5j");
+    assert_eq!(editor.current_bufpos(), pos(5, 0));
+    press_keys(&mut editor, "1");
+    assert_eq!(editor.pending_count(), Some(1));
+    press_keys(&mut editor, "0");
+    assert_eq!(editor.pending_count(), Some(10));
+    press_keys(&mut editor, "G");
+    assert_eq!(editor.pending_count(), None);
+    assert_eq!(editor.current_bufpos(), pos(9, 0));
+    press_keys(&mut editor, "99G");
+    assert_eq!(editor.current_bufpos(), pos(11, 0));
+    press_keys(&mut editor, "2gg");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "3k");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "$0");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "3\x1b");
+    assert_eq!(editor.pending_count(), None);
+}
+
+#[test]
+fn test_count_prefixes_with_edits() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(
+        &mut editor,
+        &["abcdef", "one two three four", "a", "b", "c"],
+    );
+    press_keys(&mut editor, "gg3x");
+    assert_eq!(editor.current_buffer().lines()[0], "def");
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines()[0], "abcdef");
+    press_keys(&mut editor, "j0d2w");
+    assert_eq!(editor.current_buffer().lines()[1], "three four");
+    press_keys(&mut editor, "u2d2w");
+    assert_eq!(editor.current_buffer().lines()[1], "");
+    press_keys(&mut editor, "uc2wx\x1b");
+    assert_eq!(editor.current_buffer().lines()[1], "x three four");
+    press_keys(&mut editor, "j2dd");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["abcdef", "x three four", "c"]
+    );
+    press_keys(&mut editor, "kk0d2fe");
+    assert_eq!(editor.current_buffer().lines()[0], "f");
+    press_keys(&mut editor, "2p");
+    assert_eq!(editor.current_buffer().lines()[0], "fabcdeabcde");
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines()[0], "f");
+}
+
+#[test]
+fn test_key_sequences_and_modifiers() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one", "two", "three"]);
+    press_keys(&mut editor, "2g");
+    assert_eq!(editor.pending_keys(), "2g");
+    press_keys(&mut editor, "g");
+    assert_eq!(editor.pending_keys(), "");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+
+    // An incomplete sequence falls back to its last key
+    press_keys(&mut editor, "gj");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+    press_keys(&mut editor, "g");
+    editor.handle_key_timeout();
+    assert_eq!(editor.pending_keys(), "");
+    press_keys(&mut editor, "g");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+    editor.handle_key_timeout();
+
+    press_keys(&mut editor, "ddu");
+    assert_eq!(editor.current_buffer().lines(), ["one", "two", "three"]);
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+    assert_eq!(editor.current_buffer().lines(), ["one", "two"]);
+
+    // Modified characters are not typed in insert mode
+    press_keys(&mut editor, "i");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('R'), KeyModifiers::SHIFT));
+    press_keys(&mut editor, "\x1b");
+    assert_eq!(editor.current_buffer().lines(), ["one", "Rtwo"]);
+
+    press_keys(&mut editor, "dgx");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_buffer().lines(), ["one", "Rtwo"]);
+}
+
+fn keymap_from(toml_source: &str) -> Result<KeyMap, String> {
+    let table = toml::from_str::<toml::Table>(toml_source).map_err(|err| err.to_string())?;
+    let mut keymap = KeyMap::default();
+    match keymap.merge(&table).first() {
+        Some(err) => Err(err.to_string()),
+        None => Ok(keymap),
+    }
+}
+
+#[test]
+fn test_user_keymap_merges_over_defaults() {
+    let keymap = keymap_from(
+        r#"
+        [normal]
+        "<C-s>" = "SaveBufferAs(out.txt)"
+        "n" = "MoveCursor(Down)"
+        "x" = "Unbind"
+        "<Space>d" = "ApplyOperator(Delete, WordStart(Word))"
+
+        [insert]
+        "<C-a>" = "Home"
+        "<C-e>" = "EOL"
+        "<C-b>" = "MoveCursor(Left)"
+
+        [root_menu]
+        "x" = "ExitEditor"
+        "w" = "Unbind"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        keymap
+            .menu_hints(&SubMenu::Root)
+            .iter()
+            .map(|hint| hint.key())
+            .collect::<Vec<_>>(),
+        ["b", "m", "n", "q", "t", "z", "x"]
+    );
+
+    let settings = Settings {
+        keymap,
+        ..Settings::default()
+    };
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    type_lines(&mut editor, &["one two", "three"]);
+    press_keys(&mut editor, "ggn");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "x");
+    assert_eq!(editor.current_buffer().lines(), ["one two", "three"]);
+    press_keys(&mut editor, "k d");
+    assert_eq!(editor.current_buffer().lines(), ["two", "three"]);
+
+    press_keys(&mut editor, "A");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('a'), KeyModifiers::CONTROL));
+    press_keys(&mut editor, ">");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL));
+    editor.handle_key_press(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL));
+    press_keys(&mut editor, "<\x1b");
+    assert_eq!(editor.current_buffer().lines(), [">tw<o", "three"]);
+
+    // Without modifiers, `s` is still unbound
+    press_keys(&mut editor, "s");
+    assert!(editor.current_buffer().read_name().is_none());
+}
+
+#[test]
+fn test_incomplete_insert_mappings_insert_their_keys() {
+    let keymap = keymap_from("[insert]\n\"jk\" = \"ExitInsert\"").unwrap();
+    let settings = Settings {
+        keymap,
+        ..Settings::default()
+    };
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    press_keys(&mut editor, "ijx");
+    assert_eq!(editor.current_buffer().lines(), ["jx"]);
+    press_keys(&mut editor, "j");
+    editor.handle_key_timeout();
+    assert_eq!(editor.current_buffer().lines(), ["jxj"]);
+    press_keys(&mut editor, "jk");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_buffer().lines(), ["jxj"]);
+}
+
+#[test]
+fn test_user_keymap_errors() {
+    let cases = [
+        ("[normal]\n\"<C-q\" = \"ExitEditor\"", "missing `>`"),
+        (
+            "[normal]\n\"<Hyper-q>\" = \"ExitEditor\"",
+            "unknown modifier `Hyper`",
+        ),
+        (
+            "[normal]\n\"<Foo>\" = \"ExitEditor\"",
+            "unknown key `<Foo>`",
+        ),
+        ("[normal]\n\"\" = \"ExitEditor\"", "empty key sequence"),
+        ("[normal]\n\"q\" = \"Explode\"", "unknown action `Explode`"),
+        ("[normal]\n\"q\" = \"Paste\"", "wrong arguments for `Paste`"),
+        (
+            "[normal]\n\"q\" = \"ShowMessages(Forward)\"",
+            "wrong arguments for `ShowMessages`",
+        ),
+        ("[normal]\n\"q\" = \"Paste(Sideways)\"", "found `Sideways`"),
+        (
+            "[normal]\n\"q\" = 3",
+            "expected an action name, found integer",
+        ),
+        ("[root_menu]\n\"ab\" = \"ExitEditor\"", "single key"),
+        (
+            "[nromal]\n\"q\" = \"ExitEditor\"",
+            "unknown section [nromal]",
+        ),
+        ("normal = 1", "[normal] must be a table"),
+    ];
+    for (source, expected) in cases {
+        let err = keymap_from(source).err().unwrap();
+        assert!(
+            err.contains(expected),
+            "{err:?} should mention {expected:?}"
+        );
+    }
+}
+
+fn settings_from(toml_sources: &[&str]) -> Result<Settings, String> {
+    let mut settings = Settings::default();
+    for source in toml_sources {
+        let table = toml::from_str::<toml::Table>(source).unwrap();
+        if let Some(err) = settings.merge(&table).first() {
+            return Err(err.to_string());
+        }
+    }
+    Ok(settings)
+}
+
+#[test]
+fn test_settings_files_override_each_other() {
+    let settings = settings_from(&[
+        "tab_width = 2\nline_numbers = \"absolute\"\nscrolloff = 3",
+        "tab_width = 8",
+    ])
+    .unwrap();
+    assert_eq!(settings.shiftwidth, 8);
+    assert_eq!(settings.window.line_numbers, LineNumberType::Absolute);
+    assert_eq!(settings.window.scrolloff, 3);
+
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    type_lines(&mut editor, &["one", "two"]);
+    press_keys(&mut editor, ">>");
+    assert_eq!(editor.current_buffer().lines(), ["one", "        two"]);
+    press_keys(&mut editor, "<<");
+    assert_eq!(editor.current_buffer().lines(), ["one", "two"]);
+}
+
+#[test]
+fn test_invalid_settings_only_cost_themselves() {
+    let dir = std::env::temp_dir().join("minimodal_test_partial_settings");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (user, project, broken, keymap) = (
+        dir.join("user.toml"),
+        dir.join("project.toml"),
+        dir.join("broken.toml"),
+        dir.join("keymap.toml"),
+    );
+    std::fs::write(&user, "tab_width = 2\nwrap = true").unwrap();
+    std::fs::write(&project, "scrolloff = 3\nwrap = \"yes\"\nbogus = 1").unwrap();
+    std::fs::write(&broken, "backup = ").unwrap();
+    std::fs::write(
+        &keymap,
+        "[normal]\n\"Q\" = \"ExitEditor\"\n\"q\" = \"Explode\"",
+    )
+    .unwrap();
+    let (settings, errors) = Settings::load_from(
+        &[user, project.clone(), broken.clone()],
+        Some("missing"),
+        Some(&keymap),
+    );
+    assert_eq!(settings.shiftwidth, 2);
+    assert!(settings.window.wrap);
+    assert_eq!(settings.window.scrolloff, 3);
+    assert_eq!(
+        settings.theme.text_background,
+        Theme::default().text_background
+    );
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    press_keys(&mut editor, "Q");
+    assert!(!editor.is_active());
+
+    let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(errors.len(), 5);
+    assert!(errors[0].starts_with(&format!("{}: \"bogus\"", project.display())));
+    assert!(errors[1].starts_with(&format!("{}: \"wrap\"", project.display())));
+    assert!(errors[2].starts_with(&broken.display().to_string()));
+    assert_eq!(errors[3], "--theme: unknown theme \"missing\"");
+    assert!(errors[4].starts_with(&format!("{}: ", keymap.display())));
+    assert!(errors[4].contains("Explode"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_settings_errors() {
+    for (source, message) in [
+        ("tabwidth = 2", "\"tabwidth\": unknown setting"),
+        ("tab_width = 0", "\"tab_width\": must be between 1 and 16"),
+        (
+            "tab_width = \"4\"",
+            "\"tab_width\": expected a number, found string",
+        ),
+        ("scrolloff = -1", "\"scrolloff\": must not be negative"),
+        ("line_numbers = \"numbered\"", "unknown style \"numbered\""),
+        ("theme = \"nope\"", "\"theme\": unknown theme \"nope\""),
+    ] {
+        let err = settings_from(&[source]).err().unwrap();
+        assert!(err.contains(message), "{source}: {err}");
+    }
+}
+
+#[test]
+fn test_scrolloff_keeps_lines_around_cursor() {
+    let settings = settings_from(&["scrolloff = 2"]).unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let lines: Vec<String> = (0..30).map(|i| i.to_string()).collect();
+    type_lines(
+        &mut editor,
+        &lines.iter().map(String::as_str).collect::<Vec<_>>(),
+    );
+    editor.current_winstate_mut().last_height = 10;
+    press_keys(&mut editor, "gg");
+    assert_eq!(editor.current_winstate_mut().top_line, 0);
+    press_keys(&mut editor, "7j");
+    assert_eq!(editor.current_winstate_mut().top_line, 0);
+    press_keys(&mut editor, "j");
+    assert_eq!(editor.current_winstate_mut().top_line, 1);
+    press_keys(&mut editor, "G");
+    assert_eq!(editor.current_winstate_mut().top_line, 20);
+    press_keys(&mut editor, "7k");
+    assert_eq!(editor.current_winstate_mut().top_line, 20);
+    press_keys(&mut editor, "k");
+    assert_eq!(editor.current_winstate_mut().top_line, 19);
+}
+
+fn theme_from(toml_source: &str) -> Result<Theme, String> {
+    let table = toml::from_str::<toml::Table>(toml_source).unwrap();
+    Theme::from_table("test", &table).map_err(|err| err.to_string())
+}
+
+#[test]
+fn test_builtin_themes() {
+    for name in available_themes() {
+        assert!(Theme::named(&name).is_ok(), "{name}");
+    }
+    let light = Theme::named("light").unwrap();
+    assert_eq!(light.text_background, Color::Rgb(0xfa, 0xfa, 0xf5));
+    assert_eq!(
+        light.styles.regular_text,
+        Style::default()
+            .fg(light.text_foreground)
+            .bg(light.text_background)
+    );
+    let ansi16 = Theme::named("ansi16").unwrap();
+    assert_eq!(ansi16.text_background, Color::Reset);
+    assert_eq!(ansi16.line_numbers_normal_foreground, Color::DarkGray);
+    assert_eq!(
+        Theme::named("solarized").err().unwrap().to_string(),
+        "unknown theme \"solarized\""
+    );
+}
+
+#[test]
+fn test_theme_files() {
+    let theme = theme_from(
+        r##"
+        base = "light"
+
+        [colors]
+        text_foreground = "#102030"
+        menu_border = 244
+
+        [styles]
+        selected_text = { fg = "text_foreground", bg = "yellow", modifiers = ["bold", "italic"] }
+        "##,
+    )
+    .unwrap();
+    let light = Theme::named("light").unwrap();
+    assert_eq!(theme.text_background, light.text_background);
+    assert_eq!(theme.text_foreground, Color::Rgb(0x10, 0x20, 0x30));
+    assert_eq!(theme.menu_border, Color::Indexed(244));
+    assert_eq!(theme.styles.regular_text.fg, Some(theme.text_foreground));
+    assert_eq!(
+        theme.styles.selected_text,
+        Style::default()
+            .fg(theme.text_foreground)
+            .bg(Color::Yellow)
+            .add_modifier(Modifier::BOLD | Modifier::ITALIC)
+    );
+
+    for (source, message) in [
+        ("[colors]\ntext = \"red\"", "unknown color \"text\""),
+        (
+            "[colors]\ntext_foreground = \"redish\"",
+            "\"redish\" is not a color",
+        ),
+        (
+            "[colors]\ntext_foreground = 300",
+            "300 is not between 0 and 255",
+        ),
+        (
+            "[styles]\nregular_text = { modifiers = [\"loud\"] }",
+            "unknown modifier",
+        ),
+        (
+            "[styles]\nregular = { fg = \"red\" }",
+            "unknown style \"regular\"",
+        ),
+        ("[palette]", "unknown section [palette]"),
+        ("base = \"nope\"", "base must name a built-in theme"),
+    ] {
+        let err = theme_from(source).err().unwrap();
+        assert!(err.contains(message), "{source}: {err}");
+    }
+}
+
+#[test]
+fn test_theme_menu_switches_theme() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    press_keys(&mut editor, " t");
+    assert_eq!(editor.menu_hints(&SubMenu::Themes)[1].key(), "2");
+    press_keys(&mut editor, "2");
+    assert_eq!(
+        editor.theme().text_background,
+        Theme::named("light").unwrap().text_background
+    );
+    press_keys(&mut editor, "\x1b");
+    press_keys(&mut editor, "ix");
+    assert_eq!(editor.current_buffer().lines(), ["x"]);
+}
+
+#[test]
+fn test_incremental_search() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one two", "three two", "Two four"]);
+    press_keys(&mut editor, "gg/tw");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    assert_eq!(editor.search_count(), Some((1, 3)));
+    press_keys(&mut editor, "o f");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+    press_keys(&mut editor, "\x08\x08");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    press_keys(&mut editor, "\x1b");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    assert_eq!(editor.search_count(), None);
+
+    press_keys(&mut editor, "/two\n");
+    assert_eq!(editor.get_mode(), &Mode::Normal);
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    press_keys(&mut editor, "n");
+    assert_eq!(editor.current_bufpos(), pos(1, 6));
+    assert_eq!(editor.search_count(), Some((2, 3)));
+    press_keys(&mut editor, "2n");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    press_keys(&mut editor, "N");
+    assert_eq!(editor.current_bufpos(), pos(2, 0));
+
+    // `?` searches backwards, and `n` keeps going that way
+    press_keys(&mut editor, "?three\n");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "/\n");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "?\nN");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+
+    type_command(&mut editor, "noh");
+    assert_eq!(editor.search_count(), None);
+    press_keys(&mut editor, "/missing\n");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Pattern not found: missing")
+    );
+}
+
+#[test]
+fn test_search_count_follows_edits() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["éa éa", "a"]);
+    press_keys(&mut editor, "gg/a\n");
+    assert_eq!(editor.current_bufpos(), pos(0, 1));
+    assert_eq!(editor.search_count(), Some((1, 3)));
+    press_keys(&mut editor, "n");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+
+    // The matches are looked up again once the text changes
+    press_keys(&mut editor, "Oa\x1b");
+    assert_eq!(editor.search_count(), Some((1, 4)));
+    press_keys(&mut editor, "ddG");
+    assert_eq!(editor.search_count(), Some((3, 3)));
+}
+
+#[test]
+fn test_search_smart_case_and_regex() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["Foo foo", "x = 42;", "fooBar"]);
+    press_keys(&mut editor, "gg/foo\n");
+    assert_eq!(editor.current_bufpos(), pos(0, 4));
+    assert_eq!(editor.search_count(), Some((2, 3)));
+    press_keys(&mut editor, "/Foo\n");
+    assert_eq!(editor.search_count(), Some((1, 1)));
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "/\\d+;\n");
+    assert_eq!(editor.current_bufpos(), pos(1, 4));
+    press_keys(&mut editor, "/fo(\n");
+    assert_eq!(editor.current_bufpos(), pos(1, 4));
+    assert!(editor
+        .command_line()
+        .error()
+        .is_some_and(|err| err.starts_with("Invalid pattern")));
+}
+
+#[test]
+fn test_search_matches_are_highlighted() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["abc abc", "xabc"]);
+    let mut terminal = Terminal::new(TestBackend::new(30, 8)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    press_keys(&mut editor, "gg/abc\n");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let match_bg = editor.theme().search_match_background;
+    let text_start = 4;
+    let highlighted: Vec<Vec<bool>> = (1..3)
+        .map(|y| {
+            (text_start..text_start + 8)
+                .map(|x| terminal.backend().buffer()[(x, y)].bg == match_bg)
+                .collect()
+        })
+        .collect();
+    let expected = |cols: &str| cols.chars().map(|c| c == '^').collect::<Vec<_>>();
+    assert_eq!(highlighted[0], expected("^^^ ^^^ "));
+    assert_eq!(highlighted[1], expected(" ^^^    "));
+
+    // The selection is drawn over the matches
+    press_keys(&mut editor, "vj");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let selection_bg = editor.theme().selected_text_background;
+    let row = |y: u16| -> String {
+        (text_start..text_start + 8)
+            .map(|x| {
+                let bg = terminal.backend().buffer()[(x, y)].bg;
+                match bg {
+                    _ if bg == match_bg => '^',
+                    _ if bg == selection_bg => '#',
+                    _ => ' ',
+                }
+            })
+            .collect()
+    };
+    assert_eq!(row(1), "^^^ ####");
+    assert_eq!(row(2), "####    ");
+}
+
+#[test]
+fn test_substitute_ranges_and_flags() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["foo foo", "foo bar", "bar foo", "foo"]);
+    press_keys(&mut editor, "gg");
+    type_command(&mut editor, "s/foo/x/");
+    assert_eq!(editor.current_buffer().line(0), "x foo");
+    type_command(&mut editor, "2,3s/foo/y/g");
+    assert_eq!(editor.current_buffer().line(1), "y bar");
+    assert_eq!(editor.current_buffer().line(2), "bar y");
+    assert_eq!(editor.current_buffer().line(3), "foo");
+    type_command(&mut editor, "%s#FOO#z#gi");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["x z", "y bar", "bar y", "z"]
+    );
+    assert_eq!(editor.current_bufpos(), pos(3, 0));
+
+    type_command(&mut editor, ".-2,$-2s/bar/q/");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["x z", "y q", "bar y", "z"]
+    );
+    type_command(&mut editor, "9s/x/y/");
+    assert_eq!(editor.command_line().error(), Some("Invalid range"));
+    type_command(&mut editor, "%s/nothing/y/");
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Pattern not found: nothing")
+    );
+    type_command(&mut editor, "%tabn");
+    assert_eq!(editor.command_line().error(), Some("No range allowed"));
+}
+
+#[test]
+fn test_substitute_capture_groups_and_undo() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let lines = ["key = value", "a = b", "a/b"];
+    type_lines(&mut editor, &lines);
+    type_command(&mut editor, r"%s/(\w+) = (\w+)/\2: \1 [&]/");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["value: key [key = value]", "b: a [a = b]", "a/b"]
+    );
+    type_command(&mut editor, r"3s/\//\r$/");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["value: key [key = value]", "b: a [a = b]", "a", "$b"]
+    );
+    press_keys(&mut editor, "u");
+    assert_eq!(
+        editor.current_buffer().lines(),
+        ["value: key [key = value]", "b: a [a = b]", "a/b"]
+    );
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines(), lines);
+}
+
+#[test]
+fn test_substitute_with_confirmation() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["a a", "a a", "a"]);
+    type_command(&mut editor, "%s/a/b/gc");
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    assert_eq!(
+        editor.command_line().message(),
+        Some("replace with b (y/n/a/q)?")
+    );
+    press_keys(&mut editor, "yn");
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "y");
+    press_keys(&mut editor, "q");
+    assert_eq!(editor.command_line().message(), None);
+    assert_eq!(editor.current_buffer().lines(), ["b a", "b a", "a"]);
+    assert_eq!(editor.current_bufpos(), pos(1, 0));
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines(), ["a a", "a a", "a"]);
+
+    type_command(&mut editor, "%s//c/c");
+    press_keys(&mut editor, "na");
+    assert_eq!(editor.current_buffer().lines(), ["a a", "c a", "c"]);
+    type_command(&mut editor, "%s/a/d/c");
+    press_keys(&mut editor, "\x1b");
+    assert_eq!(editor.current_buffer().lines(), ["a a", "c a", "c"]);
+}
+
+fn ctrl_w(editor: &mut Editor, keys: &str) {
+    for c in keys.chars() {
+        editor.handle_key_press(KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL));
+        editor.handle_key_press(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+    }
+}
+
+#[test]
+fn test_window_splits_have_their_own_cursor() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["one", "two", "three", "four"]);
+    press_keys(&mut editor, "gg");
+    ctrl_w(&mut editor, "s");
+    assert_eq!(editor.window_count(), 2);
+    assert_eq!(editor.current_window_index(), 1);
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    press_keys(&mut editor, "jjl");
+    ctrl_w(&mut editor, "w");
+    assert_eq!(editor.current_window_index(), 0);
+    assert_eq!(editor.current_bufpos(), pos(0, 0));
+    ctrl_w(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(2, 1));
+
+    // Lines added or removed above the cursor of the other window keep it
+    // on the same line of text
+    ctrl_w(&mut editor, "W");
+    press_keys(&mut editor, "Ozero\x1b");
+    ctrl_w(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(3, 1));
+    ctrl_w(&mut editor, "w");
+    press_keys(&mut editor, "ddddu");
+    ctrl_w(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(2, 1));
+    assert_eq!(editor.current_buffer().line(2), "three");
+    press_keys(&mut editor, "G");
+    ctrl_w(&mut editor, "w");
+    press_keys(&mut editor, "Gdd");
+    ctrl_w(&mut editor, "w");
+    assert_eq!(editor.current_bufpos(), pos(2, 1));
+
+    type_command(&mut editor, "close");
+    assert_eq!(editor.window_count(), 1);
+    type_command(&mut editor, "close");
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Cannot close last window")
+    );
+    type_command(&mut editor, "vsplit");
+    type_command(&mut editor, "split");
+    assert_eq!(editor.window_count(), 3);
+    type_command(&mut editor, "only");
+    assert_eq!(editor.window_count(), 1);
+}
+
+#[test]
+fn test_window_layout_focus_and_resize() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["text"]);
+    let mut terminal = Terminal::new(TestBackend::new(41, 23)).unwrap();
+    let mut draw = |editor: &mut Editor| {
+        terminal.draw(|frame| editor.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    };
+    // Window 1 on the left, window 0 split into 2 at the top right and 0
+    // at the bottom right
+    ctrl_w(&mut editor, "v");
+    draw(&mut editor);
+    ctrl_w(&mut editor, "ls");
+    let screen = draw(&mut editor);
+    assert_eq!(screen[(20, 5)].symbol(), "│");
+    assert_eq!(screen[(30, 11)].symbol(), "─");
+    assert_eq!(editor.current_window_index(), 2);
+    ctrl_w(&mut editor, "j");
+    assert_eq!(editor.current_window_index(), 0);
+    ctrl_w(&mut editor, "hl");
+    assert_eq!(editor.current_window_index(), 2);
+    ctrl_w(&mut editor, "k");
+    assert_eq!(editor.current_window_index(), 2);
+
+    ctrl_w(&mut editor, "+++");
+    type_command(&mut editor, "vertical resize -5");
+    let screen = draw(&mut editor);
+    assert_eq!(screen[(30, 14)].symbol(), "─");
+    assert_eq!(screen[(25, 5)].symbol(), "│");
+    ctrl_w(&mut editor, "c");
+    let screen = draw(&mut editor);
+    assert_ne!(screen[(30, 14)].symbol(), "─");
+}
+
+#[test]
+fn test_resizing_windows_split_before_a_draw() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let mut terminal = Terminal::new(TestBackend::new(40, 20)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    // Splits are sized before they are drawn, to resize them right away
+    ctrl_w(&mut editor, "sss-");
+    assert_eq!(editor.window_count(), 4);
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    ctrl_w(&mut editor, "-+");
+}
+
+#[test]
+fn test_splitting_until_there_is_no_room() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let mut terminal = Terminal::new(TestBackend::new(40, 10)).unwrap();
+    type_lines(&mut editor, &["one", "two", "three"]);
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    ctrl_w(&mut editor, "ssssssss");
+    let count = editor.window_count();
+    assert!(count < 9);
+    assert_eq!(editor.command_line().error(), Some("Not enough room"));
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    press_keys(&mut editor, "kkjgg");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+
+    // The same goes for windows side by side
+    ctrl_w(&mut editor, &"v".repeat(20));
+    assert!((count + 1..count + 20).contains(&editor.window_count()));
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    press_keys(&mut editor, "kjl");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+}
+
+#[test]
+fn test_opening_a_file_under_another_spelling() {
+    let dir = std::env::temp_dir().join("minimodal_test_spellings");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "text\n").unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_command(&mut editor, &format!("e {}", path.display()));
+    press_keys(&mut editor, "x");
+    type_command(&mut editor, &format!("e {}/./a.txt", dir.display()));
+    type_command(
+        &mut editor,
+        &format!(
+            "e {}/../{}/a.txt",
+            dir.display(),
+            dir.file_name().unwrap().to_string_lossy()
+        ),
+    );
+    #[cfg(unix)]
+    {
+        let link = dir.join("link.txt");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        type_command(&mut editor, &format!("e {}", link.display()));
+    }
+    assert_eq!(editor.buffer_count(), 2);
+    assert_eq!(editor.current_buffer().lines(), ["ext"]);
+
+    // Files that don't exist yet are told apart by their absolute path
+    let new = dir.join("new.txt");
+    type_command(&mut editor, &format!("e {}", new.display()));
+    type_command(&mut editor, &format!("e {}/./new.txt", dir.display()));
+    assert_eq!(editor.buffer_count(), 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_buffer_list_commands() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let name = |editor: &Editor| editor.current_buffer().read_name().map(ToOwned::to_owned);
+    type_lines(&mut editor, &["scratch"]);
+    type_command(&mut editor, "e newdir/one.txt");
+    type_command(&mut editor, "e newdir/two.txt");
+    assert_eq!(editor.tab_count(), 1);
+    assert_eq!(editor.buffer_count(), 3);
+    assert_eq!(name(&editor), Some(OsString::from("two.txt")));
+
+    // The hidden buffers keep their content
+    type_command(&mut editor, "bn");
+    assert_eq!(name(&editor), None);
+    assert_eq!(editor.current_buffer().line(0), "scratch");
+    type_command(&mut editor, "bp");
+    assert_eq!(name(&editor), Some(OsString::from("two.txt")));
+    type_command(&mut editor, "b 2");
+    assert_eq!(name(&editor), Some(OsString::from("one.txt")));
+    // Opening a file again reuses its buffer
+    type_command(&mut editor, "e newdir/two.txt");
+    assert_eq!(editor.buffer_count(), 3);
+    type_command(&mut editor, "b 4");
+    assert_eq!(
+        editor.command_line().error(),
+        Some("Buffer 4 does not exist")
+    );
+
+    type_command(&mut editor, "bd");
+    assert_eq!(editor.buffer_count(), 2);
+    assert_eq!(name(&editor), Some(OsString::from("one.txt")));
+    type_command(&mut editor, "bd");
+    assert_eq!(name(&editor), None);
+    // The changes to the scratch buffer are discarded
+    type_command(&mut editor, "bd");
+    assert!(editor.has_floating_window());
+    press_keys(&mut editor, "d");
+    assert_eq!(editor.buffer_count(), 1);
+    assert_eq!(name(&editor), None);
+    assert_eq!(editor.current_buffer().line(0), "");
+}
+
+#[test]
+fn test_tab_close_and_buffer_picker() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_command(&mut editor, "tabclose");
+    assert_eq!(editor.command_line().error(), Some("Cannot close last tab"));
+    type_command(&mut editor, "tabnew");
+    type_command(&mut editor, "tabe newdir/three.txt");
+    assert_eq!(editor.tab_count(), 3);
+    assert_eq!(editor.buffer_count(), 3);
+    type_command(&mut editor, "tabp");
+    type_command(&mut editor, "tabc");
+    assert_eq!(editor.tab_count(), 2);
+    assert_eq!(editor.current_tab_index(), 1);
+    // The buffer of the closed tab is still in the list
+    assert_eq!(editor.buffer_count(), 3);
+    type_command(&mut editor, "tabn");
+    assert_eq!(editor.current_tab_index(), 0);
+
+    press_keys(&mut editor, " b");
+    assert!(editor.has_floating_window());
+    press_keys(&mut editor, "k\n");
+    assert!(!editor.has_floating_window());
+    assert_eq!(
+        editor.current_buffer().read_name(),
+        Some(OsString::from("three.txt").as_os_str())
+    );
+}
+
+#[test]
+fn test_quitting_with_unsaved_changes() {
+    let path = std::env::temp_dir().join("minimodal_test_unsaved.txt");
+    let mut editor = Editor::new(vec![Buffer::open(&path).unwrap()], Settings::default());
+    assert!(!editor.current_buffer().is_modified());
+    type_lines(&mut editor, &["draft"]);
+    assert!(editor.current_buffer().is_modified());
+    press_keys(&mut editor, "u");
+    // Undoing back to the text of the file leaves nothing to save
+    assert!(!editor.current_buffer().is_modified());
+    let redo = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+    editor.handle_key_press(redo);
+    assert!(editor.current_buffer().is_modified());
+
+    type_command(&mut editor, "q");
+    assert!(editor.is_active());
+    assert!(editor.has_floating_window());
+    press_keys(&mut editor, "\x1b");
+    assert!(!editor.has_floating_window());
+    assert!(editor.is_active());
+
+    type_command(&mut editor, "q");
+    press_keys(&mut editor, "s");
+    assert!(!editor.is_active());
+    assert!(!editor.current_buffer().is_modified());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "draft\n");
+    press_keys(&mut editor, "u");
+    assert!(editor.current_buffer().is_modified());
+    editor.handle_key_press(redo);
+    assert!(!editor.current_buffer().is_modified());
+    std::fs::remove_file(&path).unwrap();
+
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["scratch"]);
+    type_command(&mut editor, "q");
+    press_keys(&mut editor, "d");
+    assert!(!editor.is_active());
+
+    // Naming an untitled buffer to save it goes on with quitting
+    let path = std::env::temp_dir().join("minimodal_test_unsaved_untitled.txt");
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["scratch"]);
+    type_command(&mut editor, "q");
+    press_keys(&mut editor, "s");
+    assert!(editor.is_active());
+    press_keys(&mut editor, &format!("{}\n", path.display()));
+    assert!(!editor.is_active());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "scratch\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_failed_save_is_reported() {
+    let dir = std::env::temp_dir().join("minimodal_test_missing_dir");
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["keep me"]);
+    type_command(
+        &mut editor,
+        &format!("w {}", dir.join("file.txt").display()),
+    );
+    assert!(editor.is_active());
+    assert!(editor.current_buffer().is_modified());
+    let error = editor.command_line().error().unwrap();
+    assert!(error.starts_with("Could not save file.txt: "));
+    // Quitting after a failed save keeps the editor open
+    type_command(&mut editor, "wq");
+    assert!(editor.is_active());
+
+    let path = std::env::temp_dir().join("minimodal_test_notified.txt");
+    type_command(&mut editor, &format!("w {}", path.display()));
+    let notification = editor.command_line().notification().unwrap();
+    assert_eq!(notification.severity, Severity::Info);
+    assert_eq!(
+        notification.message,
+        "\"minimodal_test_notified.txt\" 1L written"
+    );
+    std::fs::remove_file(&path).unwrap();
+    press_keys(&mut editor, "l");
+    assert_eq!(editor.command_line().notification(), None);
+
+    type_command(&mut editor, "messages");
+    assert!(editor.has_floating_window());
+    press_keys(&mut editor, "\x1b");
+    assert!(!editor.has_floating_window());
+}
+
+#[test]
+fn test_crash_dump_writes_modified_buffers() {
+    let dir = std::env::temp_dir().join("minimodal_test_recovery");
+    let mut editor = Editor::new(
+        vec![Buffer::untitled(), Buffer::untitled()],
+        Settings::default(),
+    );
+    type_lines(&mut editor, &["unsaved", "text"]);
+    let track = |editor: &Editor| -> Vec<TrackedBuffer> {
+        editor
+            .buffer_handles()
+            .into_iter()
+            .map(TrackedBuffer::new)
+            .collect()
+    };
+    let dumped = crash::dump_buffers(&track(&editor), &dir);
+    assert_eq!(dumped.len(), 1);
+    let (name, written) = &dumped[0];
+    assert_eq!(name, "Untitled");
+    let path = written.as_ref().unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "unsaved\ntext\n");
+
+    // A buffer still borrowed by an edit is written as it was before it
+    let tracked = track(&editor);
+    press_keys(&mut editor, "ddu");
+    let buffer = editor.current_buffer_mut();
+    let dumped = crash::dump_buffers(&tracked, &dir);
+    assert_eq!(dumped[0].0, "Untitled (before the last key)");
+    let path = dumped[0].1.as_ref().unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "unsaved\ntext\n");
+    let handles = editor.buffer_handles();
+    let untracked: Vec<TrackedBuffer> = handles.into_iter().map(TrackedBuffer::new).collect();
+    let dumped = crash::dump_buffers(&untracked, &dir);
+    assert_eq!(dumped[0].0, "buffer 1");
+    assert!(dumped[0].1.is_err());
+    drop(buffer);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_clean_expect_failures_leave_a_recovery_file() {
+    let dir = std::env::temp_dir().join("minimodal_test_clean_expect");
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    type_lines(&mut editor, &["unsaved"]);
+    crash::track_buffers(editor.buffer_handles());
+    crash::install_panic_hook(dir.clone());
+    let failed = std::panic::catch_unwind(|| None::<()>.clean_expect("dead buffer"));
+    // Back to the default hook for the other tests
+    let _ = std::panic::take_hook();
+    crash::track_buffers(vec![]);
+    assert!(failed.is_err());
+    let written: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(written.len(), 1);
+    let path = written[0].as_ref().unwrap().path();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), "unsaved\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_swap_files_follow_unsaved_changes() {
+    let dir = std::env::temp_dir().join("minimodal_test_swap_files");
+    let path = std::env::temp_dir().join("minimodal_test_swapped.txt");
+    let mut editor = Editor::new(vec![Buffer::open(&path).unwrap()], Settings::default());
+    editor.set_swap_files(SwapFiles::new(dir.clone(), Duration::ZERO));
+    let swap = swap_path(&dir, &path);
+
+    press_keys(&mut editor, "itwo\x1b");
+    editor.update_swap_files();
+    assert_eq!(std::fs::read_to_string(&swap).unwrap(), "two\n");
+    press_keys(&mut editor, "Oone\x1b");
+    editor.update_swap_files();
+    assert_eq!(std::fs::read_to_string(&swap).unwrap(), "one\ntwo\n");
+
+    // Saving makes the swap file useless
+    type_command(&mut editor, "w");
+    editor.update_swap_files();
+    assert!(!swap.exists());
+    std::fs::remove_file(&path).unwrap();
+
+    // Buffers without a file get one of their own, until they are saved
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    editor.set_swap_files(SwapFiles::new(dir.clone(), Duration::ZERO));
+    let swap = untitled_swap_path(&dir, editor.current_buffer().id());
+    press_keys(&mut editor, "inew\x1b");
+    editor.update_swap_files();
+    assert_eq!(std::fs::read_to_string(&swap).unwrap(), "new\n");
+    type_command(&mut editor, &format!("w {}", path.display()));
+    editor.update_swap_files();
+    assert!(!swap.exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_recovering_a_swap_file() {
+    let dir = std::env::temp_dir().join("minimodal_test_recovery_dialog");
+    let path = std::env::temp_dir().join("minimodal_test_recovered.txt");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&path, "first\nsecond\n").unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    let an_hour_ago = std::time::SystemTime::now() - Duration::from_secs(3600);
+    file.set_modified(an_hour_ago).unwrap();
+    let swap = swap_path(&dir, &path);
+    std::fs::write(&swap, "first\nchanged\n").unwrap();
+
+    let mut editor = Editor::new(vec![Buffer::open(&path).unwrap()], Settings::default());
+    editor.set_swap_files(SwapFiles::new(dir.clone(), Duration::ZERO));
+    editor.check_swap_files();
+    assert!(editor.has_floating_window());
+    // Showing the diff keeps the dialog open
+    press_keys(&mut editor, "d");
+    assert!(editor.has_floating_window());
+    press_keys(&mut editor, "r");
+    assert!(!editor.has_floating_window());
+    assert_eq!(editor.current_buffer().lines(), ["first", "changed"]);
+    assert!(editor.current_buffer().is_modified());
+    press_keys(&mut editor, "u");
+    assert_eq!(editor.current_buffer().lines(), ["first", "second"]);
+
+    // Deleting the swap file leaves the file as it is
+    let mut editor = Editor::new(vec![Buffer::open(&path).unwrap()], Settings::default());
+    editor.set_swap_files(SwapFiles::new(dir.clone(), Duration::ZERO));
+    editor.check_swap_files();
+    press_keys(&mut editor, "x");
+    assert!(!swap.exists());
+    assert_eq!(editor.current_buffer().lines(), ["first", "second"]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_keeps_permissions_symlinks_and_backup() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    let dir = std::env::temp_dir().join("minimodal_test_atomic_save");
+    std::fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("script.sh");
+    let link = dir.join("link.sh");
+    std::fs::write(&target, "echo old\n").unwrap();
+    std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o750)).unwrap();
+    let _ = std::fs::remove_file(&link);
+    symlink("script.sh", &link).unwrap();
+
+    let mut buffer = Buffer::open(&link).unwrap();
+    buffer.clear_line(&BufferPosition { line: 0, col: 0 });
+    buffer.insert_text(&BufferPosition { line: 0, col: 0 }, "echo new");
+    buffer.save(true).unwrap();
+    assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+    assert_eq!(std::fs::read_to_string(&target).unwrap(), "echo new\n");
+    let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("script.sh~")).unwrap(),
+        "echo old\n"
+    );
+    // No temporary file is left behind
+    let mut names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["link.sh", "script.sh", "script.sh~"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_save_in_place_keeps_hard_links_and_read_only_directories() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join("minimodal_test_save_in_place");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("file.txt");
+    let link = dir.join("hard_link.txt");
+    std::fs::write(&path, "old\n").unwrap();
+    std::fs::hard_link(&path, &link).unwrap();
+
+    let mut buffer = Buffer::open(&path).unwrap();
+    buffer.insert_text(&BufferPosition { line: 0, col: 0 }, "new ");
+    buffer.save(false).unwrap();
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "new old\n");
+
+    // A directory that new files cannot be made in
+    std::fs::remove_file(&link).unwrap();
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o555)).unwrap();
+    buffer.insert_text(&BufferPosition { line: 0, col: 0 }, "newer ");
+    let saved = buffer.save(false);
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+    saved.unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "newer new old\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_format_round_trip_and_conversion() {
+    let path = std::env::temp_dir().join("minimodal_test_file_format.txt");
+    let contents = "\u{feff}first\r\nsecond\r\nno final newline";
+    std::fs::write(&path, contents).unwrap();
+    let mut buffer = Buffer::open(&path).unwrap();
+    assert_eq!(buffer.lines(), ["first", "second", "no final newline"]);
+    assert_eq!(buffer.format().to_string(), "CRLF BOM noeol");
+    buffer.save(false).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+    let mut editor = Editor::new(vec![buffer], Settings::default());
+    type_command(&mut editor, "ff dos");
+    assert!(!editor.current_buffer().is_modified());
+    type_command(&mut editor, "fileformat unix");
+    assert!(editor.current_buffer().is_modified());
+    type_command(&mut editor, "w");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "\u{feff}first\nsecond\nno final newline"
+    );
+    type_command(&mut editor, "ff mac");
+    assert_eq!(editor.command_line().error(), Some("Invalid argument: mac"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_mixed_line_endings_are_kept() {
+    let path = std::env::temp_dir().join("minimodal_test_mixed_endings.txt");
+    let contents = "dos\r\nunix\ndos again\r\n";
+    std::fs::write(&path, contents).unwrap();
+    let mut buffer = Buffer::open(&path).unwrap();
+    assert_eq!(buffer.format().to_string(), "LF");
+    assert_eq!(buffer.lines(), ["dos\r", "unix", "dos again\r"]);
+    buffer.save(false).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_editing_graphemes_from_a_file() {
+    let path = std::env::temp_dir().join("minimodal_test_graphemes.txt");
+    std::fs::write(&path, "cafe\u{301} 日本語\nx👍🏽y\n").unwrap();
+    let buffer = Buffer::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(buffer.line_length(0), Some(8));
+    assert_eq!(buffer.line_length(1), Some(3));
+    assert_eq!(
+        buffer.display_col(&BufferPosition { line: 0, col: 7 }, 4),
+        9
+    );
+
+    let mut editor = Editor::new(vec![buffer], Settings::default());
+    press_keys(&mut editor, "$");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 7 });
+    press_keys(&mut editor, "03lx");
+    assert_eq!(editor.current_buffer().line(0), "caf 日本語");
+    press_keys(&mut editor, "0f語");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 6 });
+    press_keys(&mut editor, "j0lx");
+    assert_eq!(editor.current_buffer().line(1), "xy");
+
+    // A combining mark joins the character before the cursor
+    press_keys(&mut editor, "0a\u{301}");
+    assert_eq!(editor.current_buffer().line(1), "x\u{301}y");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 1 });
+    press_keys(&mut editor, "\x1b0x");
+    assert_eq!(editor.current_buffer().line(1), "y");
+}
+
+#[test]
+fn test_columns_of_a_long_line_across_rope_chunks() {
+    let path = std::env::temp_dir().join("minimodal_test_long_graphemes.txt");
+    let line = "e\u{301}👍🏽\t".repeat(2000);
+    std::fs::write(&path, format!("{line}\n")).unwrap();
+    let buffer = Buffer::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(buffer.line_length(0), Some(6000));
+    assert_eq!(
+        buffer.char_at(&BufferPosition { line: 0, col: 4500 }),
+        Some('e')
+    );
+    assert_eq!(buffer.line_slice(0, 5998, 5), "👍🏽\t");
+    assert_eq!(
+        buffer.display_col(&BufferPosition { line: 0, col: 3 }, 4),
+        4
+    );
+    assert_eq!(buffer.first_non_blank(0), 0);
+}
+
+#[test]
+fn test_wide_characters_are_drawn_in_their_cells() {
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    let mut terminal = Terminal::new(TestBackend::new(12, 6)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    type_lines(&mut editor, &["日本語日本語日本語", "e\u{301}x"]);
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let text_start = 4;
+    let cell = |terminal: &Terminal<TestBackend>, x: u16, y: u16| {
+        terminal.backend().buffer()[(text_start + x, y)]
+            .symbol()
+            .to_owned()
+    };
+    assert_eq!(cell(&terminal, 0, 2), "e\u{301}");
+    assert_eq!(cell(&terminal, 1, 2), "x");
+    assert_eq!(editor.get_cursor_pos().x, text_start + 1);
+
+    // Scrolling sideways keeps whole characters in view
+    press_keys(&mut editor, "k$");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(cell(&terminal, 0, 1), "語");
+    assert_eq!(cell(&terminal, 6, 1), "語");
+    assert_eq!(editor.get_cursor_pos().x, text_start + 6);
+    press_keys(&mut editor, "0llll");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(cell(&terminal, 0, 1), "本");
+    assert_eq!(editor.get_cursor_pos().x, text_start + 6);
+}
+
+#[test]
+fn test_tabs_are_expanded_and_indentation_follows_settings() {
+    let settings = settings_from(&["tabstop = 8\nshiftwidth = 4\nexpandtab = false"]).unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let mut terminal = Terminal::new(TestBackend::new(30, 6)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    press_keys(&mut editor, "i");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+    press_keys(&mut editor, "ab\x1b");
+    assert_eq!(editor.current_buffer().line(0), "\tab");
+    press_keys(&mut editor, "0l");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 1 });
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let text_start = 4;
+    let row: String = (text_start..text_start + 10)
+        .map(|x| terminal.backend().buffer()[(x, 1)].symbol().to_owned())
+        .collect();
+    assert_eq!(row, "        ab");
+    assert_eq!(editor.get_cursor_pos().x, text_start + 8);
+
+    press_keys(&mut editor, ">>");
+    assert_eq!(editor.current_buffer().line(0), "\t    ab");
+    press_keys(&mut editor, "<<");
+    assert_eq!(editor.current_buffer().line(0), "\tab");
+    press_keys(&mut editor, "<<");
+    assert_eq!(editor.current_buffer().line(0), "    ab");
+
+    let mut editor = Editor::new(vec![Buffer::untitled()], Settings::default());
+    press_keys(&mut editor, "ix");
+    editor.handle_key_press(KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+    press_keys(&mut editor, "y\x1b");
+    assert_eq!(editor.current_buffer().line(0), "x   y");
+}
+
+#[test]
+fn test_soft_wrap_and_display_line_motions() {
+    let settings = settings_from(&[
+        "wrap = true\nlinebreak = true\nshowbreak = \"> \"\nline_numbers = \"absolute\"",
+    ])
+    .unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let mut terminal = Terminal::new(TestBackend::new(16, 7)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    type_lines(&mut editor, &["one two three four five", "x"]);
+    press_keys(&mut editor, "k");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let row = |terminal: &Terminal<TestBackend>, y: u16| -> String {
+        (0..16)
+            .map(|x| terminal.backend().buffer()[(x, y)].symbol().to_owned())
+            .collect()
+    };
+    assert_eq!(row(&terminal, 1), " 1  one two     ");
+    assert_eq!(row(&terminal, 2), "    > three     ");
+    assert_eq!(row(&terminal, 3), "    > four five ");
+    assert_eq!(row(&terminal, 4), " 2  x           ");
+
+    press_keys(&mut editor, "gj");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 8 });
+    press_keys(&mut editor, "lgj");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 15 });
+    assert_eq!(editor.get_cursor_pos(), Position { x: 7, y: 3 });
+    press_keys(&mut editor, "gj");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 1, col: 0 });
+    press_keys(&mut editor, "gk");
+    assert_eq!(editor.current_bufpos(), BufferPosition { line: 0, col: 14 });
+
+    // Turning wrapping off from the leader menu scrolls sideways again
+    press_keys(&mut editor, " z ");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(row(&terminal, 1), " 1   two three f");
+    press_keys(&mut editor, "$");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(row(&terminal, 1), " 1  ee four five");
+}
+
+#[test]
+fn test_wrapped_lines_taller_than_the_window() {
+    let settings = settings_from(&["wrap = true\nline_numbers = \"off\"\nscrolloff = 0"]).unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let mut terminal = Terminal::new(TestBackend::new(16, 5)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let text: String = ('a'..='z').cycle().take(200).collect();
+    type_lines(&mut editor, &[&text, "end"]);
+    press_keys(&mut editor, "gg$");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    let row = |terminal: &Terminal<TestBackend>, y: u16| -> String {
+        (2..16)
+            .map(|x| terminal.backend().buffer()[(x, y)].symbol().to_owned())
+            .collect()
+    };
+    // The last of the 15 rows of the line is shown, with the cursor on it
+    let tail: String = text.chars().skip(196).collect();
+    assert_eq!(row(&terminal, 3), format!("{tail:<14}"));
+    assert_eq!(editor.get_cursor_pos(), Position { x: 5, y: 3 });
+
+    // Moving up a row at a time scrolls a row at a time
+    press_keys(&mut editor, "gkgkgk");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(row(&terminal, 1), text[154..168]);
+    assert_eq!(editor.get_cursor_pos(), Position { x: 5, y: 1 });
+    press_keys(&mut editor, "0");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(row(&terminal, 1), text[..14]);
+    press_keys(&mut editor, "G");
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    assert_eq!(row(&terminal, 3), "end           ");
+}
+
+#[test]
+fn test_line_number_modes() {
+    let settings = settings_from(&["line_numbers = \"relative\""]).unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let mut terminal = Terminal::new(TestBackend::new(12, 5)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    type_lines(&mut editor, &["a", "b", "c"]);
+    press_keys(&mut editor, "k");
+    let rows = |editor: &mut Editor, terminal: &mut Terminal<TestBackend>| -> Vec<String> {
+        terminal.draw(|frame| editor.draw(frame)).unwrap();
+        (1..4)
+            .map(|y| {
+                (0..6)
+                    .map(|x| terminal.backend().buffer()[(x, y)].symbol().to_owned())
+                    .collect()
+            })
+            .collect()
+    };
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1  a ", " 0  b ", " 1  c "]
+    );
+
+    // The leader menu cycles through hybrid, off and absolute numbers
+    press_keys(&mut editor, " n ");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1  a ", " 2  b ", " 1  c "]
+    );
+    press_keys(&mut editor, " n ");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        ["  a   ", "  b   ", "  c   "]
+    );
+    press_keys(&mut editor, " n ");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1  a ", " 2  b ", " 3  c "]
+    );
+
+    // Each window keeps its own mode
+    ctrl_w(&mut editor, "s");
+    press_keys(&mut editor, " n ");
+    ctrl_w(&mut editor, "w");
+    assert_eq!(
+        editor.current_winstate_mut().settings.line_numbers,
+        LineNumberType::Absolute
+    );
+}
+
+#[test]
+fn test_bookmarks_in_the_sign_column() {
+    let settings = settings_from(&["line_numbers = \"absolute\""]).unwrap();
+    let mut editor = Editor::new(vec![Buffer::untitled()], settings);
+    let mut terminal = Terminal::new(TestBackend::new(12, 7)).unwrap();
+    terminal.draw(|frame| editor.draw(frame)).unwrap();
+    type_lines(&mut editor, &["a", "b", "c", "d"]);
+    let rows = |editor: &mut Editor, terminal: &mut Terminal<TestBackend>| -> Vec<String> {
+        terminal.draw(|frame| editor.draw(frame)).unwrap();
+        (1..5)
+            .map(|y| {
+                (0..5)
+                    .map(|x| terminal.backend().buffer()[(x, y)].symbol().to_owned())
+                    .collect()
+            })
+            .collect()
+    };
+    press_keys(&mut editor, "gg m jj m ");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1▶ a", " 2  b", " 3▶ c", " 4  d"]
+    );
+
+    // Bookmarks stay on their lines as lines are added and removed above
+    press_keys(&mut editor, "ggOz\x1bjjdd");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1  z", " 2▶ a", " 3▶ c", " 4  d"]
+    );
+    press_keys(&mut editor, "G]b");
+    assert_eq!(editor.current_bufpos().line, 1);
+    press_keys(&mut editor, "[b");
+    assert_eq!(editor.current_bufpos().line, 2);
+
+    // The sign with the highest priority is drawn
+    let error = Sign::new("E", Style::default(), 100);
+    editor
+        .current_buffer_mut()
+        .signs_mut()
+        .place("errors", 2, error);
+    assert_eq!(rows(&mut editor, &mut terminal)[2], " 3E c");
+    type_command(&mut editor, "delbookmarks");
+    assert_eq!(
+        rows(&mut editor, &mut terminal),
+        [" 1  z", " 2  a", " 3E c", " 4  d"]
+    );
+    press_keys(&mut editor, "]b");
+    assert_eq!(editor.command_line().error(), Some("No bookmarks"));
+}